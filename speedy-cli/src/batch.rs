@@ -0,0 +1,298 @@
+//! Batch mode: every input processed on its own, with the same settings, into
+//! an output directory. With `--merge-chapters`, the chapter files of one
+//! recording are stitched into a single output instead.
+//!
+//! Output names come from a template (see [`render_name`]). Files run a few at
+//! a time, each with its own progress bar; a failed file does not stop the
+//! others, and a summary of every file is printed at the end.
+
+use anyhow::{Result, bail};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use speedy_core::{Clip, JobSpec, VideoProcessor};
+
+/// Default `--name-template`.
+pub const DEFAULT_TEMPLATE: &str = "{stem}_graded.{ext}";
+
+/// One input (or the chapter files of one recording, in order) and the file
+/// it is processed into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchItem {
+    pub inputs: Vec<Clip>,
+    pub output: PathBuf,
+}
+
+impl BatchItem {
+    /// The input, or the first chapter file and how many follow it.
+    fn label(&self) -> String {
+        match self.inputs.as_slice() {
+            [] => String::new(),
+            [input] => input.to_string(),
+            [first, rest @ ..] => format!("{first} (+{n} more)", n = rest.len()),
+        }
+    }
+}
+
+/// Render an output file name for the `index`-th (1-based) input.
+///
+/// Placeholders: `{stem}` (input name without extension), `{ext}` (input
+/// extension, `mp4` when it has none), `{name}` (full input name) and
+/// `{index}`.
+pub fn render_name(template: &str, input: &Path, index: usize) -> Result<String> {
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = input
+        .extension()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "mp4".to_string());
+    let name = input
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            bail!("Unclosed '{{' in name template {template:?}");
+        };
+        match &rest[open + 1..open + close] {
+            "stem" => out.push_str(&stem),
+            "ext" => out.push_str(&ext),
+            "name" => out.push_str(&name),
+            "index" => out.push_str(&index.to_string()),
+            other => bail!(
+                "Unknown placeholder {{{other}}} in name template {template:?}; \
+                 expected {{stem}}, {{ext}}, {{name}} or {{index}}"
+            ),
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    if out.is_empty() || out.contains(['/', '\\']) {
+        bail!("Name template {template:?} must produce a plain file name, got {out:?}");
+    }
+    Ok(out)
+}
+
+/// Pair every recording (an input, or its chapter files) with its output under
+/// `dir`, named after its first file. Errors before anything runs if two
+/// recordings would write the same file, or an output would overwrite one of
+/// its own inputs.
+pub fn plan_outputs(
+    recordings: &[Vec<Clip>],
+    dir: &Path,
+    template: &str,
+) -> Result<Vec<BatchItem>> {
+    let mut seen = HashSet::new();
+    let mut items = Vec::with_capacity(recordings.len());
+    for (i, inputs) in recordings.iter().enumerate() {
+        let Some(first) = inputs.first() else {
+            continue;
+        };
+        let output = dir.join(render_name(template, &first.path, i + 1)?);
+        if !seen.insert(output.clone()) {
+            bail!(
+                "Several inputs map to {output:?}; add {{index}} to --name-template to tell them apart"
+            );
+        }
+        let output_abs = std::path::absolute(&output)?;
+        for input in inputs {
+            if output_abs == std::path::absolute(&input.path)? {
+                bail!("Output {output:?} would overwrite its input");
+            }
+        }
+        items.push(BatchItem {
+            inputs: inputs.clone(),
+            output,
+        });
+    }
+    Ok(items)
+}
+
+/// The job for one item: the shared settings with this item's input/output.
+fn item_job(template: &JobSpec, item: &BatchItem) -> JobSpec {
+    JobSpec {
+        inputs: item.inputs.clone(),
+        output: item.output.clone(),
+        ..template.clone()
+    }
+}
+
+/// Print the commands each item would run.
+pub fn print_plans(template: &JobSpec, items: &[BatchItem]) -> Result<()> {
+    for item in items {
+        println!(
+            "# === {label} -> {output:?}\n",
+            label = item.label(),
+            output = item.output
+        );
+        for command in VideoProcessor::from_job(item_job(template, item)).plan()? {
+            println!("{command}\n");
+        }
+    }
+    Ok(())
+}
+
+/// Process every item, up to `jobs` at a time, and print a summary. Errors
+/// (after every item has run) if any of them failed.
+pub fn run(template: &JobSpec, items: &[BatchItem], jobs: usize) -> Result<()> {
+    let bars = MultiProgress::new();
+    let style = ProgressStyle::default_bar()
+        .template(
+            "{spinner:.green} [{elapsed_precise}] [{bar:30.cyan/blue}] {pos:>3}% {prefix} {msg}",
+        )
+        .map(|style| style.progress_chars("#>-"));
+    if let Err(e) = &style {
+        log::debug!("invalid progress bar template: {e}");
+    }
+
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<Outcome>>> = Mutex::new(vec![None; items.len()]);
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else { break };
+                    let bar = bars.add(ProgressBar::new(100));
+                    if let Ok(style) = &style {
+                        bar.set_style(style.clone());
+                    }
+                    if let Some(first) = item.inputs.first() {
+                        bar.set_prefix(display_name(&first.path));
+                    }
+                    let outcome = run_item(template, item, bar.clone());
+                    match &outcome {
+                        Ok(_) => bar.finish_with_message("done"),
+                        Err(_) => bar.abandon_with_message("failed"),
+                    }
+                    if let Ok(mut outcomes) = outcomes.lock() {
+                        outcomes[i] = Some(outcome);
+                    }
+                }
+            });
+        }
+    });
+
+    let outcomes = outcomes.into_inner().unwrap_or_default();
+    let mut failed = 0;
+    println!("\nBatch summary:");
+    for (item, outcome) in items.iter().zip(outcomes) {
+        match outcome {
+            Some(Ok(took)) => println!(
+                "  ✅ {input} -> {output:?} ({took})",
+                input = item.label(),
+                output = item.output,
+                took = format_duration(took)
+            ),
+            Some(Err(e)) => {
+                failed += 1;
+                println!("  ❌ {input}: {e}", input = item.label());
+            }
+            None => {
+                failed += 1;
+                println!("  ❌ {input}: did not run", input = item.label());
+            }
+        }
+    }
+    println!("{ok} succeeded, {failed} failed", ok = items.len() - failed);
+    if failed > 0 {
+        bail!("{failed} of {total} files failed", total = items.len());
+    }
+    Ok(())
+}
+
+/// How one item ended: its wall-clock time, or the error message.
+type Outcome = std::result::Result<Duration, String>;
+
+fn run_item(template: &JobSpec, item: &BatchItem, bar: ProgressBar) -> Outcome {
+    let started = Instant::now();
+    VideoProcessor::from_job(item_job(template, item))
+        .on_progress(move |event| {
+            bar.set_position(event.percent.unwrap_or(0.0) as u64);
+            bar.set_message(event.stage.clone());
+        })
+        .process()
+        .map(|()| started.elapsed())
+        .map_err(|e| e.to_string())
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned())
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 60 {
+        format!("{m}m {s}s", m = secs / 60, s = secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_placeholders_are_filled_from_the_input() -> Result<()> {
+        let input = Path::new("/card/DCIM/DJI_0042.MP4");
+        assert_eq!(
+            render_name(DEFAULT_TEMPLATE, input, 3)?,
+            "DJI_0042_graded.MP4"
+        );
+        assert_eq!(
+            render_name("{index}-{name}.mkv", input, 3)?,
+            "3-DJI_0042.MP4.mkv"
+        );
+        assert!(render_name("{stem}.{codec}", input, 1).is_err());
+        assert!(render_name("{stem", input, 1).is_err());
+        assert!(render_name("sub/{stem}.mp4", input, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn colliding_or_self_overwriting_outputs_are_rejected() {
+        let inputs = vec![vec![Clip::new("a/clip.mp4")], vec![Clip::new("b/clip.mp4")]];
+        assert!(plan_outputs(&inputs, Path::new("out"), DEFAULT_TEMPLATE).is_err());
+        assert!(plan_outputs(&inputs, Path::new("out"), "{index}_{stem}.{ext}").is_ok());
+        let inputs = vec![vec![Clip::new("a/clip.mp4")]];
+        assert!(plan_outputs(&inputs, Path::new("a"), "{name}").is_err());
+    }
+
+    #[test]
+    fn a_recording_is_named_after_its_first_chapter() -> Result<()> {
+        let recording = vec![
+            Clip::new("card/GX010042.MP4"),
+            Clip::new("card/GX020042.MP4"),
+        ];
+        let items = plan_outputs(
+            std::slice::from_ref(&recording),
+            Path::new("out"),
+            DEFAULT_TEMPLATE,
+        )?;
+        assert_eq!(
+            items,
+            vec![BatchItem {
+                inputs: recording,
+                output: PathBuf::from("out/GX010042_graded.MP4"),
+            }]
+        );
+        assert_eq!(items[0].label(), "card/GX010042.MP4 (+1 more)");
+        // The output may not overwrite a later chapter either.
+        let recording = vec![Clip::new("a/GX010042.MP4"), Clip::new("a/x.mp4")];
+        assert!(plan_outputs(&[recording], Path::new("a"), "x.mp4").is_err());
+        Ok(())
+    }
+}
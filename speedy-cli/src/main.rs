@@ -1,8 +1,13 @@
 use anyhow::{Context, Result};
-use clap::{CommandFactory, FromArgMatches, Parser};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::path::{Path, PathBuf};
 
-use speedy_core::{ColorProfile, Preset, VideoProcessor, check_ffmpeg};
+use speedy_core::{
+    Canvas, Clip, ColorProfile, Easing, Fill, InputOrder, Interpolation, JobSpec, Preset,
+    SpeedRamp, Transition, VideoProcessor, chapters, check_ffmpeg, order,
+};
+
+mod batch;
 
 #[derive(Parser, Debug)]
 #[command(name = "speedy")]
@@ -10,16 +15,49 @@ use speedy_core::{ColorProfile, Preset, VideoProcessor, check_ffmpeg};
     about = "Video processing tool for speed adjustment, LUT application, and color enhancement"
 )]
 #[command(version)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
     /// Input video file(s) or a directory. Pass several to stitch them together
-    /// in order; a directory is expanded to its video files sorted by name.
+    /// in order (or to process each on its own with --output-dir); a directory
+    /// is expanded to its video files sorted by name, with the chapter files
+    /// of a GoPro recording kept in order. Trim a file to part of
+    /// itself with PATH@START-END (e.g. clip.mp4@00:05-01:20), PATH@START+DURATION,
+    /// PATH@START- or PATH@-END. Play a file at a speed of its own, before
+    /// --speed, with a *SPEED suffix, optionally with the frame rate it is
+    /// resampled to (e.g. transit.mp4@02:00-12:00*20@30).
     #[arg(short, long, required_unless_present = "list_presets", num_args = 1..)]
     input: Vec<PathBuf>,
 
+    /// Order all inputs by file name, by capture time (probed creation time
+    /// or start timecode) or by file modification time, instead of the order
+    /// given. Clips lacking the chosen metadata go last. The resulting order
+    /// is printed before processing.
+    #[arg(long, value_name = "ORDER", value_enum)]
+    sort: Option<InputOrder>,
+
     /// Output video file path
-    #[arg(short, long, required_unless_present = "list_presets")]
+    #[arg(
+        short,
+        long,
+        required_unless_present_any = ["list_presets", "output_dir"],
+        conflicts_with = "output_dir"
+    )]
     output: Option<PathBuf>,
 
+    /// Batch mode: process every input on its own (same settings) into this
+    /// directory, instead of stitching them into one --output
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Output file name in batch mode. Placeholders: {stem}, {ext}, {name},
+    /// {index}
+    #[arg(long, value_name = "TEMPLATE", default_value = batch::DEFAULT_TEMPLATE)]
+    name_template: String,
+
+    /// Number of files processed at once in batch mode
+    #[arg(short = 'j', long, value_name = "N", default_value = "1")]
+    jobs: usize,
+
     /// Use a preset configuration
     #[arg(long, value_name = "PRESET")]
     preset: Option<String>,
@@ -28,16 +66,61 @@ struct Args {
     #[arg(short, long, default_value = "1.0")]
     speed: f64,
 
+    /// Pick the speed so the output lasts this long (e.g. "30s", "90" or
+    /// "1:30"), from the total length of the (trimmed, stitched) inputs
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with_all = ["speed", "speed_ramp"])]
+    fit_duration: Option<f64>,
+
+    /// Speed ramp as comma-separated TIME:SPEED keyframes, with times in
+    /// seconds of the (trimmed, stitched) source, e.g. "0:1,5:8,12:8,14:1".
+    /// The speed is interpolated between keyframes and held before the first
+    /// and after the last. Replaces --speed; audio is retimed along the ramp
+    /// and muted where it runs faster than 4x or slower than 0.25x.
+    #[arg(long, value_name = "KEYFRAMES", conflicts_with = "speed")]
+    speed_ramp: Option<SpeedRamp>,
+
+    /// How a speed ramp moves between keyframes: at a constant rate (linear)
+    /// or easing out of and into each keyframe (ease)
+    #[arg(long, value_name = "EASING", value_enum, requires = "speed_ramp")]
+    ramp_easing: Option<Easing>,
+
     /// Output frame rate for speed changes (e.g. "30" or "30000/1001").
     /// Defaults to the source frame rate, so a speed-up drops frames instead of
     /// inflating the frame rate (a 10x speed-up of 30fps stays 30fps).
     #[arg(long, value_name = "FPS")]
     output_fps: Option<String>,
 
-    /// LUT file path for color grading (supports .cube files)
+    /// How a slow-down fills in frames between source frames: repeat them
+    /// (none), cross-fade neighbours (blend), or motion-compensated
+    /// interpolation (mci; smoothest, much slower). Pair with --output-fps
+    /// (e.g. 60) to also raise the frame rate.
+    #[arg(long, value_name = "MODE", value_enum)]
+    interpolate: Option<Interpolation>,
+
+    /// Blend the frames a speed-up drops into each output frame, emulating a
+    /// shutter open for this many degrees of it (180 looks natural, 360 is
+    /// smoothest), so hyperlapses get motion blur instead of strobing
+    #[arg(long, value_name = "DEGREES")]
+    shutter_angle: Option<f64>,
+
+    /// Play the footage backwards, sound included
+    #[arg(long, conflicts_with = "boomerang")]
+    reverse: bool,
+
+    /// Play the footage forward then backwards, LOOPS times over (default 1)
+    #[arg(long, value_name = "LOOPS", num_args = 0..=1, default_missing_value = "1")]
+    boomerang: Option<u32>,
+
+    /// LUT file path for color grading (.cube, 3D or 1D; checked before
+    /// processing starts)
     #[arg(short, long)]
     lut: Option<PathBuf>,
 
+    /// Mix the LUT (--lut, or the --profile conversion LUT) with the ungraded
+    /// footage: 0.0 leaves it out, 1.0 (default) applies it fully
+    #[arg(long, value_name = "STRENGTH")]
+    lut_strength: Option<f32>,
+
     /// Color profile of the source footage
     #[arg(short = 'p', long, value_enum, default_value = "standard")]
     profile: ColorProfile,
@@ -122,13 +205,70 @@ struct Args {
     #[arg(long)]
     scale: Option<String>,
 
+    /// Transition between stitched clips as KIND[:SECONDS] (e.g. "fade:0.5",
+    /// "dip-to-black:1", "wipe", "cut"; any ffmpeg xfade transition works).
+    /// One applies to every boundary; a comma-separated list gives one per
+    /// boundary. Each transition shortens the output by its duration.
+    #[arg(long, value_name = "SPEC", value_delimiter = ',')]
+    transition: Vec<Transition>,
+
+    /// Frame that stitched clips are fitted into: "smallest" (smallest width
+    /// and height, lowest frame rate), "largest", "first" (match the first
+    /// clip), "common" (the orientation most clips share) or an explicit WxH
+    /// (e.g. 1920x1080). Other clips are scaled to fit and padded.
+    #[arg(long, value_name = "POLICY")]
+    canvas: Option<Canvas>,
+
+    /// How a stitched clip of another shape fills the canvas: black bars
+    /// (pad), over a blurred and darkened copy of itself (blur), or scaled up
+    /// and center-cropped (crop)
+    #[arg(long, value_name = "MODE", value_enum)]
+    fill: Option<Fill>,
+
+    /// Always re-encode when stitching. By default, clips that already match
+    /// (codec, resolution, frame rate, audio) are joined losslessly with stream
+    /// copy when no grade, speed change, trim, transition or encoder setting
+    /// (codec, quality, bitrate) is requested.
+    #[arg(long)]
+    no_stream_copy: bool,
+
+    /// Treat camera chapter files (one recording split at ~4 GB, recognized
+    /// by GoPro naming or continuous timecode/creation time) as one clip:
+    /// with --output-dir each recording becomes one output, and with
+    /// --stabilize each is smoothed as one segment. No transition is placed
+    /// between chapters.
+    #[arg(long)]
+    merge_chapters: bool,
+
+    /// Print every ffprobe/ffmpeg command the job would run, shell-quoted,
+    /// instead of running it. The inputs are still probed.
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Write the job spec equivalent to this command line to FILE instead of
+    /// processing (TOML, or JSON for a .json extension; "-" prints TOML)
+    #[arg(long, value_name = "FILE", global = true)]
+    emit_job: Option<PathBuf>,
+
     /// List available presets
     #[arg(long)]
     list_presets: bool,
 
     /// Verbose output
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     verbose: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Run a job spec written by --emit-job (or by hand / another tool)
+    Run {
+        /// Job spec file: TOML, or JSON for a .json extension
+        job: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
@@ -153,7 +293,84 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    // Check FFmpeg availability
+    if let Some(dir) = &args.output_dir {
+        return run_batch(&args, &matches, dir);
+    }
+
+    // Every job goes through a JobSpec, so `--emit-job` writes exactly what
+    // the command line would have run.
+    let job = match &args.command {
+        Some(Commands::Run { job }) => {
+            log::info!("Loading job spec {job}", job = job.display());
+            JobSpec::load(job)?
+        }
+        None => job_from_args(&args, &matches)?,
+    };
+
+    if let Some(path) = &args.emit_job {
+        if path == Path::new("-") {
+            let toml = job.to_toml()?;
+            print!("{toml}");
+        } else {
+            job.save(path)?;
+            println!("📝 Job spec written to: {path:?}");
+        }
+        return Ok(());
+    }
+
+    require_ffmpeg();
+
+    if job.inputs.is_empty() {
+        anyhow::bail!("No input video files found");
+    }
+    for clip in &job.inputs {
+        if !clip.path.exists() {
+            anyhow::bail!("Input file does not exist: {:?}", clip.path);
+        }
+    }
+
+    // Create output directory if it doesn't exist. Skip an empty parent (a bare
+    // filename like `out.mp4`), where create_dir_all("") would error.
+    if !args.dry_run
+        && let Some(parent) = job.output.parent().filter(|p| !p.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent).context("Failed to create output directory")?;
+    }
+
+    log::info!("Starting video processing...");
+    if job.inputs.len() == 1 {
+        log::info!("Input: {input}", input = job.inputs[0]);
+    } else {
+        let names: Vec<String> = job.inputs.iter().map(Clip::to_string).collect();
+        log::info!(
+            "Inputs ({count}): {names}",
+            count = job.inputs.len(),
+            names = names.join(", ")
+        );
+    }
+    log::info!("Output: {output:?}", output = job.output);
+
+    let output = job.output.clone();
+    let processor = VideoProcessor::from_job(job);
+
+    if args.dry_run {
+        for command in processor.plan()? {
+            println!("{command}\n");
+        }
+        return Ok(());
+    }
+
+    // Process the video
+    processor.process()?;
+
+    println!("\n✅ Video processing completed successfully!");
+    println!("📁 Output saved to: {:?}", output);
+
+    Ok(())
+}
+
+/// Check FFmpeg availability, exiting with install instructions if missing.
+fn require_ffmpeg() {
     match check_ffmpeg() {
         Ok(version) => {
             log::info!("FFmpeg version {} detected", version);
@@ -171,38 +388,74 @@ fn main() -> Result<()> {
             std::process::exit(1);
         }
     }
+}
 
-    // Resolve inputs: expand any directories into sorted video files.
-    let inputs = resolve_inputs(&args.input)?;
+/// Batch mode: process each input on its own into `dir`.
+fn run_batch(args: &Args, matches: &clap::ArgMatches, dir: &Path) -> Result<()> {
+    if args.command.is_some() {
+        anyhow::bail!("--output-dir applies to -i inputs, not to `speedy run`");
+    }
+    if args.emit_job.is_some() {
+        anyhow::bail!("--emit-job writes a single job; it cannot be combined with --output-dir");
+    }
+    require_ffmpeg();
+
+    let mut inputs = resolve_inputs(&args.input)?;
+    if let Some(order) = args.sort {
+        sort_inputs(&mut inputs, order)?;
+    }
     if inputs.is_empty() {
         anyhow::bail!("No input video files found");
     }
-    for file in &inputs {
-        if !file.exists() {
-            anyhow::bail!("Input file does not exist: {:?}", file);
+    for clip in &inputs {
+        if !clip.path.exists() {
+            anyhow::bail!("Input file does not exist: {:?}", clip.path);
         }
     }
+    let shots = if args.merge_chapters {
+        group_recordings(inputs)?
+    } else {
+        inputs.into_iter().map(|clip| vec![clip]).collect()
+    };
+    let items = batch::plan_outputs(&shots, dir, &args.name_template)?;
+    // The shared settings; each item gets its own input and output.
+    let template = settings_from_args(args, matches, Vec::new(), PathBuf::new())?;
+
+    if args.dry_run {
+        return batch::print_plans(&template, &items);
+    }
+    std::fs::create_dir_all(dir).context("Failed to create output directory")?;
+    log::info!(
+        "Processing {count} file(s) into {dir:?}, {jobs} at a time",
+        count = items.len(),
+        jobs = args.jobs.max(1)
+    );
+    batch::run(&template, &items, args.jobs)
+}
 
+/// Build the job described by the command-line flags (preset first, then the
+/// individual settings on top).
+fn job_from_args(args: &Args, matches: &clap::ArgMatches) -> Result<JobSpec> {
+    // Resolve inputs: expand any directories into sorted video files.
+    let mut inputs = resolve_inputs(&args.input)?;
+    if let Some(order) = args.sort {
+        sort_inputs(&mut inputs, order)?;
+    }
     let output = args
         .output
+        .clone()
         .ok_or_else(|| anyhow::anyhow!("Output file required"))?;
+    settings_from_args(args, matches, inputs, output)
+}
 
-    // Create output directory if it doesn't exist. Skip an empty parent (a bare
-    // filename like `out.mp4`), where create_dir_all("") would error.
-    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
-        std::fs::create_dir_all(parent).context("Failed to create output directory")?;
-    }
-
-    log::info!("Starting video processing...");
-    if inputs.len() == 1 {
-        log::info!("Input: {:?}", inputs[0]);
-    } else {
-        log::info!("Inputs ({}): {:?}", inputs.len(), inputs);
-    }
-    log::info!("Output: {:?}", output);
-
-    // Create video processor
-    let mut processor = VideoProcessor::new_multi(inputs, &output);
+/// Apply the preset and setting flags to a job on `inputs` -> `output`.
+fn settings_from_args(
+    args: &Args,
+    matches: &clap::ArgMatches,
+    inputs: Vec<Clip>,
+    output: PathBuf,
+) -> Result<JobSpec> {
+    let mut processor = VideoProcessor::from_clips(inputs, output);
 
     // Apply preset if specified
     let preset_used = args.preset.is_some();
@@ -234,7 +487,7 @@ fn main() -> Result<()> {
         processor = processor.quality(args.quality);
     }
     if !preset_used || explicit("profile") {
-        processor = processor.profile(args.profile);
+        processor = processor.profile(args.profile.clone());
     }
     if !preset_used || explicit("contrast") {
         processor = processor.contrast(args.contrast);
@@ -263,10 +516,14 @@ fn main() -> Result<()> {
         processor = processor.threads(threads);
     }
 
-    if let Some(lut) = args.lut {
+    if let Some(lut) = &args.lut {
         processor = processor.lut(lut);
     }
 
+    if let Some(strength) = args.lut_strength {
+        processor = processor.lut_strength(strength);
+    }
+
     if let Some(denoise) = args.denoise {
         processor = processor.denoise(denoise);
     }
@@ -289,42 +546,85 @@ fn main() -> Result<()> {
         processor = processor.stabilize_smoothing(smoothing);
     }
 
-    if let Some(curves) = args.curves {
-        processor = processor.curves(&curves);
+    if args.no_stream_copy {
+        processor = processor.stream_copy(false);
+    }
+
+    if let Some(canvas) = args.canvas {
+        processor = processor.canvas(canvas);
+    }
+
+    if let Some(fill) = args.fill {
+        processor = processor.fill(fill);
+    }
+
+    if args.merge_chapters {
+        processor = processor.merge_chapters(true);
+    }
+
+    if !args.transition.is_empty() {
+        processor = processor.transitions(args.transition.clone());
+    }
+
+    if let Some(curves) = &args.curves {
+        processor = processor.curves(curves);
     }
 
     if let Some(hue_shift) = args.hue_shift {
         processor = processor.hue_shift(hue_shift);
     }
 
-    if let Some(color_balance) = args.color_balance {
-        processor = processor.color_balance_str(&color_balance);
+    if let Some(color_balance) = &args.color_balance {
+        processor = processor.color_balance_str(color_balance);
     }
 
-    if let Some(selective_color) = args.selective_color {
-        processor = processor.selective_color(&selective_color);
+    if let Some(selective_color) = &args.selective_color {
+        processor = processor.selective_color(selective_color);
     }
 
-    if let Some(scale) = args.scale {
-        processor = processor.scale(&scale);
+    if let Some(scale) = &args.scale {
+        processor = processor.scale(scale);
     }
 
-    if let Some(output_fps) = args.output_fps {
-        processor = processor.output_fps(&output_fps);
+    if let Some(seconds) = args.fit_duration {
+        processor = processor.fit_duration(seconds);
     }
 
-    // Process the video
-    processor.process()?;
+    if let Some(ramp) = &args.speed_ramp {
+        processor = processor.speed_ramp(ramp.clone());
+    }
 
-    println!("\n✅ Video processing completed successfully!");
-    println!("📁 Output saved to: {:?}", output);
+    if let Some(easing) = args.ramp_easing {
+        processor = processor.ramp_easing(easing);
+    }
 
-    Ok(())
+    if let Some(output_fps) = &args.output_fps {
+        processor = processor.output_fps(output_fps);
+    }
+
+    if let Some(interpolation) = args.interpolate {
+        processor = processor.interpolate(interpolation);
+    }
+
+    if let Some(angle) = args.shutter_angle {
+        processor = processor.shutter_angle(angle);
+    }
+
+    if args.reverse {
+        processor = processor.reverse(true);
+    }
+
+    if let Some(loops) = args.boomerang {
+        processor = processor.boomerang(loops);
+    }
+
+    Ok(processor.job_spec())
 }
 
-/// Expand the given paths into an ordered list of input files. Directories are
-/// replaced by their video files sorted by name; regular paths are kept as-is.
-fn resolve_inputs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+/// Expand the given paths into an ordered list of input clips. Directories are
+/// replaced by their video files sorted by name; existing files are kept as-is.
+/// Any other path may carry a trim (`clip.mp4@00:05-01:20`).
+fn resolve_inputs(paths: &[PathBuf]) -> Result<Vec<Clip>> {
     let mut files = Vec::new();
     for path in paths {
         if path.is_dir() {
@@ -333,7 +633,7 @@ fn resolve_inputs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
                 .filter_map(|entry| entry.ok().map(|e| e.path()))
                 .filter(|p| is_video_file(p))
                 .collect();
-            dir_files.sort();
+            chapters::sort_chapters(&mut dir_files);
             if dir_files.is_empty() {
                 anyhow::bail!("No video files found in directory: {}", path.display());
             }
@@ -342,14 +642,74 @@ fn resolve_inputs(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
                 dir_files.len(),
                 path.display()
             );
-            files.extend(dir_files);
+            files.extend(dir_files.into_iter().map(Clip::from));
+        } else if path.exists() {
+            files.push(Clip::new(path));
         } else {
-            files.push(path.clone());
+            // Checked after `exists`, so a file whose name holds an `@` is
+            // never mistaken for a trimmed clip.
+            match path.to_str() {
+                Some(spec) => files.push(spec.parse()?),
+                None => files.push(Clip::new(path)),
+            }
         }
     }
     Ok(files)
 }
 
+/// Reorder `inputs` by `--sort` and list the result, with the value each clip
+/// was sorted by. Printed to stderr, so `--emit-job -` output stays clean.
+fn sort_inputs(inputs: &mut [Clip], order: InputOrder) -> Result<()> {
+    let shown = order::sort_clips(inputs, order)?;
+    let by = match order {
+        InputOrder::Name => "name",
+        InputOrder::CreationTime => "creation time",
+        InputOrder::Timecode => "timecode",
+        InputOrder::Mtime => "modification time",
+    };
+    eprintln!("Input order (by {by}):");
+    for (n, (clip, value)) in inputs.iter().zip(shown).enumerate() {
+        match (order, value) {
+            (InputOrder::Name, _) => eprintln!("  {n:>3}. {clip}", n = n + 1),
+            (_, Some(value)) => eprintln!("  {n:>3}. {clip}  [{value}]", n = n + 1),
+            (_, None) => eprintln!("  {n:>3}. {clip}  [no {by}]", n = n + 1),
+        }
+    }
+    Ok(())
+}
+
+/// Group `inputs` into recordings, each a file and the chapter files that
+/// continue it (probed for timecode and creation time). A trimmed or retimed
+/// clip is always a recording of its own.
+fn group_recordings(inputs: Vec<Clip>) -> Result<Vec<Vec<Clip>>> {
+    let mut recordings = Vec::new();
+    let mut run: Vec<PathBuf> = Vec::new();
+    let flush = |run: &mut Vec<PathBuf>, recordings: &mut Vec<Vec<Clip>>| -> Result<()> {
+        for group in chapters::group_recordings(run)? {
+            if group.len() > 1 {
+                log::info!(
+                    "{first} and {more} more chapter file(s) form one recording",
+                    first = group[0].display(),
+                    more = group.len() - 1
+                );
+            }
+            recordings.push(group.into_iter().map(Clip::from).collect());
+        }
+        run.clear();
+        Ok(())
+    };
+    for clip in inputs {
+        if clip.is_trimmed() || clip.is_retimed() {
+            flush(&mut run, &mut recordings)?;
+            recordings.push(vec![clip]);
+        } else {
+            run.push(clip.path);
+        }
+    }
+    flush(&mut run, &mut recordings)?;
+    Ok(recordings)
+}
+
 /// Whether a path looks like a video file based on its extension.
 fn is_video_file(path: &Path) -> bool {
     matches!(
@@ -361,6 +721,14 @@ fn is_video_file(path: &Path) -> bool {
     )
 }
 
+/// Parse a `--fit-duration` as seconds (`30`, `30s`) or `[HH:]MM:SS`.
+fn parse_duration(text: &str) -> Result<f64, String> {
+    let trimmed = text.trim();
+    speedy_core::clip::parse_time(trimmed.strip_suffix('s').unwrap_or(trimmed))
+        .filter(|&seconds| seconds > 0.0)
+        .ok_or_else(|| format!("{text:?} is not a duration like \"30s\", \"90\" or \"1:30\""))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,11 +746,179 @@ mod tests {
         }
     }
 
+    #[test]
+    fn job_from_args_layers_explicit_flags_over_the_preset() -> Result<()> {
+        let matches = Args::command().try_get_matches_from([
+            "speedy",
+            "-i",
+            "a.mp4",
+            "-o",
+            "out.mp4",
+            "--preset",
+            "mavic4pro-dlog",
+            "--quality",
+            "18",
+        ])?;
+        let args = Args::from_arg_matches(&matches)?;
+        let job = job_from_args(&args, &matches)?;
+        // The preset's settings survive the flag defaults...
+        assert_eq!(job.profile, ColorProfile::DLog);
+        assert!(job.stabilize);
+        assert_eq!(job.codec, "libx265");
+        // ...while an explicit flag overrides it.
+        assert_eq!(job.quality, 18);
+        Ok(())
+    }
+
+    #[test]
+    fn transition_list_is_split_per_boundary() -> Result<()> {
+        let args = Args::try_parse_from([
+            "speedy",
+            "-i",
+            "a.mp4",
+            "b.mp4",
+            "c.mp4",
+            "-o",
+            "out.mp4",
+            "--transition",
+            "crossfade:1,cut",
+        ])?;
+        assert_eq!(
+            args.transition,
+            vec![Transition::xfade("fade", 1.0)?, Transition::Cut]
+        );
+        assert!(
+            Args::try_parse_from([
+                "speedy",
+                "-i",
+                "a.mp4",
+                "-o",
+                "o.mp4",
+                "--transition",
+                "fade:x"
+            ])
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fit_duration_accepts_seconds_and_clock_times() -> Result<()> {
+        assert_eq!(parse_duration("30s"), Ok(30.0));
+        assert_eq!(parse_duration("90"), Ok(90.0));
+        assert_eq!(parse_duration("1:30"), Ok(90.0));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("soon").is_err());
+        let args = Args::try_parse_from([
+            "speedy",
+            "-i",
+            "a.mp4",
+            "-o",
+            "out.mp4",
+            "--fit-duration",
+            "30s",
+        ])?;
+        assert_eq!(args.fit_duration, Some(30.0));
+        assert!(
+            Args::try_parse_from([
+                "speedy",
+                "-i",
+                "a.mp4",
+                "-o",
+                "o.mp4",
+                "--speed",
+                "2",
+                "--fit-duration",
+                "30"
+            ])
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn speed_ramp_parses_keyframes_and_excludes_speed() -> Result<()> {
+        let matches = Args::command().try_get_matches_from([
+            "speedy",
+            "-i",
+            "a.mp4",
+            "-o",
+            "out.mp4",
+            "--speed-ramp",
+            "0:1,5:8,12:8,14:1",
+            "--ramp-easing",
+            "ease",
+        ])?;
+        let job = job_from_args(&Args::from_arg_matches(&matches)?, &matches)?;
+        assert_eq!(job.speed_ramp, Some("0:1,5:8,12:8,14:1".parse()?));
+        assert_eq!(job.ramp_easing, Easing::Ease);
+        assert!(
+            Args::try_parse_from([
+                "speedy",
+                "-i",
+                "a.mp4",
+                "-o",
+                "o.mp4",
+                "--speed",
+                "2",
+                "--speed-ramp",
+                "0:1,5:8"
+            ])
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sort_orders_name_the_metadata_used() -> Result<()> {
+        let args = Args::try_parse_from([
+            "speedy",
+            "-i",
+            "a.mp4",
+            "-o",
+            "out.mp4",
+            "--sort",
+            "creation-time",
+        ])?;
+        assert_eq!(args.sort, Some(InputOrder::CreationTime));
+        let args = Args::try_parse_from(["speedy", "-i", "a.mp4", "-o", "out.mp4"])?;
+        assert_eq!(args.sort, None);
+        assert!(
+            Args::try_parse_from(["speedy", "-i", "a.mp4", "-o", "o.mp4", "--sort", "size"])
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn run_subcommand_needs_no_input_flags() -> Result<()> {
+        let args = Args::try_parse_from(["speedy", "run", "job.toml", "--dry-run"])?;
+        assert!(args.dry_run);
+        assert!(
+            matches!(args.command, Some(Commands::Run { ref job }) if job == Path::new("job.toml"))
+        );
+        Ok(())
+    }
+
     #[test]
     fn resolve_inputs_passes_through_explicit_files_in_order() -> Result<()> {
         let inputs = vec![PathBuf::from("b.mp4"), PathBuf::from("a.mov")];
         // Explicit (non-directory) paths are kept as given, in order.
-        assert_eq!(resolve_inputs(&inputs)?, inputs);
+        let expected: Vec<Clip> = inputs.iter().map(Clip::new).collect();
+        assert_eq!(resolve_inputs(&inputs)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_inputs_parses_trims_on_paths_that_do_not_exist() -> Result<()> {
+        let resolved = resolve_inputs(&[PathBuf::from("dji.mp4@00:05-01:20")])?;
+        assert_eq!(resolved, vec![Clip::new("dji.mp4").start(5.0).end(80.0)]);
+        assert!(resolve_inputs(&[PathBuf::from("dji.mp4@later")]).is_err());
+        let resolved = resolve_inputs(&[PathBuf::from("transit.mp4@2:00-*20@30")])?;
+        assert_eq!(
+            resolved,
+            vec![Clip::new("transit.mp4").start(120.0).speed(20.0).fps("30")]
+        );
         Ok(())
     }
 
@@ -390,7 +926,15 @@ mod tests {
     fn resolve_inputs_expands_directory_sorted_video_only() -> Result<()> {
         let dir = std::env::temp_dir().join(format!("speedy_resolve_test_{}", std::process::id()));
         std::fs::create_dir_all(&dir)?;
-        for name in ["clip_b.mp4", "clip_a.mp4", "telemetry.srt", "proxy.LRF"] {
+        for name in [
+            "clip_b.mp4",
+            "clip_a.mp4",
+            "GX020001.MP4",
+            "GX010002.MP4",
+            "GX010001.MP4",
+            "telemetry.srt",
+            "proxy.LRF",
+        ] {
             std::fs::write(dir.join(name), b"")?;
         }
 
@@ -399,10 +943,20 @@ mod tests {
 
         let names: Vec<String> = resolved?
             .iter()
-            .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .filter_map(|c| c.path.file_name().map(|n| n.to_string_lossy().into_owned()))
             .collect();
-        // Non-video files excluded; videos returned sorted by name.
-        assert_eq!(names, vec!["clip_a.mp4", "clip_b.mp4"]);
+        // Non-video files excluded; videos returned sorted by name, except
+        // that a GoPro recording's chapters stay together.
+        assert_eq!(
+            names,
+            vec![
+                "GX010001.MP4",
+                "GX020001.MP4",
+                "GX010002.MP4",
+                "clip_a.mp4",
+                "clip_b.mp4"
+            ]
+        );
         Ok(())
     }
 }
@@ -0,0 +1,46 @@
+//! Cooperative cancellation of running jobs.
+//!
+//! A [`CancellationToken`] is handed to [`crate::FFmpegCommand::cancellation`]
+//! or [`crate::VideoProcessor::cancellation`] and tripped from another thread
+//! (e.g. a GUI's Cancel button). The running ffmpeg child is killed, temporary
+//! files and the partial output are removed, and the job returns
+//! [`crate::SpeedyError::Cancelled`].
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A cloneable handle that cancels every job it was given to. Clones share
+/// state, so keep one and pass the other to the job.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation. Idempotent.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_cancellation() {
+        let token = CancellationToken::new();
+        let handle = token.clone();
+        assert!(!token.is_cancelled());
+        handle.cancel();
+        assert!(token.is_cancelled());
+    }
+}
@@ -0,0 +1,226 @@
+//! The frame stitched clips are fitted into.
+//!
+//! A [`Canvas`] policy picks the output size and frame rate of a stitch from
+//! the clips: `smallest` (the default; nothing is upscaled), `largest`,
+//! `first` (match the first clip), `common` (the orientation most clips
+//! share), or an explicit `WxH`. A clip of another shape is fitted into it as
+//! its [`Fill`] says: padded with black bars, over a blurred copy of itself,
+//! or cropped to fill the frame.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::VideoInfo;
+use crate::error::{Result, SpeedyError};
+
+/// How the output frame of a stitch is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Canvas {
+    /// The smallest width and height across clips, and the lowest frame rate.
+    #[default]
+    Smallest,
+    /// The largest width and height across clips, and the highest frame rate.
+    Largest,
+    /// The first clip's size and frame rate.
+    First,
+    /// The orientation (landscape or portrait) most clips share, at the
+    /// smallest size among those clips, and the most common frame rate.
+    Common,
+    /// An explicit size, at the most common frame rate.
+    Size(u32, u32),
+}
+
+/// How a clip of another aspect ratio is fitted into the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fill {
+    /// Scale to fit and pad with black bars.
+    #[default]
+    Pad,
+    /// Scale to fit over a blurred, darkened copy of the clip scaled to fill
+    /// the frame (the "blur pillarbox" look).
+    Blur,
+    /// Scale to fill the frame and center-crop the overflow.
+    Crop,
+}
+
+impl Canvas {
+    /// The canvas for clips of the given frame sizes (as filters see them).
+    pub(crate) fn size(&self, dims: &[(u32, u32)]) -> Option<(u32, u32)> {
+        match *self {
+            Canvas::Smallest => smallest(dims.iter().copied()),
+            Canvas::Largest => dims
+                .iter()
+                .copied()
+                .reduce(|(aw, ah), (bw, bh)| (aw.max(bw), ah.max(bh))),
+            Canvas::First => dims.first().copied(),
+            Canvas::Common => {
+                let portrait = |&(w, h): &(u32, u32)| h > w;
+                let portraits = dims.iter().filter(|d| portrait(d)).count();
+                let landscapes = dims.len() - portraits;
+                // A tie goes to the first clip's orientation.
+                let wanted = match portraits.cmp(&landscapes) {
+                    std::cmp::Ordering::Greater => true,
+                    std::cmp::Ordering::Less => false,
+                    std::cmp::Ordering::Equal => dims.first().is_some_and(portrait),
+                };
+                smallest(dims.iter().copied().filter(|d| portrait(d) == wanted))
+            }
+            Canvas::Size(width, height) => Some((width, height)),
+        }
+    }
+
+    /// Which clip's frame rate the stitch is normalized to.
+    pub(crate) fn rate_clip(&self, infos: &[VideoInfo]) -> usize {
+        let by_fps = |pick_higher: bool| {
+            (0..infos.len())
+                .reduce(|best, i| {
+                    let better = if pick_higher {
+                        infos[i].fps > infos[best].fps
+                    } else {
+                        infos[i].fps < infos[best].fps
+                    };
+                    if better { i } else { best }
+                })
+                .unwrap_or(0)
+        };
+        match self {
+            Canvas::Smallest => by_fps(false),
+            Canvas::Largest => by_fps(true),
+            Canvas::First => 0,
+            Canvas::Common | Canvas::Size(..) => {
+                // The first clip of the most frequent rate; ties go to the
+                // rate seen first.
+                let count = |i: usize| {
+                    infos
+                        .iter()
+                        .filter(|info| info.frame_rate == infos[i].frame_rate)
+                        .count()
+                };
+                (0..infos.len())
+                    .reduce(|best, i| if count(i) > count(best) { i } else { best })
+                    .unwrap_or(0)
+            }
+        }
+    }
+}
+
+/// The smallest width and the smallest height among `dims`.
+fn smallest(dims: impl Iterator<Item = (u32, u32)>) -> Option<(u32, u32)> {
+    dims.reduce(|(aw, ah), (bw, bh)| (aw.min(bw), ah.min(bh)))
+}
+
+impl FromStr for Canvas {
+    type Err = SpeedyError;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        match spec.trim() {
+            "smallest" => return Ok(Canvas::Smallest),
+            "largest" => return Ok(Canvas::Largest),
+            "first" => return Ok(Canvas::First),
+            "common" => return Ok(Canvas::Common),
+            _ => {}
+        }
+        let size = spec
+            .trim()
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)));
+        match size {
+            // Even sizes, as 4:2:0 encoders require.
+            Some((width, height))
+                if width > 0 && height > 0 && width % 2 == 0 && height % 2 == 0 =>
+            {
+                Ok(Canvas::Size(width, height))
+            }
+            Some(_) => Err(SpeedyError::invalid(
+                "canvas",
+                format!("{spec:?}; width and height must be positive and even"),
+            )),
+            None => Err(SpeedyError::invalid(
+                "canvas",
+                format!(
+                    "{spec:?}; expected smallest, largest, first, common or WxH (e.g. 1920x1080)"
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Canvas::Smallest => f.write_str("smallest"),
+            Canvas::Largest => f.write_str("largest"),
+            Canvas::First => f.write_str("first"),
+            Canvas::Common => f.write_str("common"),
+            Canvas::Size(width, height) => write!(f, "{width}x{height}"),
+        }
+    }
+}
+
+impl TryFrom<String> for Canvas {
+    type Error = SpeedyError;
+
+    fn try_from(spec: String) -> Result<Self> {
+        spec.parse()
+    }
+}
+
+impl From<Canvas> for String {
+    fn from(canvas: Canvas) -> Self {
+        canvas.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies_pick_the_canvas_size() {
+        let dims = [(3840, 2160), (1080, 1920), (1920, 1080)];
+        assert_eq!(Canvas::Smallest.size(&dims), Some((1080, 1080)));
+        assert_eq!(Canvas::Largest.size(&dims), Some((3840, 2160)));
+        assert_eq!(Canvas::First.size(&dims), Some((3840, 2160)));
+        // Two landscape clips outvote the portrait one.
+        assert_eq!(Canvas::Common.size(&dims), Some((1920, 1080)));
+        assert_eq!(
+            Canvas::Common.size(&[(1080, 1920), (3840, 2160)]),
+            Some((1080, 1920))
+        );
+        assert_eq!(Canvas::Size(1280, 720).size(&dims), Some((1280, 720)));
+    }
+
+    #[test]
+    fn policies_pick_the_frame_rate_clip() {
+        let clip = |fps: f64, rate: &str| VideoInfo {
+            fps,
+            frame_rate: Some(rate.to_string()),
+            ..VideoInfo::default()
+        };
+        let infos = [
+            clip(29.97, "30000/1001"),
+            clip(25.0, "25/1"),
+            clip(50.0, "50/1"),
+            clip(25.0, "25/1"),
+        ];
+        assert_eq!(Canvas::Smallest.rate_clip(&infos), 1);
+        assert_eq!(Canvas::Largest.rate_clip(&infos), 2);
+        assert_eq!(Canvas::First.rate_clip(&infos), 0);
+        assert_eq!(Canvas::Common.rate_clip(&infos), 1);
+    }
+
+    #[test]
+    fn specs_round_trip_and_reject_odd_sizes() -> anyhow::Result<()> {
+        for spec in ["smallest", "largest", "first", "common", "1920x1080"] {
+            assert_eq!(spec.parse::<Canvas>()?.to_string(), spec);
+        }
+        for spec in ["1919x1080", "0x720", "wide", "1920"] {
+            assert!(spec.parse::<Canvas>().is_err(), "{spec} should fail");
+        }
+        Ok(())
+    }
+}
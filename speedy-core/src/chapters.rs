@@ -0,0 +1,264 @@
+//! Camera chapter files: one long recording split into several files.
+//!
+//! GoPro, DJI and Sony cameras start a new file every ~4 GB of a recording.
+//! GoPro names its chapters so that a plain sort by file name interleaves
+//! recordings (`GX010042`, `GX010043`, `GX020042`); [`sort_chapters`] orders
+//! them recording by recording instead. [`group_chapters`] then finds which
+//! neighbouring files continue the same recording: by GoPro's naming scheme,
+//! or else by continuity of timecode or creation time (the next chapter starts
+//! where the previous one ended).
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::{VideoInfo, get_video_info};
+
+/// How far (seconds) a chapter's start timecode may be from where the
+/// previous chapter's ended.
+const TIMECODE_TOLERANCE: f64 = 0.5;
+
+/// How far (seconds) a chapter's creation time may be from where the
+/// previous chapter's ended. Creation times usually have one-second
+/// resolution, and cameras take a moment to open the next file.
+const CREATION_TOLERANCE: f64 = 2.0;
+
+/// Sort files so the chapters of each recording follow each other in order;
+/// other files sort by name.
+pub fn sort_chapters(paths: &mut [PathBuf]) {
+    paths.sort_by_cached_key(|path| (path.parent().map(Path::to_path_buf), name_key(path)));
+}
+
+/// Sort key of a file name that keeps a GoPro recording's chapters together:
+/// the recording and chapter number, or the file name and 0.
+pub(crate) fn name_key(path: &Path) -> (String, u32) {
+    gopro_chapter(path).unwrap_or_else(|| (file_name(path), 0))
+}
+
+/// Group files, in chapter order (see [`sort_chapters`]), into recordings:
+/// each range holds a file and the chapters continuing it. `infos` are the
+/// probed infos of `paths`, in the same order.
+///
+/// GoPro files belong together when their names say so. Other files continue
+/// the previous one when they share its format and their timecode (or, without
+/// timecodes, their creation time) starts where the previous file ended.
+pub fn group_chapters(paths: &[PathBuf], infos: &[VideoInfo]) -> Vec<Range<usize>> {
+    let count = paths.len().min(infos.len());
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for i in 0..count {
+        match groups.last_mut() {
+            Some(group) if continues(&paths[i - 1], &infos[i - 1], &paths[i], &infos[i]) => {
+                group.end = i + 1;
+            }
+            _ => groups.push(i..i + 1),
+        }
+    }
+    groups
+}
+
+/// Probe `paths` (in chapter order) and group them into recordings, as
+/// [`group_chapters`] does.
+pub fn group_recordings(paths: &[PathBuf]) -> Result<Vec<Vec<PathBuf>>> {
+    let infos = paths
+        .iter()
+        .map(get_video_info)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(group_chapters(paths, &infos)
+        .into_iter()
+        .map(|range| paths[range].to_vec())
+        .collect())
+}
+
+/// Whether `next` continues the recording `prev` belongs to.
+fn continues(prev: &Path, prev_info: &VideoInfo, next: &Path, next_info: &VideoInfo) -> bool {
+    match (gopro_chapter(prev), gopro_chapter(next)) {
+        (Some((a, chapter_a)), Some((b, chapter_b))) => {
+            return a == b && chapter_b == chapter_a + 1;
+        }
+        (None, None) => {}
+        // GoPro names every chapter, so a named file never continues (or is
+        // continued by) an unnamed one.
+        _ => return false,
+    }
+    let same_format = prev_info.video_codec == next_info.video_codec
+        && (prev_info.width, prev_info.height) == (next_info.width, next_info.height)
+        && prev_info.frame_rate == next_info.frame_rate;
+    if !same_format {
+        return false;
+    }
+    let ended = |start: f64| start + prev_info.duration;
+    if let (Some(a), Some(b)) = (timecode_seconds(prev_info), timecode_seconds(next_info)) {
+        return (b - ended(a)).abs() <= TIMECODE_TOLERANCE;
+    }
+    match (
+        prev_info
+            .creation_time
+            .as_deref()
+            .and_then(parse_creation_time),
+        next_info
+            .creation_time
+            .as_deref()
+            .and_then(parse_creation_time),
+    ) {
+        (Some(a), Some(b)) => (b - ended(a)).abs() <= CREATION_TOLERANCE,
+        _ => false,
+    }
+}
+
+/// A GoPro file's recording and chapter number: `GX020042.MP4` is chapter 2
+/// of recording `GX0042`. Older models name the first chapter `GOPR0042.MP4`
+/// and later ones `GP010042.MP4`, all recording `GOPR0042`.
+fn gopro_chapter(path: &Path) -> Option<(String, u32)> {
+    let stem = path.file_stem()?.to_str()?.to_ascii_uppercase();
+    if stem.len() != 8 {
+        return None;
+    }
+    let (prefix, digits) = stem.split_at(2);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return match (prefix, &digits[..2]) {
+            ("GO", "PR") if digits[2..].bytes().all(|b| b.is_ascii_digit()) => {
+                Some((stem.clone(), 0))
+            }
+            _ => None,
+        };
+    }
+    let (chapter, number) = digits.split_at(2);
+    let chapter = chapter.parse().ok()?;
+    match prefix {
+        "GH" | "GX" => Some((format!("{prefix}{number}"), chapter)),
+        "GP" => Some((format!("GOPR{number}"), chapter)),
+        _ => None,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// A `HH:MM:SS:FF` (or drop-frame `HH:MM:SS;FF`) timecode in seconds.
+pub(crate) fn timecode_seconds(info: &VideoInfo) -> Option<f64> {
+    let timecode = info.timecode.as_deref()?;
+    let fields: Vec<f64> = timecode
+        .split([':', ';', '.'])
+        .map(|field| field.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hours, minutes, seconds, frames] = fields[..] else {
+        return None;
+    };
+    let frames = if info.fps > 0.0 {
+        frames / info.fps
+    } else {
+        0.0
+    };
+    Some(hours * 3600.0 + minutes * 60.0 + seconds + frames)
+}
+
+/// An ISO 8601 creation time (`2024-05-01T10:00:00.000000Z`, as ffprobe
+/// reports it) in seconds since the Unix epoch. The zone is ignored: only
+/// differences between files of one camera matter.
+pub(crate) fn parse_creation_time(text: &str) -> Option<f64> {
+    let (date, time) = text.trim().split_once(['T', ' '])?;
+    let mut date = date.split('-').map(|field| field.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let time = time.trim_end_matches('Z');
+    let time = time.split(['+', '-']).next()?;
+    let mut fields = time.split(':');
+    let hours: f64 = fields.next()?.parse().ok()?;
+    let minutes: f64 = fields.next()?.parse().ok()?;
+    let seconds: f64 = fields.next().unwrap_or("0").parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some(days as f64 * 86400.0 + hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Days from 1970-01-01 to the given proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(duration: f64, creation_time: &str, timecode: Option<&str>) -> VideoInfo {
+        VideoInfo {
+            duration,
+            width: 3840,
+            height: 2160,
+            fps: 25.0,
+            video_codec: Some("hevc".to_string()),
+            frame_rate: Some("25/1".to_string()),
+            creation_time: Some(creation_time.to_string()),
+            timecode: timecode.map(str::to_string),
+            ..VideoInfo::default()
+        }
+    }
+
+    #[test]
+    fn gopro_chapters_sort_recording_by_recording() {
+        let mut paths: Vec<PathBuf> = [
+            "card/GX020042.MP4",
+            "card/GX010043.MP4",
+            "card/GX010042.MP4",
+            "card/GP010007.MP4",
+            "card/GOPR0007.MP4",
+            "card/DJI_0001.MP4",
+        ]
+        .map(PathBuf::from)
+        .to_vec();
+        sort_chapters(&mut paths);
+        let names: Vec<String> = paths.iter().map(|p| file_name(p)).collect();
+        assert_eq!(
+            names,
+            [
+                "DJI_0001.MP4",
+                "GOPR0007.MP4",
+                "GP010007.MP4",
+                "GX010042.MP4",
+                "GX020042.MP4",
+                "GX010043.MP4"
+            ]
+        );
+    }
+
+    #[test]
+    fn gopro_names_decide_their_groups() {
+        let paths = ["GX010042.MP4", "GX020042.MP4", "GX010043.MP4"].map(PathBuf::from);
+        // Creation times are ignored for named chapters.
+        let infos = [
+            clip(600.0, "2024-05-01T10:00:00Z", None),
+            clip(300.0, "2024-05-01T12:00:00Z", None),
+            clip(60.0, "2024-05-01T10:15:00Z", None),
+        ];
+        assert_eq!(group_chapters(&paths, &infos), vec![0..2, 2..3]);
+    }
+
+    #[test]
+    fn other_files_group_by_timecode_then_creation_time() {
+        let paths = ["C0001.MP4", "C0002.MP4", "C0003.MP4"].map(PathBuf::from);
+        // Timecode continues into C0002 (10:00:00:00 + 512.4s); C0003 starts later.
+        let infos = [
+            clip(512.4, "2024-05-01T10:00:00Z", Some("10:00:00:00")),
+            clip(100.0, "2024-05-01T10:30:00Z", Some("10:08:32:10")),
+            clip(100.0, "2024-05-01T10:10:13Z", Some("10:20:00:00")),
+        ];
+        assert_eq!(group_chapters(&paths, &infos), vec![0..2, 2..3]);
+
+        let paths = ["DJI_0001.MP4", "DJI_0002.MP4", "DJI_0003.MP4"].map(PathBuf::from);
+        let infos = [
+            clip(3599.0, "2024-12-31T23:00:00.000000Z", None),
+            clip(60.0, "2025-01-01T00:00:00.000000Z", None),
+            clip(60.0, "2025-01-01T00:05:00.000000Z", None),
+        ];
+        assert_eq!(group_chapters(&paths, &infos), vec![0..2, 2..3]);
+    }
+}
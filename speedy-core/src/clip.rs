@@ -0,0 +1,500 @@
+//! Input clips, optionally trimmed to part of their source.
+//!
+//! A [`Clip`] is a source file plus optional in/out points, so footage nobody
+//! wants (a drone's takeoff and landing, say) is left out without cutting the
+//! files first. Written as text, a clip is `path@START-END`,
+//! `path@START+DURATION`, `path@START-` or `path@-END`, with times in seconds
+//! or `[HH:]MM:SS[.fff]`; a bare path uses the whole file.
+//!
+//! A clip may also play at a speed of its own, before the job's speed: a
+//! `*SPEED` suffix (`transit.mp4@02:00-12:00*20`), optionally with the frame
+//! rate it is resampled to (`*20@30`).
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::error::{Result, SpeedyError};
+use crate::ffmpeg_wrapper::fps_string_value;
+
+/// One input of a job: a source file, or part of one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ClipEntry", into = "ClipEntry")]
+pub struct Clip {
+    pub path: PathBuf,
+    /// Seconds into the source where the clip begins; `None` is the start.
+    pub start: Option<f64>,
+    /// Where the clip ends; `None` is the end of the source.
+    pub end: Option<ClipEnd>,
+    /// Speed multiplier for this clip alone, applied before the job's own
+    /// speed; `None` plays it in real time.
+    pub speed: Option<f64>,
+    /// Frame rate the clip is resampled to after its speed change; `None` is
+    /// the source's own rate.
+    pub fps: Option<String>,
+}
+
+/// The out point of a [`Clip`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipEnd {
+    /// Seconds into the source.
+    At(f64),
+    /// Seconds after the clip's start.
+    After(f64),
+}
+
+impl Clip {
+    /// The whole of `path`.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            start: None,
+            end: None,
+            speed: None,
+            fps: None,
+        }
+    }
+
+    /// Begin `seconds` into the source.
+    pub fn start(mut self, seconds: f64) -> Self {
+        self.start = Some(seconds);
+        self
+    }
+
+    /// End `seconds` into the source.
+    pub fn end(mut self, seconds: f64) -> Self {
+        self.end = Some(ClipEnd::At(seconds));
+        self
+    }
+
+    /// End `seconds` after the clip's start.
+    pub fn duration(mut self, seconds: f64) -> Self {
+        self.end = Some(ClipEnd::After(seconds));
+        self
+    }
+
+    /// Play the clip `multiplier` times as fast, before the job's speed.
+    pub fn speed(mut self, multiplier: f64) -> Self {
+        self.speed = Some(multiplier);
+        self
+    }
+
+    /// Resample the clip to `fps` (e.g. `"30"` or `"30000/1001"`) after its
+    /// speed change.
+    pub fn fps(mut self, fps: &str) -> Self {
+        self.fps = Some(fps.to_string());
+        self
+    }
+
+    /// Whether the clip leaves out any of its source.
+    pub fn is_trimmed(&self) -> bool {
+        self.start.is_some() || self.end.is_some()
+    }
+
+    /// Whether the clip plays at a speed of its own.
+    pub fn is_retimed(&self) -> bool {
+        self.speed.is_some()
+    }
+
+    /// The `(start, end)` seconds of a `source_duration`-long source that
+    /// the clip uses. An out point past the end of the source is clamped to
+    /// it; an in point at or past the end is an error.
+    pub(crate) fn range(&self, source_duration: f64) -> Result<(f64, f64)> {
+        self.check()?;
+        let start = self.start.unwrap_or(0.0);
+        if start >= source_duration {
+            return Err(self.invalid(format!(
+                "starts at {start}, but the source is only {source}s long",
+                start = format_time(start),
+                source = format_seconds(source_duration)
+            )));
+        }
+        let end = match self.end {
+            None => source_duration,
+            Some(ClipEnd::At(end)) => end,
+            Some(ClipEnd::After(duration)) => start + duration,
+        };
+        if end > source_duration {
+            log::warn!(
+                "{self} ends past the end of its source ({source}s); using the rest of the file",
+                source = format_seconds(source_duration)
+            );
+        }
+        Ok((start, end.min(source_duration)))
+    }
+
+    /// Reject in/out points no source could satisfy, and a speed or frame
+    /// rate no filter could apply.
+    pub(crate) fn check(&self) -> Result<()> {
+        let start = self.start.unwrap_or(0.0);
+        if !start.is_finite() || start < 0.0 {
+            return Err(self.invalid(format!("start {start} must be a time in the source")));
+        }
+        if let Some(speed) = self.speed
+            && (!speed.is_finite() || speed <= 0.0)
+        {
+            return Err(self.invalid(format!("speed {speed} must be a positive, finite number")));
+        }
+        if let Some(fps) = &self.fps {
+            if fps_string_value(fps).is_none() {
+                return Err(self.invalid(format!(
+                    "frame rate {fps:?}; expected a positive number like \"30\" or \"30000/1001\""
+                )));
+            }
+            if self.speed.is_none() {
+                return Err(self.invalid(format!(
+                    "frame rate {fps} is what the clip's speed change is resampled to; give a speed too"
+                )));
+            }
+        }
+        match self.end {
+            Some(ClipEnd::At(end)) if !end.is_finite() || end <= start => {
+                Err(self.invalid(format!("end {end} must come after the start ({start})")))
+            }
+            Some(ClipEnd::After(duration)) if !duration.is_finite() || duration <= 0.0 => {
+                Err(self.invalid(format!("duration {duration} must be positive")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn invalid(&self, message: String) -> SpeedyError {
+        SpeedyError::invalid(
+            "clip",
+            format!("{path}: {message}", path = self.path.display()),
+        )
+    }
+}
+
+impl From<PathBuf> for Clip {
+    fn from(path: PathBuf) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&Path> for Clip {
+    fn from(path: &Path) -> Self {
+        Self::new(path)
+    }
+}
+
+/// Parses `path[@RANGE][*SPEED[@FPS]]`. Only the text after the last `*` is
+/// taken as a speed, and then the text after the last `@` as a range, so a
+/// file whose own name holds a `*` or an `@` still needs a speed or a range
+/// (e.g. `shot@home.mp4@0-10`) — or, on the command line, just to exist.
+impl FromStr for Clip {
+    type Err = SpeedyError;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let Some((clip, retime)) = spec.rsplit_once('*') else {
+            return parse_range(spec);
+        };
+        let (speed, fps) = match retime.split_once('@') {
+            Some((speed, fps)) => (speed, Some(fps.trim())),
+            None => (retime, None),
+        };
+        let speed: f64 = speed.trim().parse().map_err(|_| {
+            SpeedyError::invalid(
+                "clip",
+                format!("{spec:?}: expected a speed after the `*`, e.g. path*8 or path*8@30"),
+            )
+        })?;
+        let mut clip = parse_range(clip)?.speed(speed);
+        if let Some(fps) = fps {
+            clip = clip.fps(fps);
+        }
+        clip.check()?;
+        Ok(clip)
+    }
+}
+
+/// Parses `path[@RANGE]`, the part of a clip before any speed.
+fn parse_range(spec: &str) -> Result<Clip> {
+    let Some((path, range)) = spec.rsplit_once('@') else {
+        return Ok(Clip::new(spec));
+    };
+    let bad_range = || {
+        SpeedyError::invalid(
+            "clip",
+            format!(
+                "{spec:?}: expected path@START-END, path@START+DURATION, path@START- or path@-END"
+            ),
+        )
+    };
+    let time = |text: &str| parse_time(text).ok_or_else(bad_range);
+    let mut clip = Clip::new(path);
+    if let Some((start, duration)) = range.split_once('+') {
+        clip = clip.start(time(start)?).duration(time(duration)?);
+    } else if let Some((start, end)) = range.split_once('-') {
+        if start.trim().is_empty() && end.trim().is_empty() {
+            return Err(bad_range());
+        }
+        if !start.trim().is_empty() {
+            clip = clip.start(time(start)?);
+        }
+        if !end.trim().is_empty() {
+            clip = clip.end(time(end)?);
+        }
+    } else {
+        return Err(bad_range());
+    }
+    clip.check()?;
+    Ok(clip)
+}
+
+/// Renders in the syntax [`FromStr`] accepts.
+impl fmt::Display for Clip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{path}", path = self.path.display())?;
+        if self.is_trimmed() {
+            f.write_str("@")?;
+            if let Some(start) = self.start {
+                f.write_str(&format_time(start))?;
+            }
+            match self.end {
+                None => f.write_str("-")?,
+                Some(ClipEnd::At(end)) => write!(f, "-{end}", end = format_time(end))?,
+                Some(ClipEnd::After(duration)) => {
+                    write!(f, "+{duration}", duration = format_time(duration))?
+                }
+            }
+        }
+        if let Some(speed) = self.speed {
+            write!(f, "*{speed}")?;
+            if let Some(fps) = &self.fps {
+                write!(f, "@{fps}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a time as seconds (`80`, `4.5`) or `[HH:]MM:SS[.fff]`.
+pub fn parse_time(text: &str) -> Option<f64> {
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: f64 = part.parse().ok()?;
+        let last = i + 1 == parts.len();
+        // Minutes and seconds after a larger unit stay below 60; only the
+        // last field may be fractional.
+        if !value.is_finite()
+            || value < 0.0
+            || (i > 0 && value >= 60.0)
+            || (!last && value.fract() != 0.0)
+        {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Some(seconds)
+}
+
+/// Format seconds as `MM:SS` or `H:MM:SS`, with milliseconds when needed.
+fn format_time(seconds: f64) -> String {
+    let seconds = (seconds * 1000.0).round() / 1000.0;
+    let whole = seconds.trunc() as u64;
+    let (hours, minutes) = (whole / 3600, whole / 60 % 60);
+    let secs = seconds - (whole - whole % 60) as f64;
+    let secs = if secs.fract() == 0.0 {
+        format!("{secs:02}")
+    } else {
+        let text = format!("{secs:06.3}");
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    };
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs}")
+    } else {
+        format!("{minutes:02}:{secs}")
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    format!("{seconds:.2}")
+}
+
+/// How a [`Clip`] is written in a job spec: a bare path, or a table with
+/// in/out points and a speed.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ClipEntry {
+    Whole(PathBuf),
+    Trimmed(TrimmedEntry),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrimmedEntry {
+    path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<TimeEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end: Option<TimeEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<TimeEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    speed: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fps: Option<String>,
+}
+
+/// A time in a job spec: seconds, or a `[HH:]MM:SS[.fff]` string.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TimeEntry {
+    Seconds(f64),
+    Timestamp(String),
+}
+
+impl TryFrom<TimeEntry> for f64 {
+    type Error = SpeedyError;
+
+    fn try_from(entry: TimeEntry) -> Result<Self> {
+        match entry {
+            TimeEntry::Seconds(seconds) => Ok(seconds),
+            TimeEntry::Timestamp(text) => parse_time(&text).ok_or_else(|| {
+                SpeedyError::invalid(
+                    "clip",
+                    format!("{text:?} is not a time; expected seconds or [HH:]MM:SS[.fff]"),
+                )
+            }),
+        }
+    }
+}
+
+impl TryFrom<ClipEntry> for Clip {
+    type Error = SpeedyError;
+
+    fn try_from(entry: ClipEntry) -> Result<Self> {
+        let entry = match entry {
+            ClipEntry::Whole(path) => return Ok(Clip::new(path)),
+            ClipEntry::Trimmed(entry) => entry,
+        };
+        let mut clip = Clip::new(&entry.path);
+        if let Some(start) = entry.start {
+            clip = clip.start(start.try_into()?);
+        }
+        match (entry.end, entry.duration) {
+            (Some(_), Some(_)) => {
+                return Err(clip.invalid("give an end or a duration, not both".to_string()));
+            }
+            (Some(end), None) => clip = clip.end(end.try_into()?),
+            (None, Some(duration)) => clip = clip.duration(duration.try_into()?),
+            (None, None) => {}
+        }
+        clip.speed = entry.speed;
+        clip.fps = entry.fps;
+        clip.check()?;
+        Ok(clip)
+    }
+}
+
+impl From<Clip> for ClipEntry {
+    fn from(clip: Clip) -> Self {
+        if !clip.is_trimmed() && !clip.is_retimed() && clip.fps.is_none() {
+            return ClipEntry::Whole(clip.path);
+        }
+        let (end, duration) = match clip.end {
+            None => (None, None),
+            Some(ClipEnd::At(end)) => (Some(TimeEntry::Seconds(end)), None),
+            Some(ClipEnd::After(duration)) => (None, Some(TimeEntry::Seconds(duration))),
+        };
+        ClipEntry::Trimmed(TrimmedEntry {
+            path: clip.path,
+            start: clip.start.map(TimeEntry::Seconds),
+            end,
+            duration,
+            speed: clip.speed,
+            fps: clip.fps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_parse_every_range_form() -> anyhow::Result<()> {
+        assert_eq!(
+            "dji.mp4@00:05-01:20".parse::<Clip>()?,
+            Clip::new("dji.mp4").start(5.0).end(80.0)
+        );
+        assert_eq!(
+            "dji.mp4@1:00:00+90.5".parse::<Clip>()?,
+            Clip::new("dji.mp4").start(3600.0).duration(90.5)
+        );
+        assert_eq!(
+            "a@b.mp4@-10".parse::<Clip>()?,
+            Clip::new("a@b.mp4").end(10.0)
+        );
+        assert_eq!(
+            "dji.mp4@4-".parse::<Clip>()?,
+            Clip::new("dji.mp4").start(4.0)
+        );
+        assert_eq!("dji.mp4".parse::<Clip>()?, Clip::new("dji.mp4"));
+        assert_eq!(
+            "transit.mp4@02:00-12:00*20@30".parse::<Clip>()?,
+            Clip::new("transit.mp4")
+                .start(120.0)
+                .end(720.0)
+                .speed(20.0)
+                .fps("30")
+        );
+        assert_eq!(
+            "slow.mp4*0.25".parse::<Clip>()?,
+            Clip::new("slow.mp4").speed(0.25)
+        );
+        for spec in [
+            "a.mp4@",
+            "a.mp4@-",
+            "a.mp4@10-5",
+            "a.mp4@1:75",
+            "a.mp4@5+0",
+            "a.mp4*",
+            "a.mp4*0",
+            "a.mp4*fast",
+            "a.mp4*8@0",
+        ] {
+            assert!(spec.parse::<Clip>().is_err(), "{spec} should fail");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() -> anyhow::Result<()> {
+        for clip in [
+            Clip::new("a.mp4").start(5.0).end(80.0),
+            Clip::new("a.mp4").start(3725.25).duration(12.5),
+            Clip::new("a.mp4").end(30.0),
+            Clip::new("a.mp4").start(5.0).speed(8.0).fps("30000/1001"),
+            Clip::new("a.mp4").speed(2.5),
+        ] {
+            assert_eq!(clip.to_string().parse::<Clip>()?, clip, "{clip}");
+        }
+        assert_eq!(
+            Clip::new("a.mp4").start(5.0).end(80.0).to_string(),
+            "a.mp4@00:05-01:20"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn range_resolves_against_the_source_length() -> anyhow::Result<()> {
+        assert_eq!(Clip::new("a.mp4").range(60.0)?, (0.0, 60.0));
+        assert_eq!(
+            Clip::new("a.mp4").start(5.0).duration(10.0).range(60.0)?,
+            (5.0, 15.0)
+        );
+        assert_eq!(
+            Clip::new("a.mp4").start(5.0).end(90.0).range(60.0)?,
+            (5.0, 60.0)
+        );
+        assert!(Clip::new("a.mp4").start(60.0).range(60.0).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+//! The error type returned by speedy-core's public API.
+//!
+//! Each variant carries enough structured context for a caller (e.g. a service
+//! wrapping speedy-core) to react to the failure without parsing messages:
+//! a missing FFmpeg install, a probe failure on a specific input, a rejected
+//! parameter, a malformed LUT file, a missing encoder, exhausted stabilization
+//! retries, an ffmpeg run that exited non-zero (with the tail of its stderr), a
+//! cancelled job, or a job spec that could not be read.
+
+use regex::Regex;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Result alias used throughout speedy-core.
+pub type Result<T, E = SpeedyError> = std::result::Result<T, E>;
+
+/// How many trailing stderr lines a [`SpeedyError::FfmpegFailed`] keeps.
+/// ffmpeg prints its actual complaint last, after the banner and stream dump.
+const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum SpeedyError {
+    /// `ffmpeg` or `ffprobe` could not be launched (not installed / not on
+    /// `PATH`).
+    #[error("{tool} not found; please install FFmpeg")]
+    FfmpegNotFound {
+        tool: &'static str,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// ffprobe failed on an input, or its output could not be understood.
+    #[error("ffprobe failed on {path}: {message}", path = path.display())]
+    ProbeFailed { path: PathBuf, message: String },
+
+    /// A caller-supplied setting is out of range or malformed.
+    #[error("invalid {name}: {message}")]
+    InvalidParameter { name: &'static str, message: String },
+
+    /// A LUT file is malformed. `line` is the (1-based) line at fault, when
+    /// the problem is on one.
+    #[error("invalid LUT {path}{at}: {message}", path = path.display(), at = line_label(*line))]
+    InvalidLut {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String,
+    },
+
+    /// The requested encoder is not compiled into the installed ffmpeg.
+    #[error("encoder {encoder} is not available in this FFmpeg build")]
+    EncoderUnavailable { encoder: String },
+
+    /// A stabilization pass kept failing validation after every retry.
+    #[error("{pass} failed for {path} after {attempts} attempts", path = path.display())]
+    StabilizationFailed {
+        pass: &'static str,
+        path: PathBuf,
+        attempts: u32,
+    },
+
+    /// ffmpeg ran but exited unsuccessfully. `code` is `None` when it was
+    /// killed by a signal.
+    #[error("ffmpeg exited with {status}:\n{stderr_tail}", status = exit_label(*code))]
+    FfmpegFailed {
+        code: Option<i32>,
+        stderr_tail: String,
+    },
+
+    /// The job was stopped through its
+    /// [`CancellationToken`](crate::CancellationToken).
+    #[error("cancelled")]
+    Cancelled,
+
+    /// A [`JobSpec`](crate::JobSpec) could not be parsed or serialized.
+    #[error("invalid job spec: {message}")]
+    InvalidJob { message: String },
+
+    /// A filesystem or process-management operation failed.
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl SpeedyError {
+    /// Shorthand for [`SpeedyError::InvalidParameter`].
+    pub(crate) fn invalid(name: &'static str, message: impl Into<String>) -> Self {
+        SpeedyError::InvalidParameter {
+            name,
+            message: message.into(),
+        }
+    }
+
+    /// Wrap an I/O error with a description of what was being attempted.
+    pub(crate) fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        SpeedyError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Classify a failure to launch `tool`: a missing binary becomes
+    /// [`SpeedyError::FfmpegNotFound`], anything else an I/O error.
+    pub(crate) fn spawn(tool: &'static str, source: std::io::Error) -> Self {
+        if source.kind() == std::io::ErrorKind::NotFound {
+            SpeedyError::FfmpegNotFound { tool, source }
+        } else {
+            SpeedyError::io(format!("Failed to run {tool}"), source)
+        }
+    }
+
+    /// Classify an unsuccessful ffmpeg exit from its stderr: a missing encoder
+    /// gets its own variant, everything else keeps the exit code and the tail
+    /// of stderr.
+    pub(crate) fn from_ffmpeg_exit(code: Option<i32>, stderr: &str) -> Self {
+        if let Some(encoder) = unavailable_encoder(stderr) {
+            return SpeedyError::EncoderUnavailable { encoder };
+        }
+        SpeedyError::FfmpegFailed {
+            code,
+            stderr_tail: stderr_tail(stderr),
+        }
+    }
+}
+
+/// Render an exit code for messages (`code 1`, or `a signal` when killed).
+fn exit_label(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("code {code}"),
+        None => "a signal".to_string(),
+    }
+}
+
+/// Render a line number for messages (` (line 12)`, or nothing).
+fn line_label(line: Option<usize>) -> String {
+    line.map(|line| format!(" (line {line})"))
+        .unwrap_or_default()
+}
+
+/// The last [`STDERR_TAIL_LINES`] non-empty lines of ffmpeg's stderr.
+fn stderr_tail(stderr: &str) -> String {
+    let lines: Vec<&str> = stderr.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n")
+}
+
+/// The encoder ffmpeg reported as missing, if that is why it failed. Covers
+/// both `Unknown encoder 'libx265'` and the newer `Encoder libx265 not found`.
+fn unavailable_encoder(stderr: &str) -> Option<String> {
+    let patterns = [
+        r"Unknown encoder '([^']+)'",
+        r"Encoder '?([\w-]+)'? not found",
+    ];
+    patterns.iter().find_map(|pattern| {
+        Regex::new(pattern)
+            .ok()?
+            .captures(stderr)
+            .map(|caps| caps[1].to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_encoder_is_classified_from_stderr() {
+        for stderr in [
+            "Stream mapping: ...\nUnknown encoder 'libx265'\n",
+            "[vost#0:0 @ 0x5581] Encoder libx265 not found.\n",
+        ] {
+            assert!(
+                matches!(
+                    SpeedyError::from_ffmpeg_exit(Some(1), stderr),
+                    SpeedyError::EncoderUnavailable { ref encoder } if encoder == "libx265"
+                ),
+                "stderr: {stderr}"
+            );
+        }
+    }
+
+    #[test]
+    fn other_failures_keep_code_and_stderr_tail() {
+        let stderr: String = (0..50).map(|i| format!("line {i}\n")).collect();
+        let err = SpeedyError::from_ffmpeg_exit(Some(187), &stderr);
+        assert!(
+            matches!(
+                &err,
+                SpeedyError::FfmpegFailed { code: Some(187), stderr_tail }
+                    if stderr_tail.lines().count() == STDERR_TAIL_LINES
+                        && stderr_tail.ends_with("line 49")
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn spawn_not_found_means_ffmpeg_missing() {
+        let err = SpeedyError::spawn(
+            "ffprobe",
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+        );
+        assert!(matches!(
+            err,
+            SpeedyError::FfmpegNotFound {
+                tool: "ffprobe",
+                ..
+            }
+        ));
+    }
+}
@@ -1,11 +1,23 @@
-use anyhow::{Context, Result};
 use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::mpsc;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
 use std::thread;
 
+use crate::cancel::CancellationToken;
+use crate::canvas::Fill;
+use crate::error::{Result, SpeedyError};
+use crate::lut::LutKind;
+#[cfg(feature = "async")]
+use crate::progress::ProgressStream;
+use crate::progress::{ProgressEvent, ProgressParser};
+use crate::ramp::RampSegment;
+use crate::retime::Interpolation;
+use crate::transition::Transition;
+
 /// FFmpeg command builder with fluent interface
 #[derive(Debug, Clone)]
 pub struct FFmpegCommand {
@@ -27,12 +39,36 @@ pub struct FFmpegCommand {
     /// `(width, height, fps)` and concatenated via the concat filter so clips
     /// of differing resolution/orientation can be stitched into one output.
     concat_normalize: Option<(u32, u32, String)>,
+    /// How stitch mode fits an input of another aspect ratio into the
+    /// normalized frame.
+    concat_fill: Fill,
+    /// Per-input `(has_audio, duration_secs)` for stitch mode. When set, each
+    /// input's audio is normalized to a common rate/layout and concatenated in
+    /// sync with its video; inputs without audio contribute generated silence
+    /// of their duration.
+    concat_audio: Option<Vec<(bool, f64)>>,
+    /// Per-boundary transitions for stitch mode and each input's duration
+    /// (seconds), which places every `xfade`. Absent or all cuts joins the
+    /// inputs with a single concat filter.
+    concat_transitions: Option<(Vec<Transition>, Vec<f64>)>,
+    /// Per-input `(start, end)` seconds for stitch mode, `None` for an input
+    /// used whole. Trimmed in each input's branch of the graph.
+    concat_trims: Vec<Option<(f64, f64)>>,
+    /// Per-input `(speed, fps)` for stitch mode, `None` for an input played
+    /// in real time. Retimed in each input's branch, after its trim.
+    concat_speeds: Vec<Option<(f64, String)>>,
+    /// `(start, end)` seconds of every input to use, by input seeking.
+    trim: Option<(f64, f64)>,
     /// Known total duration in seconds, used for progress because the concat
     /// filter does not produce a single `Duration` line FFmpeg can report.
     total_duration: Option<f64>,
     /// When set, the output carries no audio (audio is neither filtered nor
     /// mapped). Used for stabilization intermediates.
     video_only: bool,
+    /// When set, the output carries no video (video is neither filtered nor
+    /// mapped). Used to grade a clip's audio separately from its video on the
+    /// stabilization path.
+    audio_only: bool,
     /// Working directory for the ffmpeg process. Lets filter option values
     /// reference files by name (avoiding filtergraph path-escaping pitfalls with
     /// colons/backslashes in absolute paths).
@@ -40,6 +76,12 @@ pub struct FFmpegCommand {
     /// When set, disable ffmpeg's automatic rotation (`-noautorotate`) on every
     /// input, so footage keeps its stored orientation.
     no_autorotate: bool,
+    /// When set, [`execute`](Self::execute) kills ffmpeg as soon as the token
+    /// is tripped.
+    cancel: Option<CancellationToken>,
+    /// Options placed before every `-i` (e.g. `-f lavfi`, or `-f concat -safe 0`
+    /// for a concat-demuxer list).
+    input_options: Vec<String>,
 }
 
 impl FFmpegCommand {
@@ -67,10 +109,19 @@ impl FFmpegCommand {
             metadata_args: Vec::new(),
             hw_accel: None,
             concat_normalize: None,
+            concat_fill: Fill::Pad,
+            concat_audio: None,
+            concat_transitions: None,
+            concat_trims: Vec::new(),
+            concat_speeds: Vec::new(),
+            trim: None,
             total_duration: None,
             video_only: false,
+            audio_only: false,
             working_dir: None,
             no_autorotate: false,
+            cancel: None,
+            input_options: Vec::new(),
         }
     }
 
@@ -78,15 +129,82 @@ impl FFmpegCommand {
     /// aspect, padded if needed), auto-rotated, set to `fps`, then concatenated.
     /// Any configured video filters (e.g. a LUT) are applied once after the join.
     ///
-    /// Note: the stitched output is currently video-only; audio tracks are not
-    /// concatenated.
+    /// The stitched output is video-only unless
+    /// [`concat_audio`](Self::concat_audio) is also set.
     pub fn concat_normalize(mut self, width: u32, height: u32, fps: &str) -> Self {
         self.concat_normalize = Some((width, height, fps.to_string()));
         self
     }
 
-    /// Provide a known total duration (seconds) for progress reporting.
-    /// Needed for concat, where FFmpeg cannot report a single Duration line.
+    /// How [`concat_normalize`](Self::concat_normalize) fits an input of
+    /// another aspect ratio into the common frame (default [`Fill::Pad`]).
+    pub fn concat_fill(mut self, fill: Fill) -> Self {
+        self.concat_fill = fill;
+        self
+    }
+
+    /// Carry audio through stitch mode. `clips` holds one `(has_audio,
+    /// duration_secs)` per input, in input order. Each clip's audio is resampled
+    /// to [`STITCH_AUDIO_RATE`] stereo and padded/trimmed to the clip's duration
+    /// so the joined track stays in sync with the video; clips without audio get
+    /// generated silence instead. Configured audio filters (e.g. the speed
+    /// change's `atempo` chain) are applied once to the joined track.
+    pub fn concat_audio(mut self, clips: Vec<(bool, f64)>) -> Self {
+        self.concat_audio = Some(clips);
+        self
+    }
+
+    /// Join stitched inputs with transitions instead of hard cuts.
+    /// `transitions[k]` joins input `k` to input `k + 1`, so there is one fewer
+    /// than there are inputs; `clip_durations` holds each input's duration in
+    /// seconds. An `xfade` overlaps the two clips by its duration (their audio
+    /// is crossfaded with `acrossfade` over the same span), so the output is
+    /// shorter than the clips laid end to end; callers should account for that
+    /// in [`total_duration`](Self::total_duration).
+    pub fn concat_transitions(
+        mut self,
+        transitions: Vec<Transition>,
+        clip_durations: Vec<f64>,
+    ) -> Self {
+        self.concat_transitions = Some((transitions, clip_durations));
+        self
+    }
+
+    /// Use only part of each stitched input: `trims` holds one `(start, end)`
+    /// in seconds per input, in input order, or `None` for an input used
+    /// whole. Each trim is applied at the head of the input's branch, before
+    /// normalization, so durations given to [`concat_audio`](Self::concat_audio)
+    /// and [`concat_transitions`](Self::concat_transitions) are the trimmed ones.
+    pub fn concat_trims(mut self, trims: Vec<Option<(f64, f64)>>) -> Self {
+        self.concat_trims = trims;
+        self
+    }
+
+    /// Play each stitched input at a speed of its own: `speeds` holds one
+    /// `(speed, fps)` per input, in input order, or `None` for an input played
+    /// in real time. Each input is retimed (video resampled to `fps`, audio
+    /// retimed with `atempo`) in its branch, right after its trim, so durations
+    /// given to [`concat_audio`](Self::concat_audio) and
+    /// [`concat_transitions`](Self::concat_transitions) are the retimed ones.
+    /// A [`speed`](Self::speed) change still applies once, to the joined
+    /// timeline.
+    pub fn concat_speeds(mut self, speeds: Vec<Option<(f64, String)>>) -> Self {
+        self.concat_speeds = speeds;
+        self
+    }
+
+    /// Use only `start..end` seconds of the input. The input is seeked
+    /// (`-ss`/`-to` before `-i`), so decoding begins near the in point rather
+    /// than at the top of the file. This is the single-input equivalent of
+    /// [`concat_trims`](Self::concat_trims).
+    pub fn trim(mut self, start: f64, end: f64) -> Self {
+        self.trim = Some((start, end));
+        self
+    }
+
+    /// Provide the expected output duration (seconds) for progress reporting.
+    /// Needed for concat, where FFmpeg cannot report a single Duration line,
+    /// and when a speed change makes the output shorter than the input.
     pub fn total_duration(mut self, seconds: f64) -> Self {
         self.total_duration = Some(seconds);
         self
@@ -98,6 +216,12 @@ impl FFmpegCommand {
         self
     }
 
+    /// Drop video entirely: do not build video filters, and map only audio.
+    pub fn audio_only(mut self) -> Self {
+        self.audio_only = true;
+        self
+    }
+
     /// Run ffmpeg from this working directory, so filter option values can
     /// reference files by name and avoid filtergraph path-escaping issues.
     pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
@@ -105,6 +229,13 @@ impl FFmpegCommand {
         self
     }
 
+    /// Kill the running ffmpeg (returning [`SpeedyError::Cancelled`]) when
+    /// `token` is tripped.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
     /// Disable ffmpeg's automatic rotation on every input (`-noautorotate`), so
     /// the stored orientation is kept as-is.
     pub fn disable_autorotate(mut self) -> Self {
@@ -118,11 +249,15 @@ impl FFmpegCommand {
     /// on the common frame, and so per-segment stabilized clips share a frame
     /// rate / time base before a stream-copy concat. This is the per-clip
     /// equivalent of [`concat_normalize`](Self::concat_normalize).
-    pub fn scale_pad(mut self, width: u32, height: u32, fps: &str) -> Self {
-        self.video_filters.push(format!(
-            "scale={width}:{height}:force_original_aspect_ratio=decrease,\
-             pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps}"
-        ));
+    pub fn scale_pad(self, width: u32, height: u32, fps: &str) -> Self {
+        self.scale_fill(width, height, fps, Fill::Pad)
+    }
+
+    /// [`scale_pad`](Self::scale_pad), fitting the input into the frame as
+    /// `fill` says instead of always padding.
+    pub fn scale_fill(mut self, width: u32, height: u32, fps: &str, fill: Fill) -> Self {
+        self.video_filters
+            .push(fit_chain(width, height, fps, fill, "0"));
         self
     }
 
@@ -207,37 +342,100 @@ impl FFmpegCommand {
             // setpts so any later per-frame filters (e.g. a LUT) only process
             // the frames that survive decimation.
             if let Some(fps) = output_fps {
-                self.video_filters.push(format!("fps={fps}"));
+                self.video_filters
+                    .push(resample_filter(fps, Interpolation::None));
             }
 
             // Audio speed adjustment (with pitch correction) - only if audio exists
             if has_audio {
-                if (0.5..=2.0).contains(&multiplier) {
-                    self.audio_filters.push(format!("atempo={:.4}", multiplier));
-                } else {
-                    // For speeds outside 0.5-2.0 range, chain multiple atempo filters
-                    let mut current = multiplier;
-                    while current > 2.0 {
-                        self.audio_filters.push("atempo=2.0".to_string());
-                        current /= 2.0;
-                    }
-                    if current > 1.0 {
-                        self.audio_filters.push(format!("atempo={:.4}", current));
-                    }
-
-                    while current < 0.5 {
-                        self.audio_filters.push("atempo=0.5".to_string());
-                        current *= 2.0;
-                    }
-                    if current < 1.0 {
-                        self.audio_filters.push(format!("atempo={:.4}", current));
-                    }
-                }
+                self.audio_filters.extend(atempo_chain(multiplier));
             }
         }
         self
     }
 
+    /// Resample the (retimed) video to `fps`, filling frames in as
+    /// `interpolation` says. Use after [`speed`](Self::speed) or
+    /// [`speed_ramp`](Self::speed_ramp) given no `output_fps`, for a slow-down
+    /// that should not just repeat frames; `fps` may be above the source rate
+    /// to make it smoother still.
+    pub fn resample(mut self, fps: &str, interpolation: Interpolation) -> Self {
+        self.video_filters.push(resample_filter(fps, interpolation));
+        self
+    }
+
+    /// Play the input backwards (`reverse`, and `areverse` when `has_audio`).
+    /// Both filters hold the whole input in memory before emitting a frame, so
+    /// long footage is best reversed in chunks.
+    pub fn reverse(mut self, has_audio: bool) -> Self {
+        self.video_filters.push("reverse".to_string());
+        if has_audio {
+            self.audio_filters.push("areverse".to_string());
+        }
+        self
+    }
+
+    /// Play the input forward then backwards, `loops` times over (at least
+    /// once). Like [`reverse`](Self::reverse) this holds the input in memory,
+    /// once per reversed or repeated copy.
+    pub fn boomerang(mut self, loops: u32, has_audio: bool) -> Self {
+        self.video_filters.push(boomerang_chain(
+            loops, "split", "reverse", "boom", "v=1:a=0",
+        ));
+        if has_audio {
+            self.audio_filters.push(boomerang_chain(
+                loops, "asplit", "areverse", "aboom", "v=0:a=1",
+            ));
+        }
+        self
+    }
+
+    /// Average every `frames` consecutive frames (`tmix`), so a speed-up
+    /// resampled afterwards keeps blurred frames instead of sharp, strobing
+    /// ones. Use after [`speed`](Self::speed) given no `output_fps`, followed
+    /// by [`resample`](Self::resample). No-op for 1 frame or fewer.
+    pub fn motion_blur(mut self, frames: u32) -> Self {
+        if frames > 1 {
+            self.video_filters.push(format!("tmix=frames={frames}"));
+        }
+        self
+    }
+
+    /// Change the speed over time: play each of `segments` (constant-speed
+    /// spans covering the input, in order, e.g. from
+    /// [`SpeedRamp::segments`](crate::SpeedRamp::segments)) at its own speed.
+    ///
+    /// The video is retimed by one `setpts` expression summing the time each
+    /// span contributes, then resampled to `output_fps` as with
+    /// [`speed`](Self::speed). The audio is split at the same points, each
+    /// piece retimed with `atempo` and the pieces joined again; a piece faster
+    /// or slower than [`RAMP_AUDIO_SPEEDS`] is muted rather than turned into a
+    /// chirp or a drone.
+    pub fn speed_ramp(
+        mut self,
+        segments: &[RampSegment],
+        has_audio: bool,
+        output_fps: Option<&str>,
+    ) -> Self {
+        if segments.iter().all(|s| s.speed == 1.0) {
+            return self;
+        }
+        // Rebased first, so `T` counts from the start of the (joined) input.
+        self.video_filters.push("setpts=PTS-STARTPTS".to_string());
+        self.video_filters.push(format!(
+            "setpts='({ramp})/TB'",
+            ramp = ramp_expression(segments)
+        ));
+        if let Some(fps) = output_fps {
+            self.video_filters
+                .push(resample_filter(fps, Interpolation::None));
+        }
+        if has_audio {
+            self.audio_filters.push(ramp_audio_chain(segments));
+        }
+        self
+    }
+
     /// Apply contrast adjustment
     pub fn contrast(mut self, value: f32) -> Self {
         self.video_filters.push(format!("eq=contrast={:.2}", value));
@@ -271,6 +469,43 @@ impl FFmpegCommand {
         self
     }
 
+    /// Apply a 1D LUT (one curve per channel), quoted as for
+    /// [`lut3d`](Self::lut3d).
+    pub fn lut1d(mut self, lut_file: impl AsRef<Path>) -> Self {
+        let escaped = lut_file.as_ref().to_string_lossy().replace('\'', "'\\''");
+        self.video_filters.push(format!("lut1d=file='{escaped}'"));
+        self
+    }
+
+    /// Apply a LUT of the given `kind` at `strength` (0.0-1.0): below full
+    /// strength the frame is split, one copy graded, and the two mixed
+    /// (`mix`) with `strength` of the graded copy. Both copies are converted
+    /// to 16-bit planar RGB first, which the LUT filters work in anyway, so
+    /// they match for the mix without losing precision.
+    pub fn lut_at_strength(
+        mut self,
+        lut_file: impl AsRef<Path>,
+        kind: LutKind,
+        strength: f32,
+    ) -> Self {
+        let graded = match kind {
+            LutKind::OneD => "lut1d",
+            LutKind::ThreeD => "lut3d",
+        };
+        let escaped = lut_file.as_ref().to_string_lossy().replace('\'', "'\\''");
+        let lut = format!("{graded}=file='{escaped}'");
+        if strength >= 1.0 {
+            self.video_filters.push(lut);
+            return self;
+        }
+        let ungraded = 1.0 - strength;
+        self.video_filters.push(format!(
+            "format=gbrp16le,split[lutsrc][lutin];[lutin]{lut}[lutout];\
+             [lutsrc][lutout]mix=inputs=2:weights='{ungraded:.3} {strength:.3}'"
+        ));
+        self
+    }
+
     /// Apply video stabilization
     pub fn stabilize(mut self) -> Self {
         self.video_filters.push("deshake".to_string());
@@ -425,6 +660,13 @@ impl FFmpegCommand {
         self
     }
 
+    /// Add options that apply to every input (placed before each `-i`), such
+    /// as `-f lavfi` to read a generated source.
+    pub fn input_options(mut self, args: Vec<String>) -> Self {
+        self.input_options.extend(args);
+        self
+    }
+
     /// Add custom FFmpeg arguments
     pub fn custom_args(mut self, args: Vec<String>) -> Self {
         self.extra_args.extend(args);
@@ -444,6 +686,12 @@ impl FFmpegCommand {
 
     /// Build the FFmpeg command
     pub fn build(&self) -> Command {
+        self.command(false)
+    }
+
+    /// Build the command, optionally with machine-readable progress on stdout
+    /// (as [`execute`](Self::execute) runs it).
+    fn command(&self, progress: bool) -> Command {
         let mut cmd = Command::new("ffmpeg");
 
         // Run from the configured working directory, if any.
@@ -455,6 +703,9 @@ impl FFmpegCommand {
         if self.overwrite {
             cmd.arg("-y");
         }
+        if progress {
+            cmd.args(["-progress", "pipe:1", "-nostats"]);
+        }
 
         // Hardware acceleration (applies to the inputs that follow)
         if let Some(ref hw) = self.hw_accel {
@@ -468,6 +719,10 @@ impl FFmpegCommand {
             if self.no_autorotate {
                 cmd.arg("-noautorotate");
             }
+            cmd.args(&self.input_options);
+            if let Some((start, end)) = self.trim {
+                cmd.args(["-ss", &format!("{start:.6}"), "-to", &format!("{end:.6}")]);
+            }
             cmd.arg("-i").arg(input);
         }
 
@@ -479,22 +734,73 @@ impl FFmpegCommand {
             // down to fit and padding to keep aspect), concatenate them, then
             // apply the shared video filter chain (e.g. the LUT) once.
             let n = self.inputs.len();
+            let audio = self
+                .concat_audio
+                .as_ref()
+                .filter(|clips| !self.video_only && clips.len() == n);
             let mut graph = String::new();
             for i in 0..n {
                 // setpts=PTS-STARTPTS rebases each segment to start at 0, which
                 // the concat filter requires; otherwise clips with non-zero
                 // start PTS (trimmed sources, MP4 edit lists) can produce gaps
                 // or non-monotonic-timestamp failures.
+                let trim = match self.concat_trims.get(i).copied().flatten() {
+                    // Rebased first, so in/out points count from the start of
+                    // the file whatever its first timestamp.
+                    Some((start, end)) => {
+                        format!("setpts=PTS-STARTPTS,trim=start={start:.6}:end={end:.6},")
+                    }
+                    None => String::new(),
+                };
+                let retime = match self.concat_speeds.get(i).cloned().flatten() {
+                    Some((speed, fps)) => format!(
+                        "setpts={factor:.4}*(PTS-STARTPTS),{resample},",
+                        factor = 1.0 / speed,
+                        resample = resample_filter(&fps, Interpolation::None)
+                    ),
+                    None => String::new(),
+                };
                 graph.push_str(&format!(
-                    "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,\
-                     pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},\
-                     setpts=PTS-STARTPTS[v{i}];"
+                    "[{i}:v]{trim}{retime}{fit},setpts=PTS-STARTPTS[v{i}];",
+                    fit = fit_chain(w, h, fps, self.concat_fill, &i.to_string())
                 ));
             }
-            for i in 0..n {
-                graph.push_str(&format!("[v{i}]"));
+            if let Some(clips) = audio {
+                for (i, &(has_audio, duration)) in clips.iter().enumerate() {
+                    let trim = self.concat_trims.get(i).copied().flatten();
+                    let speed = self.concat_speeds.get(i).cloned().flatten();
+                    graph.push_str(&stitch_audio_branch(
+                        i,
+                        has_audio,
+                        duration,
+                        trim,
+                        speed.map(|(speed, _)| speed),
+                    ));
+                }
+            }
+            let transitions =
+                self.concat_transitions
+                    .as_ref()
+                    .filter(|(transitions, durations)| {
+                        transitions.len() + 1 == n
+                            && durations.len() == n
+                            && transitions.iter().any(|t| *t != Transition::Cut)
+                    });
+            if let Some((transitions, durations)) = transitions {
+                graph.push_str(&transition_chain(transitions, durations, audio.is_some()));
+            } else {
+                for i in 0..n {
+                    graph.push_str(&format!("[v{i}]"));
+                    if audio.is_some() {
+                        graph.push_str(&format!("[a{i}]"));
+                    }
+                }
+                if audio.is_some() {
+                    graph.push_str(&format!("concat=n={n}:v=1:a=1[cat][acat]"));
+                } else {
+                    graph.push_str(&format!("concat=n={n}:v=1[cat]"));
+                }
             }
-            graph.push_str(&format!("concat=n={n}:v=1[cat]"));
             // Normalize to a codec-friendly pixel format: RGB-producing filters
             // such as lut3d would otherwise leave the stream as gbrp (planar
             // RGB), which many encoders/players cannot handle.
@@ -503,18 +809,31 @@ impl FFmpegCommand {
             } else {
                 graph.push_str(&format!(";[cat]{video_chain},format={out_fmt}[v]"));
             }
+            // The speed change's atempo chain runs once over the joined track.
+            if audio.is_some() {
+                if self.audio_filters.is_empty() {
+                    graph.push_str(";[acat]anull[a]");
+                } else {
+                    graph.push_str(&format!(
+                        ";[acat]{audio_chain}[a]",
+                        audio_chain = self.audio_filters.join(",")
+                    ));
+                }
+            }
 
             cmd.arg("-filter_complex");
             cmd.arg(&graph);
-            // Stitched clips are treated as video-only (no synchronized audio).
             cmd.args(["-map", "[v]"]);
+            if audio.is_some() {
+                cmd.args(["-map", "[a]"]);
+            }
         } else {
             // Single-input mode: apply video/audio filters to input 0.
             let mut filter_complex = String::new();
             let mut has_video_filters = false;
             let mut has_audio_filters = false;
 
-            if !self.video_filters.is_empty() {
+            if !self.audio_only && !self.video_filters.is_empty() {
                 // The trailing format guards against RGB-producing filters (e.g.
                 // lut3d) leaving the output as gbrp, which breaks many encoders.
                 filter_complex.push_str(&format!("[0:v]{video_chain},format={out_fmt}[v]"));
@@ -536,7 +855,7 @@ impl FFmpegCommand {
                 // Map the filtered outputs
                 if has_video_filters {
                     cmd.args(["-map", "[v]"]);
-                } else {
+                } else if !self.audio_only {
                     cmd.args(["-map", "0:v?"]);
                 }
 
@@ -552,6 +871,9 @@ impl FFmpegCommand {
             if self.video_only {
                 cmd.arg("-an");
             }
+            if self.audio_only {
+                cmd.arg("-vn");
+            }
         }
 
         // Video codec
@@ -598,111 +920,526 @@ impl FFmpegCommand {
         cmd
     }
 
-    /// Execute the FFmpeg command with progress tracking
+    /// Execute the FFmpeg command, reporting structured progress.
+    ///
+    /// ffmpeg runs with `-progress pipe:1`; every progress block it writes to
+    /// stdout reaches `progress_callback` as a [`ProgressEvent`] (stage
+    /// `"encode"`). stderr is drained alongside for the input `Duration:` (used
+    /// when no [`total_duration`](Self::total_duration) was given) and for the
+    /// error tail on failure. The call blocks until ffmpeg exits, so the
+    /// callback may borrow from the caller. A tripped
+    /// [`cancellation`](Self::cancellation) token kills ffmpeg and returns
+    /// [`SpeedyError::Cancelled`].
     pub fn execute<F>(&self, progress_callback: F) -> Result<()>
     where
-        F: Fn(f64, String) + Send + 'static,
+        F: Fn(&ProgressEvent) + Sync,
     {
-        let mut cmd = self.build();
-        // Progress is parsed from stderr; stdout is unused. Discard it rather
-        // than pipe-without-draining, which could deadlock if ffmpeg writes a
-        // lot to stdout (e.g. a stream muxed to "-").
-        cmd.stdout(Stdio::null()).stderr(Stdio::piped());
-
-        log::info!("Executing FFmpeg command: {:?}", cmd);
+        self.check_not_cancelled()?;
+        let mut cmd = self.command(true);
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        log::info!("Executing FFmpeg command: {cmd:?}");
+
+        let mut child = cmd.spawn().map_err(|e| SpeedyError::spawn("ffmpeg", e))?;
+        let stdout = child.stdout.take().ok_or_else(|| not_piped("stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| not_piped("stderr"))?;
+        let watch = OutputWatch::new(self.total_duration);
+
+        let (status, all_output) = thread::scope(|scope| {
+            let watch = &watch;
+            let callback = &progress_callback;
+
+            scope.spawn(move || {
+                let mut parser = ProgressParser::new(watch.total_override);
+                for line in BufReader::new(stdout)
+                    .lines()
+                    .map_while(std::result::Result::ok)
+                {
+                    watch.progress_line(&mut parser, &line, callback);
+                }
+            });
 
-        // Debug: print the exact command being run
-        let cmd_string = format!("{:?}", cmd);
-        log::debug!("Raw command: {}", cmd_string);
+            let stderr_reader = scope.spawn(move || {
+                let mut all_output = String::new();
+                for line in BufReader::new(stderr)
+                    .lines()
+                    .map_while(std::result::Result::ok)
+                {
+                    watch.stderr_line(&line, &mut all_output);
+                }
+                all_output
+            });
+
+            let status = match &self.cancel {
+                Some(token) => wait_or_kill(&mut child, token),
+                None => child.wait().map(Some),
+            };
+            let all_output = stderr_reader.join().unwrap_or_else(|_| {
+                log::warn!("FFmpeg stderr reader panicked");
+                String::new()
+            });
+            (status, all_output)
+        });
 
-        let mut child = cmd.spawn().context("Failed to spawn FFmpeg process")?;
+        finish_run(status, &all_output)
+    }
+
+    /// Async twin of [`execute`](Self::execute): ffmpeg runs under
+    /// `tokio::process` and its progress is delivered as a [`ProgressStream`],
+    /// without a blocking thread. Await the returned future (or spawn it) to
+    /// run the command; the stream ends when it finishes. Dropping the future
+    /// kills ffmpeg.
+    #[cfg(feature = "async")]
+    pub fn execute_async(
+        &self,
+    ) -> (
+        ProgressStream,
+        impl std::future::Future<Output = Result<()>> + Send + 'static,
+    ) {
+        let (sink, stream) = ProgressStream::channel();
+        let cmd = self.clone();
+        (stream, async move { cmd.run_async(&sink).await })
+    }
+
+    /// Run the command on the async runtime, reporting to `progress_callback`.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async(
+        &self,
+        progress_callback: &(dyn Fn(&ProgressEvent) + Sync),
+    ) -> Result<()> {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+
+        self.check_not_cancelled()?;
+        let mut cmd = tokio::process::Command::from(self.command(true));
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        log::info!("Executing FFmpeg command: {cmd:?}");
+
+        let mut child = cmd.spawn().map_err(|e| SpeedyError::spawn("ffmpeg", e))?;
+        let stdout = child.stdout.take().ok_or_else(|| not_piped("stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| not_piped("stderr"))?;
+        let watch = OutputWatch::new(self.total_duration);
+
+        let read_progress = async {
+            let mut parser = ProgressParser::new(watch.total_override);
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                watch.progress_line(&mut parser, &line, progress_callback);
+            }
+        };
+        let read_stderr = async {
+            let mut all_output = String::new();
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                watch.stderr_line(&line, &mut all_output);
+            }
+            all_output
+        };
+        let wait = async {
+            match &self.cancel {
+                Some(token) => wait_or_kill_async(&mut child, token).await,
+                None => child.wait().await.map(Some),
+            }
+        };
+        let ((), all_output, status) = tokio::join!(read_progress, read_stderr, wait);
 
-        // Set up progress monitoring
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| anyhow::anyhow!("Failed to capture stderr"))?;
+        finish_run(status, &all_output)
+    }
 
-        let (tx, rx) = mpsc::channel();
+    /// Refuse to start once the cancellation token has been tripped.
+    fn check_not_cancelled(&self) -> Result<()> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(SpeedyError::Cancelled);
+        }
+        Ok(())
+    }
+}
 
-        // Use the caller-provided duration when available (e.g. concat input,
-        // where FFmpeg cannot report a real Duration line).
-        let total_override = self.total_duration;
+/// What the stdout (progress) and stderr readers of one ffmpeg run share.
+struct OutputWatch {
+    /// Expected output duration given by the caller, if any.
+    total_override: Option<f64>,
+    /// Input duration scraped from stderr, when the caller gave none.
+    probed_duration: OnceLock<f64>,
+    duration_regex: Option<Regex>,
+}
 
-        // Spawn thread to read stderr and parse progress
-        let reader_thread = thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            let duration_regex = Regex::new(r"Duration: (\d{2}):(\d{2}):(\d{2})\.(\d{2})").unwrap();
-            let progress_regex = Regex::new(r"time=(\d{2}):(\d{2}):(\d{2})\.(\d{2})").unwrap();
+impl OutputWatch {
+    fn new(total_override: Option<f64>) -> Self {
+        Self {
+            total_override,
+            probed_duration: OnceLock::new(),
+            duration_regex: Regex::new(DURATION_PATTERN).ok(),
+        }
+    }
 
-            let mut total_duration: Option<f64> = total_override;
-            let mut all_output = String::new();
+    /// Feed one `-progress` line, reporting each completed block.
+    fn progress_line(
+        &self,
+        parser: &mut ProgressParser,
+        line: &str,
+        callback: &(dyn Fn(&ProgressEvent) + Sync),
+    ) {
+        if let Some(seconds) = self.probed_duration.get() {
+            parser.set_total_duration(*seconds);
+        }
+        if let Some(mut event) = parser.feed(line) {
+            event.stage = "encode".to_string();
+            callback(&event);
+        }
+    }
 
-            for line in reader.lines().map_while(Result::ok) {
-                all_output.push_str(&line);
-                all_output.push('\n');
+    /// Record one stderr line, picking up the input duration on the way.
+    fn stderr_line(&self, line: &str, all_output: &mut String) {
+        if self.total_override.is_none()
+            && self.probed_duration.get().is_none()
+            && let Some(seconds) = self
+                .duration_regex
+                .as_ref()
+                .and_then(|re| parse_duration_line(re, line))
+        {
+            let _ = self.probed_duration.set(seconds);
+        }
+        all_output.push_str(line);
+        all_output.push('\n');
+    }
+}
 
-                // Parse total duration
-                if total_duration.is_none()
-                    && let Some(caps) = duration_regex.captures(&line)
-                {
-                    let hours: f64 = caps[1].parse().unwrap_or(0.0);
-                    let minutes: f64 = caps[2].parse().unwrap_or(0.0);
-                    let seconds: f64 = caps[3].parse().unwrap_or(0.0);
-                    let centis: f64 = caps[4].parse().unwrap_or(0.0);
-                    total_duration =
-                        Some(hours * 3600.0 + minutes * 60.0 + seconds + centis / 100.0);
-                }
+fn not_piped(stream: &str) -> SpeedyError {
+    SpeedyError::io(
+        format!("Failed to capture FFmpeg {stream}"),
+        std::io::Error::other(format!("{stream} was not piped")),
+    )
+}
 
-                // Parse current progress
-                if let Some(caps) = progress_regex.captures(&line) {
-                    let hours: f64 = caps[1].parse().unwrap_or(0.0);
-                    let minutes: f64 = caps[2].parse().unwrap_or(0.0);
-                    let seconds: f64 = caps[3].parse().unwrap_or(0.0);
-                    let centis: f64 = caps[4].parse().unwrap_or(0.0);
-                    let current_time = hours * 3600.0 + minutes * 60.0 + seconds + centis / 100.0;
-
-                    if let Some(duration) = total_duration {
-                        let progress = (current_time / duration * 100.0).min(100.0);
-                        let _ = tx.send((progress, line.clone()));
-                    }
-                }
+/// Classify how an ffmpeg run ended: `Ok(None)` means it was killed on
+/// cancellation.
+fn finish_run(status: std::io::Result<Option<ExitStatus>>, all_output: &str) -> Result<()> {
+    let status = status
+        .map_err(|e| SpeedyError::io("Failed to wait for FFmpeg process", e))?
+        .ok_or(SpeedyError::Cancelled)?;
+    if !status.success() {
+        log::error!("FFmpeg failed with output:\n{all_output}");
+        return Err(SpeedyError::from_ffmpeg_exit(status.code(), all_output));
+    }
+    Ok(())
+}
 
-                // Also send the raw line for debugging
-                let _ = tx.send((0.0, line));
+/// How often a cancellable [`FFmpegCommand::execute`] checks its token.
+const CANCEL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Wait for `child` to exit, killing it once `token` is tripped. Returns `None`
+/// when it was killed.
+fn wait_or_kill(
+    child: &mut Child,
+    token: &CancellationToken,
+) -> std::io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if token.is_cancelled() {
+            log::info!("Cancelling FFmpeg (pid {pid})", pid = child.id());
+            if let Err(e) = child.kill() {
+                log::debug!("could not kill FFmpeg: {e}");
             }
-            all_output
-        });
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(CANCEL_POLL_INTERVAL);
+    }
+}
 
-        // Process progress updates
-        thread::spawn(move || {
-            while let Ok((progress, _message)) = rx.recv() {
-                if progress > 0.0 {
-                    progress_callback(progress, format!("Processing: {:.1}%", progress));
+/// [`wait_or_kill`] on the async runtime.
+#[cfg(feature = "async")]
+async fn wait_or_kill_async(
+    child: &mut tokio::process::Child,
+    token: &CancellationToken,
+) -> std::io::Result<Option<ExitStatus>> {
+    loop {
+        tokio::select! {
+            status = child.wait() => return status.map(Some),
+            () = tokio::time::sleep(CANCEL_POLL_INTERVAL) => {
+                if token.is_cancelled() {
+                    log::info!("Cancelling FFmpeg (pid {pid:?})", pid = child.id());
+                    child.kill().await?;
+                    return Ok(None);
                 }
             }
-        });
+        }
+    }
+}
 
-        // Wait for FFmpeg to complete
-        let status = child.wait().context("Failed to wait for FFmpeg process")?;
+/// ffmpeg's per-input `Duration: HH:MM:SS.cc` stderr line.
+const DURATION_PATTERN: &str = r"Duration: (\d{2}):(\d{2}):(\d{2}(?:\.\d+)?)";
 
-        // Wait for reader thread to finish and get all output
-        let all_output = reader_thread
-            .join()
-            .unwrap_or_else(|_| String::from("Failed to get output"));
+/// Seconds from a `Duration: HH:MM:SS.cc` stderr line.
+fn parse_duration_line(regex: &Regex, line: &str) -> Option<f64> {
+    let caps = regex.captures(line)?;
+    let hours: f64 = caps[1].parse().ok()?;
+    let minutes: f64 = caps[2].parse().ok()?;
+    let seconds: f64 = caps[3].parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
 
-        if !status.success() {
-            log::error!("FFmpeg failed with output:\n{}", all_output);
-            anyhow::bail!(
-                "FFmpeg failed with exit code: {:?}. Check logs for details.",
-                status.code()
-            );
+/// Sample rate every stitched clip's audio is resampled to before the concat
+/// filter, which requires identical audio parameters across segments.
+pub const STITCH_AUDIO_RATE: u32 = 48_000;
+
+/// Filters fitting a frame into `width`x`height` at `fps` with square pixels,
+/// as a chain that continues from its (unlabelled) input to its (unlabelled)
+/// output. The blur fill splits the stream inside the chain; `tag` keeps its
+/// labels unique within one graph.
+fn fit_chain(width: u32, height: u32, fps: &str, fill: Fill, tag: &str) -> String {
+    let fit = format!("scale={width}:{height}:force_original_aspect_ratio=decrease");
+    match fill {
+        Fill::Pad => {
+            format!("{fit},pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps}")
+        }
+        Fill::Crop => format!(
+            "scale={width}:{height}:force_original_aspect_ratio=increase,\
+             crop={width}:{height},setsar=1,fps={fps}"
+        ),
+        Fill::Blur => {
+            // The background is blurred at an eighth of the size, which looks
+            // the same and costs a fraction of a full-size blur.
+            let (small_w, small_h) = ((width / 16).max(1) * 2, (height / 16).max(1) * 2);
+            format!(
+                "split[fg{tag}][bg{tag}];\
+                 [bg{tag}]scale={small_w}:{small_h}:force_original_aspect_ratio=increase,\
+                 crop={small_w}:{small_h},gblur=sigma=6,scale={width}:{height},\
+                 eq=brightness=-0.15,setsar=1[bgb{tag}];\
+                 [fg{tag}]{fit},setsar=1[fgs{tag}];\
+                 [bgb{tag}][fgs{tag}]overlay=(W-w)/2:(H-h)/2,fps={fps}"
+            )
         }
+    }
+}
 
-        Ok(())
+/// The filter resampling video to `fps`: frames are dropped or repeated
+/// (`fps`), cross-faded (`framerate`), or motion-interpolated (`minterpolate`
+/// with bidirectional search and overlapped block motion compensation).
+fn resample_filter(fps: &str, interpolation: Interpolation) -> String {
+    match interpolation {
+        Interpolation::None => format!("fps={fps}"),
+        Interpolation::Blend => format!("framerate=fps={fps}"),
+        Interpolation::Mci => {
+            format!("minterpolate=fps={fps}:mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1")
+        }
+    }
+}
+
+/// A filter chain playing its input forward and reversed in turn, `loops`
+/// times: the input is split into one copy per pass (`split`), every other
+/// copy is reversed (`reverse`), and the copies are joined in order. Labels
+/// start with `tag`; `streams` is the concat filter's stream counts.
+fn boomerang_chain(loops: u32, split: &str, reverse: &str, tag: &str, streams: &str) -> String {
+    let passes = 2 * loops.max(1);
+    let mut copies = String::new();
+    let mut reversed = String::new();
+    let mut joined = String::new();
+    for i in 0..passes {
+        copies.push_str(&format!("[{tag}{i}]"));
+        if i % 2 == 1 {
+            reversed.push_str(&format!("[{tag}{i}]{reverse}[{tag}r{i}];"));
+            joined.push_str(&format!("[{tag}r{i}]"));
+        } else {
+            joined.push_str(&format!("[{tag}{i}]"));
+        }
+    }
+    format!("{split}={passes}{copies};{reversed}{joined}concat=n={passes}:{streams}")
+}
+
+/// Speeds a ramped piece of audio keeps its sound at; beyond them it is muted.
+pub const RAMP_AUDIO_SPEEDS: std::ops::RangeInclusive<f64> = 0.25..=4.0;
+
+/// `atempo` filters changing the tempo by `multiplier`. One filter covers
+/// 0.5-2.0; beyond that they are chained.
+fn atempo_chain(multiplier: f64) -> Vec<String> {
+    if (0.5..=2.0).contains(&multiplier) {
+        return vec![format!("atempo={multiplier:.4}")];
+    }
+    let mut chain = Vec::new();
+    let mut current = multiplier;
+    while current > 2.0 {
+        chain.push("atempo=2.0".to_string());
+        current /= 2.0;
+    }
+    if current > 1.0 {
+        chain.push(format!("atempo={current:.4}"));
+    }
+    while current < 0.5 {
+        chain.push("atempo=0.5".to_string());
+        current *= 2.0;
+    }
+    if current < 1.0 {
+        chain.push(format!("atempo={current:.4}"));
+    }
+    chain
+}
+
+/// The output time (in seconds) of an input frame at `T` seconds, as an ffmpeg
+/// expression: the sum, over every span, of the part of it already played
+/// divided by its speed. The last span runs on past its end, so frames beyond
+/// the expected duration keep advancing.
+fn ramp_expression(segments: &[RampSegment]) -> String {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let (start, speed) = (s.start, s.speed);
+            if i + 1 == segments.len() {
+                format!("max(T-{start:.6},0)/{speed:.6}")
+            } else {
+                let length = s.end - s.start;
+                format!("clip(T-{start:.6},0,{length:.6})/{speed:.6}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Audio filters retiming each span of a ramp on its own, as a chain that
+/// continues from its (unlabelled) input to its (unlabelled) output.
+fn ramp_audio_chain(segments: &[RampSegment]) -> String {
+    let retime = |s: &RampSegment| {
+        let mut chain = if s.speed == 1.0 {
+            vec!["anull".to_string()]
+        } else {
+            atempo_chain(s.speed)
+        };
+        if !RAMP_AUDIO_SPEEDS.contains(&s.speed) {
+            chain.push("volume=0".to_string());
+        }
+        chain.join(",")
+    };
+    if let [only] = segments {
+        return retime(only);
+    }
+    let n = segments.len();
+    // Rebased first, so the cut points count from the start of the track.
+    let mut graph = format!("asetpts=PTS-STARTPTS,asplit={n}");
+    for i in 0..n {
+        graph.push_str(&format!("[ramp{i}]"));
+    }
+    for (i, s) in segments.iter().enumerate() {
+        // The last piece runs to the end, however long the track is.
+        let end = if i + 1 == n {
+            String::new()
+        } else {
+            format!(":end={end:.6}", end = s.end)
+        };
+        graph.push_str(&format!(
+            ";[ramp{i}]atrim=start={start:.6}{end},asetpts=PTS-STARTPTS,{retime}[ramped{i}]",
+            start = s.start,
+            retime = retime(s)
+        ));
+    }
+    graph.push(';');
+    for i in 0..n {
+        graph.push_str(&format!("[ramped{i}]"));
+    }
+    graph.push_str(&format!("concat=n={n}:v=0:a=1"));
+    graph
+}
+
+/// One clip's audio branch of the stitch graph, labelled `[a{index}]`.
+///
+/// Real audio is resampled to a common rate/layout, then padded with silence and
+/// trimmed to exactly the clip's duration, so a track that is slightly shorter
+/// or longer than its video cannot shift every later clip out of sync. A clip
+/// without audio gets generated silence of the same duration. A trimmed clip's
+/// audio is cut to the same `(start, end)` as its video first, and a clip with
+/// a speed of its own is then retimed to match its video.
+fn stitch_audio_branch(
+    index: usize,
+    has_audio: bool,
+    duration: f64,
+    trim: Option<(f64, f64)>,
+    speed: Option<f64>,
+) -> String {
+    let rate = STITCH_AUDIO_RATE;
+    if has_audio {
+        let trim = match trim {
+            Some((start, end)) => {
+                format!("asetpts=PTS-STARTPTS,atrim=start={start:.6}:end={end:.6},")
+            }
+            None => String::new(),
+        };
+        let retime = match speed {
+            Some(speed) if speed != 1.0 => {
+                format!("{chain},", chain = atempo_chain(speed).join(","))
+            }
+            _ => String::new(),
+        };
+        format!(
+            "[{index}:a]{trim}{retime}aresample={rate},aformat=sample_fmts=fltp:channel_layouts=stereo,\
+             apad,atrim=duration={duration:.6},asetpts=PTS-STARTPTS[a{index}];"
+        )
+    } else {
+        format!(
+            "anullsrc=r={rate}:cl=stereo,aformat=sample_fmts=fltp,\
+             atrim=duration={duration:.6},asetpts=PTS-STARTPTS[a{index}];"
+        )
     }
 }
 
+/// Join the normalized `[v{i}]` (and `[a{i}]`) branches of the stitch graph
+/// pairwise, left to right, into `[cat]` (and `[acat]`): an `xfade` (and
+/// `acrossfade`) at each transition, a two-input concat at each cut.
+///
+/// `xfade` places a transition by its offset into the first input, which here
+/// is everything joined so far, so the offset is the running output length
+/// minus the transition's duration.
+fn transition_chain(transitions: &[Transition], durations: &[f64], with_audio: bool) -> String {
+    let mut parts = Vec::new();
+    let (mut video, mut audio) = ("v0".to_string(), "a0".to_string());
+    let mut joined = durations.first().copied().unwrap_or(0.0);
+    for (k, transition) in transitions.iter().enumerate() {
+        let next = k + 1;
+        let (video_out, audio_out) = if next == transitions.len() {
+            ("cat".to_string(), "acat".to_string())
+        } else {
+            (format!("vj{next}"), format!("aj{next}"))
+        };
+        let clip = durations.get(next).copied().unwrap_or(0.0);
+        match transition {
+            Transition::Cut => {
+                parts.push(format!("[{video}][v{next}]concat=n=2:v=1:a=0[{video_out}]"));
+                if with_audio {
+                    parts.push(format!("[{audio}][a{next}]concat=n=2:v=0:a=1[{audio_out}]"));
+                }
+                joined += clip;
+            }
+            Transition::Xfade { kind, duration } => {
+                let offset = (joined - duration).max(0.0);
+                parts.push(format!(
+                    "[{video}][v{next}]xfade=transition={kind}:duration={duration:.6}:\
+                     offset={offset:.6}[{video_out}]"
+                ));
+                if with_audio {
+                    parts.push(format!(
+                        "[{audio}][a{next}]acrossfade=d={duration:.6}[{audio_out}]"
+                    ));
+                }
+                joined += clip - duration;
+            }
+        }
+        video = video_out;
+        audio = audio_out;
+    }
+    parts.join(";")
+}
+
 /// Whether `path`'s extension is an MP4/MOV-family container, where `-movflags`
 /// (`use_metadata_tags`, `+faststart`) applies. Other containers (MKV, WebM)
 /// reject those flags.
@@ -718,119 +1455,332 @@ pub fn is_mp4_family(path: &Path) -> bool {
 
 /// Check if FFmpeg is available and return version info
 pub fn check_ffmpeg() -> Result<String> {
-    let output = Command::new("ffmpeg")
-        .arg("-version")
+    let output = version_command()
         .output()
-        .context("FFmpeg not found. Please install FFmpeg.")?;
+        .map_err(|e| SpeedyError::spawn("ffmpeg", e))?;
+    Ok(parse_version(&output.stdout))
+}
 
-    let version = String::from_utf8_lossy(&output.stdout);
+/// [`check_ffmpeg`] without blocking the async runtime.
+#[cfg(feature = "async")]
+pub async fn check_ffmpeg_async() -> Result<String> {
+    let output = tokio::process::Command::from(version_command())
+        .output()
+        .await
+        .map_err(|e| SpeedyError::spawn("ffmpeg", e))?;
+    Ok(parse_version(&output.stdout))
+}
 
-    // Extract version number
-    let version_regex = Regex::new(r"ffmpeg version (\S+)").unwrap();
-    if let Some(caps) = version_regex.captures(&version) {
-        Ok(caps[1].to_string())
-    } else {
-        Ok("unknown".to_string())
-    }
+fn version_command() -> Command {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-version").stdin(Stdio::null());
+    cmd
+}
+
+/// The version from `ffmpeg -version` output, or `"unknown"`.
+fn parse_version(stdout: &[u8]) -> String {
+    let version = String::from_utf8_lossy(stdout);
+    Regex::new(r"ffmpeg version (\S+)")
+        .ok()
+        .and_then(|re| re.captures(&version).map(|caps| caps[1].to_string()))
+        .unwrap_or_else(|| "unknown".to_string())
 }
 
 /// Get video metadata using ffprobe
 pub fn get_video_info(path: impl AsRef<Path>) -> Result<VideoInfo> {
-    let output = Command::new("ffprobe")
-        .args([
-            "-v",
-            "quiet",
-            "-print_format",
-            "json",
-            "-show_format",
-            "-show_streams",
-        ])
-        .arg(path.as_ref())
+    let path = path.as_ref();
+    let output = probe_command(path)
         .output()
-        .context("Failed to run ffprobe")?;
+        .map_err(|e| SpeedyError::spawn("ffprobe", e))?;
+    probe_result(path, &output)
+}
+
+/// [`get_video_info`] without blocking the async runtime.
+#[cfg(feature = "async")]
+pub async fn get_video_info_async(path: impl AsRef<Path>) -> Result<VideoInfo> {
+    let path = path.as_ref();
+    let output = tokio::process::Command::from(probe_command(path))
+        .output()
+        .await
+        .map_err(|e| SpeedyError::spawn("ffprobe", e))?;
+    probe_result(path, &output)
+}
 
+/// The ffprobe invocation behind [`get_video_info`].
+pub(crate) fn probe_command(path: &Path) -> Command {
+    let mut cmd = Command::new("ffprobe");
+    cmd.args([
+        "-v",
+        "quiet",
+        "-print_format",
+        "json",
+        "-show_format",
+        "-show_streams",
+    ])
+    .arg(path)
+    .stdin(Stdio::null());
+    cmd
+}
+
+/// Turn a finished ffprobe run on `path` into its [`VideoInfo`].
+fn probe_result(path: &Path, output: &std::process::Output) -> Result<VideoInfo> {
     // ffprobe failed (missing/corrupt file, etc.): don't silently return zeroed
     // metadata, which would feed a bogus 0x0 / 0fps plan into the pipeline.
     if !output.status.success() {
-        anyhow::bail!(
-            "ffprobe failed for {path} (exit {code}): {stderr}",
-            path = path.as_ref().display(),
-            code = output
-                .status
-                .code()
-                .map(|c| c.to_string())
-                .unwrap_or_else(|| "signal".to_string()),
-            stderr = String::from_utf8_lossy(&output.stderr).trim()
-        );
+        return Err(SpeedyError::ProbeFailed {
+            path: path.to_path_buf(),
+            message: format!(
+                "exit {code}: {stderr}",
+                code = output
+                    .status
+                    .code()
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| "signal".to_string()),
+                stderr = String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
     }
 
-    let json = String::from_utf8_lossy(&output.stdout);
-
-    // Parse JSON output (simplified for now)
-    // In production, use serde_json to properly parse
-
-    // Extract basic info using regex (simplified)
-    let duration_regex = Regex::new(r#""duration":\s*"(\d+\.\d+)""#).unwrap();
-    let width_regex = Regex::new(r#""width":\s*(\d+)"#).unwrap();
-    let height_regex = Regex::new(r#""height":\s*(\d+)"#).unwrap();
-    let fps_regex = Regex::new(r#""r_frame_rate":\s*"(\d+)/(\d+)""#).unwrap();
-    let rotation_regex = Regex::new(r#""rotation":\s*(-?\d+)"#).unwrap();
-    let audio_regex = Regex::new(r#""codec_type":\s*"audio""#).unwrap();
+    parse_probe_json(&output.stdout).map_err(|e| SpeedyError::ProbeFailed {
+        path: path.to_path_buf(),
+        message: format!("unreadable ffprobe output: {e}"),
+    })
+}
 
-    let duration = duration_regex
-        .captures(&json)
-        .and_then(|c| c[1].parse().ok())
+/// Build a [`VideoInfo`] from ffprobe's `-show_format -show_streams` JSON.
+///
+/// Video fields come from the first *video* stream and audio fields from the
+/// first *audio* stream, so a file whose first stream is audio or data (DJI
+/// telemetry, GoPro GPMF) never leaks its values into the video description.
+fn parse_probe_json(json: &[u8]) -> serde_json::Result<VideoInfo> {
+    let probe: ProbeOutput = serde_json::from_slice(json)?;
+    let video = probe.streams.iter().find(|s| s.codec_type == "video");
+    let audio = probe.streams.iter().find(|s| s.codec_type == "audio");
+    let format = probe.format.unwrap_or_default();
+
+    let video_duration = video.and_then(|v| parse_seconds(v.duration.as_deref()));
+    let duration = parse_seconds(format.duration.as_deref())
+        .or(video_duration)
         .unwrap_or(0.0);
 
-    let width = width_regex
-        .captures(&json)
-        .and_then(|c| c[1].parse().ok())
-        .unwrap_or(0);
-
-    let height = height_regex
-        .captures(&json)
-        .and_then(|c| c[1].parse().ok())
-        .unwrap_or(0);
-
-    let fps = fps_regex
-        .captures(&json)
-        .and_then(|c| {
-            let num: f64 = c[1].parse().ok()?;
-            let den: f64 = c[2].parse().ok()?;
-            Some(num / den)
-        })
+    let frame_rate = video.and_then(|v| positive_rate(v.r_frame_rate.as_deref()));
+    let avg_frame_rate = video.and_then(|v| positive_rate(v.avg_frame_rate.as_deref()));
+    let fps = frame_rate
+        .as_deref()
+        .and_then(fps_string_value)
         .unwrap_or(0.0);
 
-    let rotation = rotation_regex
-        .captures(&json)
-        .and_then(|c| c[1].parse().ok())
-        .unwrap_or(0);
-
-    let has_audio = audio_regex.is_match(&json);
+    // Creation time and timecode may sit on the container or on the video
+    // stream depending on the camera/muxer.
+    let tag = |key: &str| {
+        format
+            .tags
+            .get(key)
+            .or_else(|| video.and_then(|v| v.tags.get(key)))
+            .cloned()
+    };
 
     Ok(VideoInfo {
         duration,
-        width,
-        height,
+        width: video.and_then(|v| v.width).unwrap_or(0),
+        height: video.and_then(|v| v.height).unwrap_or(0),
         fps,
-        rotation,
-        has_audio,
+        rotation: video.map(ProbeStream::rotation).unwrap_or(0),
+        has_audio: audio.is_some(),
+        format_name: format.format_name,
+        video_codec: video.and_then(|v| v.codec_name.clone()),
+        pix_fmt: video.and_then(|v| v.pix_fmt.clone()),
+        bit_depth: video.and_then(ProbeStream::bit_depth),
+        color_primaries: video.and_then(|v| v.color_primaries.clone()),
+        color_transfer: video.and_then(|v| v.color_transfer.clone()),
+        color_space: video.and_then(|v| v.color_space.clone()),
+        frame_rate,
+        avg_frame_rate,
+        time_base: video.and_then(|v| v.time_base.clone()),
+        video_duration,
+        audio_duration: audio.and_then(|a| parse_seconds(a.duration.as_deref())),
+        creation_time: tag("creation_time"),
+        timecode: tag("timecode"),
+        audio_codec: audio.and_then(|a| a.codec_name.clone()),
+        audio_sample_rate: audio
+            .and_then(|a| a.sample_rate.as_deref())
+            .and_then(|r| r.parse().ok()),
+        audio_channels: audio.and_then(|a| a.channels),
     })
 }
 
-#[derive(Debug, Clone)]
+/// Parse an ffprobe duration string (seconds), rejecting `N/A` and negatives.
+fn parse_seconds(value: Option<&str>) -> Option<f64> {
+    value
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+}
+
+/// Keep a rational rate (e.g. `"30000/1001"`) verbatim only when it is a
+/// positive rational; ffprobe reports `0/0` for unknown rates.
+fn positive_rate(rate: Option<&str>) -> Option<String> {
+    let rate = rate?;
+    let (num, den) = rate.split_once('/')?;
+    match (num.parse::<f64>(), den.parse::<f64>()) {
+        (Ok(num), Ok(den)) if num > 0.0 && den > 0.0 => Some(rate.to_string()),
+        _ => None,
+    }
+}
+
+/// Parse an ffmpeg frame-rate string (`"30000/1001"` or `"29.97"`) into a
+/// positive float, returning `None` when it is missing, malformed, or
+/// non-positive. Used to reject a degenerate source fps before feeding it to the
+/// `fps` filter (where `fps=0` would be invalid).
+pub(crate) fn fps_string_value(s: &str) -> Option<f64> {
+    if let Some((num, den)) = s.split_once('/') {
+        let num: f64 = num.trim().parse().ok()?;
+        let den: f64 = den.trim().parse().ok()?;
+        if num > 0.0 && den > 0.0 {
+            Some(num / den)
+        } else {
+            None
+        }
+    } else {
+        let value: f64 = s.trim().parse().ok()?;
+        (value > 0.0).then_some(value)
+    }
+}
+
+/// Top level of ffprobe's `-print_format json -show_format -show_streams`.
+#[derive(Debug, Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+/// The `format` (container) section of ffprobe's JSON. Numbers are strings in
+/// ffprobe's output, so they are parsed on demand.
+#[derive(Debug, Default, Deserialize)]
+struct ProbeFormat {
+    format_name: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// One entry of ffprobe's `streams` array (only the fields speedy uses).
+#[derive(Debug, Default, Deserialize)]
+struct ProbeStream {
+    #[serde(default)]
+    codec_type: String,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    pix_fmt: Option<String>,
+    bits_per_raw_sample: Option<String>,
+    color_primaries: Option<String>,
+    color_transfer: Option<String>,
+    color_space: Option<String>,
+    r_frame_rate: Option<String>,
+    avg_frame_rate: Option<String>,
+    time_base: Option<String>,
+    duration: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    side_data_list: Vec<ProbeSideData>,
+}
+
+/// A stream side-data entry; only the display matrix rotation is used.
+#[derive(Debug, Default, Deserialize)]
+struct ProbeSideData {
+    rotation: Option<f64>,
+}
+
+impl ProbeStream {
+    /// Rotation in degrees from the display matrix side data, falling back to
+    /// the legacy `rotate` tag. The tag uses the opposite sign convention (a
+    /// `rotate=90` tag is a `-90` display matrix), so it is negated to match.
+    fn rotation(&self) -> i32 {
+        self.side_data_list
+            .iter()
+            .find_map(|d| d.rotation)
+            .map(|r| r.round() as i32)
+            .or_else(|| {
+                self.tags
+                    .get("rotate")
+                    .and_then(|r| r.trim().parse::<i32>().ok())
+                    .map(|r| -r)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Bits per component: `bits_per_raw_sample` when reported, otherwise
+    /// inferred from the pixel format name (`yuv420p10le` -> 10, `yuv420p` or
+    /// `nv12` -> 8). High-bit formats always carry an endianness suffix.
+    fn bit_depth(&self) -> Option<u8> {
+        if let Some(bits) = self
+            .bits_per_raw_sample
+            .as_deref()
+            .and_then(|b| b.parse::<u8>().ok())
+            .filter(|b| *b > 0)
+        {
+            return Some(bits);
+        }
+        let pix_fmt = self.pix_fmt.as_deref()?;
+        let high_bit = Regex::new(r"(\d+)(le|be)$").ok()?;
+        match high_bit.captures(pix_fmt) {
+            Some(caps) => caps[1].parse::<u8>().ok().filter(|b| *b <= 16),
+            None => Some(8),
+        }
+    }
+}
+
+/// Media description of a clip, as probed by [`get_video_info`]. Video fields
+/// describe the first video stream and audio fields the first audio stream.
+#[derive(Debug, Clone, Default)]
 pub struct VideoInfo {
+    /// Container duration in seconds (the longest stream).
     pub duration: f64,
     pub width: u32,
     pub height: u32,
+    /// Base frame rate (`r_frame_rate`) as a float; `0.0` when unknown.
     pub fps: f64,
+    /// Display rotation in degrees (display matrix convention, e.g. `-90`).
     pub rotation: i32,
     pub has_audio: bool,
+    /// Container format, e.g. `"mov,mp4,m4a,3gp,3g2,mj2"`.
+    pub format_name: Option<String>,
+    pub video_codec: Option<String>,
+    pub pix_fmt: Option<String>,
+    /// Bits per color component (8 for most camera footage, 10 for D-Log/HLG).
+    pub bit_depth: Option<u8>,
+    pub color_primaries: Option<String>,
+    pub color_transfer: Option<String>,
+    /// Color matrix coefficients (ffprobe's `color_space`).
+    pub color_space: Option<String>,
+    /// Base frame rate as a rational string (e.g. `"30000/1001"`).
+    pub frame_rate: Option<String>,
+    /// Average frame rate as a rational string; the real cadence of
+    /// variable-frame-rate sources, where `frame_rate` is only a timebase guess.
+    pub avg_frame_rate: Option<String>,
+    /// Video stream time base (e.g. `"1/30000"`).
+    pub time_base: Option<String>,
+    /// Video stream duration in seconds, when the container reports it.
+    pub video_duration: Option<f64>,
+    /// Audio stream duration in seconds, when the container reports it.
+    pub audio_duration: Option<f64>,
+    /// Capture time from container (or stream) metadata, as reported.
+    pub creation_time: Option<String>,
+    /// Start timecode (e.g. `"12:34:56:12"`), when the camera writes one.
+    pub timecode: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_sample_rate: Option<u32>,
+    pub audio_channels: Option<u32>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::{Context, Result};
 
     /// Collect the built command's arguments as owned strings for inspection.
     fn args_of(cmd: &Command) -> Vec<String> {
@@ -856,6 +1806,52 @@ mod tests {
         assert!(has_pair(&args, "-i", "in.mp4"));
     }
 
+    #[test]
+    fn progress_output_is_requested_only_when_executing() {
+        let cmd = FFmpegCommand::new("in.mp4", "out.mp4").overwrite();
+        assert!(!args_of(&cmd.build()).iter().any(|a| a == "-progress"));
+        // Global option: must precede the inputs, not trail the output.
+        let args = args_of(&cmd.command(true));
+        let progress = args.iter().position(|a| a == "-progress");
+        let input = args.iter().position(|a| a == "-i");
+        assert!(progress.is_some() && progress < input, "{args:?}");
+        assert!(has_pair(&args, "-progress", "pipe:1"));
+    }
+
+    #[test]
+    fn cancelled_command_does_not_start() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let ran = FFmpegCommand::new("in.mp4", "out.mp4")
+            .cancellation(token)
+            .execute(|_| {});
+        assert!(matches!(ran, Err(SpeedyError::Cancelled)), "{ran:?}");
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn cancelled_async_command_ends_its_progress_stream() {
+        use tokio_stream::StreamExt;
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let (mut progress, job) = FFmpegCommand::new("in.mp4", "out.mp4")
+            .cancellation(token)
+            .execute_async();
+        let ran = job.await;
+        assert!(matches!(ran, Err(SpeedyError::Cancelled)), "{ran:?}");
+        assert_eq!(progress.next().await, None);
+    }
+
+    #[test]
+    fn duration_line_is_parsed_to_seconds() -> Result<()> {
+        let re = Regex::new(DURATION_PATTERN)?;
+        let line = "  Duration: 00:01:02.50, start: 0.000000, bitrate: 90142 kb/s";
+        assert_eq!(parse_duration_line(&re, line), Some(62.5));
+        assert_eq!(parse_duration_line(&re, "Duration: N/A"), None);
+        Ok(())
+    }
+
     #[test]
     fn single_input_with_lut_forces_yuv420p() -> Result<()> {
         let args = args_of(
@@ -870,6 +1866,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn partial_lut_strength_mixes_the_graded_copy_with_the_original() -> Result<()> {
+        let args = args_of(
+            &FFmpegCommand::new("in.mp4", "out.mp4")
+                .lut_at_strength("grade.cube", LutKind::ThreeD, 0.6)
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert_eq!(
+            fc,
+            "[0:v]format=gbrp16le,split[lutsrc][lutin];[lutin]lut3d=file='grade.cube'[lutout];\
+             [lutsrc][lutout]mix=inputs=2:weights='0.400 0.600',format=yuv420p[v]"
+        );
+        // At full strength it is the plain LUT filter.
+        let args = args_of(
+            &FFmpegCommand::new("in.mp4", "out.mp4")
+                .lut_at_strength("curve.cube", LutKind::OneD, 1.0)
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert_eq!(fc, "[0:v]lut1d=file='curve.cube',format=yuv420p[v]");
+        Ok(())
+    }
+
     #[test]
     fn concat_normalize_builds_join_graph() -> Result<()> {
         let inputs = vec![
@@ -907,6 +1927,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn blur_fill_overlays_each_input_on_its_own_blurred_copy() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let args = args_of(
+            &FFmpegCommand::new_multi(inputs, "out.mp4")
+                .concat_normalize(1920, 1080, "30")
+                .concat_fill(Fill::Blur)
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        for i in 0..2 {
+            assert!(fc.contains(&format!("[{i}:v]split[fg{i}][bg{i}];")), "{fc}");
+            assert!(
+                fc.contains(&format!(
+                    "[bgb{i}][fgs{i}]overlay=(W-w)/2:(H-h)/2,fps=30,setpts=PTS-STARTPTS[v{i}]"
+                )),
+                "{fc}"
+            );
+        }
+        assert!(fc.contains("scale=240:134:force_original_aspect_ratio=increase"));
+        assert!(fc.contains("scale=1920:1080,eq=brightness=-0.15"));
+        Ok(())
+    }
+
+    #[test]
+    fn crop_fill_scales_up_and_crops_a_single_input() -> Result<()> {
+        let args = args_of(
+            &FFmpegCommand::new("portrait.mp4", "out.mkv")
+                .scale_fill(1920, 1080, "30", Fill::Crop)
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(
+            fc.starts_with(
+                "[0:v]scale=1920:1080:force_original_aspect_ratio=increase,crop=1920:1080,setsar=1,fps=30"
+            ),
+            "{fc}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn prores_codec_keeps_10bit_422_pixel_format() -> Result<()> {
         // ProRes does not support yuv420p; forcing it would degrade or fail.
@@ -936,6 +1997,58 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn resample_interpolates_or_blends_instead_of_repeating_frames() -> Result<()> {
+        let fc_for = |interpolation| {
+            let args = args_of(
+                &FFmpegCommand::new("in.mp4", "out.mp4")
+                    .speed(0.5, false, None)
+                    .resample("60", interpolation)
+                    .build(),
+            );
+            filter_complex(&args).cloned()
+        };
+        assert_eq!(
+            fc_for(Interpolation::Mci).context("expected -filter_complex")?,
+            "[0:v]setpts=2.0000*PTS,\
+             minterpolate=fps=60:mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1,\
+             format=yuv420p[v]"
+        );
+        assert_eq!(
+            fc_for(Interpolation::Blend).context("expected -filter_complex")?,
+            "[0:v]setpts=2.0000*PTS,framerate=fps=60,format=yuv420p[v]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn boomerang_joins_forward_and_reversed_copies_before_the_speed() -> Result<()> {
+        let args = args_of(
+            &FFmpegCommand::new("in.mp4", "out.mp4")
+                .boomerang(2, true)
+                .speed(2.0, true, Some("30"))
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(
+            fc.starts_with(
+                "[0:v]split=4[boom0][boom1][boom2][boom3];\
+                 [boom1]reverse[boomr1];[boom3]reverse[boomr3];\
+                 [boom0][boomr1][boom2][boomr3]concat=n=4:v=1:a=0,setpts=0.5000*PTS,fps=30,"
+            ),
+            "fc: {fc}"
+        );
+        assert!(
+            fc.contains(
+                "[0:a]asplit=4[aboom0][aboom1][aboom2][aboom3];\
+                 [aboom1]areverse[aboomr1];[aboom3]areverse[aboomr3];\
+                 [aboom0][aboomr1][aboom2][aboomr3]concat=n=4:v=0:a=1,atempo=2.0000[a]"
+            ),
+            "fc: {fc}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn speed_change_without_output_fps_keeps_raw_retimed_stream() -> Result<()> {
         // Back-compat: with no target fps only setpts is applied (no decimation).
@@ -985,6 +2098,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn speed_ramp_retimes_video_by_span_and_audio_piece_by_piece() -> Result<()> {
+        let segments = [
+            RampSegment {
+                start: 0.0,
+                end: 2.0,
+                speed: 1.0,
+            },
+            RampSegment {
+                start: 2.0,
+                end: 4.0,
+                speed: 8.0,
+            },
+        ];
+        let args = args_of(
+            &FFmpegCommand::new("in.mp4", "out.mp4")
+                .speed_ramp(&segments, true, Some("30"))
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(
+            fc.starts_with(
+                "[0:v]setpts=PTS-STARTPTS,setpts='(clip(T-0.000000,0,2.000000)/1.000000\
+                 +max(T-2.000000,0)/8.000000)/TB',fps=30,format=yuv420p[v]"
+            ),
+            "fc: {fc}"
+        );
+        // Real time keeps its sound; the 8x span is retimed, then muted.
+        assert!(
+            fc.contains(
+                "[0:a]asetpts=PTS-STARTPTS,asplit=2[ramp0][ramp1];\
+                 [ramp0]atrim=start=0.000000:end=2.000000,asetpts=PTS-STARTPTS,anull[ramped0];\
+                 [ramp1]atrim=start=2.000000,asetpts=PTS-STARTPTS,\
+                 atempo=2.0,atempo=2.0,atempo=2.0000,volume=0[ramped1];\
+                 [ramped0][ramped1]concat=n=2:v=0:a=1[a]"
+            ),
+            "fc: {fc}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn dehaze_builds_blackpoint_contrast_vibrance_chain() -> Result<()> {
         // strength 0.5 -> black 0.05, contrast 1.075, sat 1.175, gamma 1.030,
@@ -1076,6 +2230,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn audio_only_single_input_drops_video() -> Result<()> {
+        let args = args_of(
+            &FFmpegCommand::new("in.mp4", "out.wav")
+                .scale_pad(1920, 1080, "30")
+                .speed(2.0, true, Some("30"))
+                .audio_only()
+                .build(),
+        );
+        // Only the audio chain is built; video filters are ignored.
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert_eq!(fc, "[0:a]atempo=2.0000[a]");
+        assert!(has_pair(&args, "-map", "[a]"), "args: {args:?}");
+        assert!(
+            !args.iter().any(|a| a == "[v]" || a == "0:v?"),
+            "must not map video: {args:?}"
+        );
+        assert!(args.iter().any(|a| a == "-vn"), "expected -vn: {args:?}");
+        Ok(())
+    }
+
     #[test]
     fn scale_pad_is_the_first_filter() -> Result<()> {
         let args = args_of(
@@ -1138,6 +2313,116 @@ mod tests {
         assert!(has_pair(&mkv, "-map_metadata", "0"), "{mkv:?}");
     }
 
+    #[test]
+    fn fps_string_value_parses_rational_and_decimal() {
+        assert_eq!(fps_string_value("30000/1001"), Some(30000.0 / 1001.0));
+        assert_eq!(fps_string_value("30"), Some(30.0));
+        assert_eq!(fps_string_value("60.0"), Some(60.0));
+        // Degenerate or malformed rates are rejected so they never reach `fps=`.
+        assert_eq!(fps_string_value("0/0"), None);
+        assert_eq!(fps_string_value("0"), None);
+        assert_eq!(fps_string_value("abc"), None);
+    }
+
+    /// Trimmed ffprobe output for a DJI D-Log clip: a data stream first, then
+    /// rotated 10-bit HEVC video, then AAC audio.
+    const DJI_PROBE: &str = r#"{
+        "streams": [
+            {
+                "index": 0,
+                "codec_type": "data",
+                "r_frame_rate": "0/0",
+                "avg_frame_rate": "0/0",
+                "tags": { "handler_name": "DJI meta" }
+            },
+            {
+                "index": 1,
+                "codec_name": "hevc",
+                "codec_type": "video",
+                "width": 3840,
+                "height": 2160,
+                "pix_fmt": "yuv420p10le",
+                "color_primaries": "bt2020",
+                "color_transfer": "arib-std-b67",
+                "color_space": "bt2020nc",
+                "r_frame_rate": "60000/1001",
+                "avg_frame_rate": "59940/1000",
+                "time_base": "1/60000",
+                "duration": "12.512500",
+                "tags": { "timecode": "09:41:07:12" },
+                "side_data_list": [
+                    { "side_data_type": "Display Matrix", "rotation": -90 }
+                ]
+            },
+            {
+                "index": 2,
+                "codec_name": "aac",
+                "codec_type": "audio",
+                "sample_rate": "48000",
+                "channels": 2,
+                "duration": "12.522667"
+            }
+        ],
+        "format": {
+            "format_name": "mov,mp4,m4a,3gp,3g2,mj2",
+            "duration": "12.522667",
+            "tags": { "creation_time": "2025-06-01T09:41:07.000000Z" }
+        }
+    }"#;
+
+    #[test]
+    fn parse_probe_json_reads_video_and_audio_streams_not_the_first() -> Result<()> {
+        let info = parse_probe_json(DJI_PROBE.as_bytes())?;
+        // The data stream's 0/0 rate must not leak into the video fields.
+        assert_eq!((info.width, info.height), (3840, 2160));
+        assert_eq!(info.frame_rate.as_deref(), Some("60000/1001"));
+        assert_eq!(info.avg_frame_rate.as_deref(), Some("59940/1000"));
+        assert!((info.fps - 60000.0 / 1001.0).abs() < 1e-9, "{}", info.fps);
+        assert_eq!(info.rotation, -90);
+        assert_eq!(info.video_codec.as_deref(), Some("hevc"));
+        assert_eq!(info.bit_depth, Some(10));
+        assert_eq!(info.color_transfer.as_deref(), Some("arib-std-b67"));
+        assert_eq!(info.time_base.as_deref(), Some("1/60000"));
+        assert_eq!(info.video_duration, Some(12.5125));
+        assert_eq!(info.audio_duration, Some(12.522667));
+        assert_eq!(info.duration, 12.522667);
+        assert_eq!(
+            info.creation_time.as_deref(),
+            Some("2025-06-01T09:41:07.000000Z")
+        );
+        assert_eq!(info.timecode.as_deref(), Some("09:41:07:12"));
+        assert!(info.has_audio);
+        assert_eq!(info.audio_codec.as_deref(), Some("aac"));
+        assert_eq!(info.audio_sample_rate, Some(48000));
+        assert_eq!(info.audio_channels, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_probe_json_handles_missing_fields() -> Result<()> {
+        // Video-only, legacy rotate tag, unknown rates, no container duration.
+        let json = r#"{
+            "streams": [{
+                "codec_type": "video",
+                "width": 1920,
+                "height": 1080,
+                "pix_fmt": "nv12",
+                "r_frame_rate": "0/0",
+                "duration": "N/A",
+                "tags": { "rotate": "90" }
+            }],
+            "format": {}
+        }"#;
+        let info = parse_probe_json(json.as_bytes())?;
+        assert_eq!(info.rotation, -90, "rotate tag is negated to match");
+        assert_eq!(info.frame_rate, None);
+        assert_eq!(info.fps, 0.0);
+        assert_eq!(info.bit_depth, Some(8), "nv12 is 8-bit");
+        assert_eq!(info.duration, 0.0);
+        assert!(!info.has_audio);
+        Ok(())
+    }
+
     #[test]
     fn is_mp4_family_matches_mp4_mov_m4v_only() {
         for ok in ["a.mp4", "a.MOV", "clip.m4v"] {
@@ -1148,6 +2433,176 @@ mod tests {
         }
     }
 
+    #[test]
+    fn concat_audio_joins_audio_in_sync_with_silence_for_mute_clips() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let args = args_of(
+            &FFmpegCommand::new_multi(inputs, "out.mp4")
+                .concat_normalize(1920, 1080, "30")
+                .concat_audio(vec![(true, 12.5), (false, 4.0)])
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        // Real audio is resampled and fitted to the clip's duration.
+        assert!(
+            fc.contains("[0:a]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo,apad,atrim=duration=12.500000"),
+            "graph: {fc}"
+        );
+        // The mute clip gets generated silence instead of a missing [1:a].
+        assert!(!fc.contains("[1:a]"), "graph: {fc}");
+        assert!(
+            fc.contains(
+                "anullsrc=r=48000:cl=stereo,aformat=sample_fmts=fltp,atrim=duration=4.000000"
+            ),
+            "graph: {fc}"
+        );
+        // Video and audio are interleaved per segment for the concat filter.
+        assert!(
+            fc.contains("[v0][a0][v1][a1]concat=n=2:v=1:a=1[cat][acat]"),
+            "graph: {fc}"
+        );
+        assert!(fc.ends_with(";[acat]anull[a]"), "graph: {fc}");
+        assert!(has_pair(&args, "-map", "[v]"));
+        assert!(has_pair(&args, "-map", "[a]"));
+        Ok(())
+    }
+
+    #[test]
+    fn concat_transitions_chain_xfade_and_cuts_with_running_offsets() -> Result<()> {
+        let inputs = vec![
+            PathBuf::from("a.mp4"),
+            PathBuf::from("b.mp4"),
+            PathBuf::from("c.mp4"),
+        ];
+        let args = args_of(
+            &FFmpegCommand::new_multi(inputs, "out.mp4")
+                .concat_normalize(1920, 1080, "30")
+                .concat_audio(vec![(true, 10.0), (true, 6.0), (false, 8.0)])
+                .concat_transitions(
+                    vec![Transition::xfade("fadeblack", 1.0)?, Transition::Cut],
+                    vec![10.0, 6.0, 8.0],
+                )
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(
+            fc.contains(
+                "[v0][v1]xfade=transition=fadeblack:duration=1.000000:offset=9.000000[vj1];\
+                 [a0][a1]acrossfade=d=1.000000[aj1];\
+                 [vj1][v2]concat=n=2:v=1:a=0[cat];[aj1][a2]concat=n=2:v=0:a=1[acat]"
+            ),
+            "graph: {fc}"
+        );
+        assert!(!fc.contains("concat=n=3"), "graph: {fc}");
+        assert!(fc.ends_with(";[acat]anull[a]"), "graph: {fc}");
+        Ok(())
+    }
+
+    #[test]
+    fn concat_trims_cut_each_branch_before_normalizing() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let args = args_of(
+            &FFmpegCommand::new_multi(inputs, "out.mp4")
+                .concat_normalize(1920, 1080, "30")
+                .concat_audio(vec![(true, 75.0), (true, 20.0)])
+                .concat_trims(vec![Some((5.0, 80.0)), None])
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(
+            fc.starts_with(
+                "[0:v]setpts=PTS-STARTPTS,trim=start=5.000000:end=80.000000,scale=1920:1080"
+            ),
+            "graph: {fc}"
+        );
+        assert!(fc.contains(";[1:v]scale=1920:1080"), "graph: {fc}");
+        assert!(
+            fc.contains("[0:a]asetpts=PTS-STARTPTS,atrim=start=5.000000:end=80.000000,aresample"),
+            "graph: {fc}"
+        );
+        assert!(fc.contains("[1:a]aresample"), "graph: {fc}");
+        assert!(!args.iter().any(|a| a == "-ss"), "args: {args:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn concat_speeds_retime_each_branch_and_leave_the_speed_to_the_join() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let args = args_of(
+            &FFmpegCommand::new_multi(inputs, "out.mp4")
+                .concat_normalize(1920, 1080, "30")
+                .concat_audio(vec![(true, 10.0), (true, 20.0)])
+                .concat_trims(vec![Some((0.0, 80.0)), None])
+                .concat_speeds(vec![Some((8.0, "30".to_string())), None])
+                .speed(2.0, true, Some("30"))
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(
+            fc.contains("end=80.000000,setpts=0.1250*(PTS-STARTPTS),fps=30,scale=1920:1080"),
+            "graph: {fc}"
+        );
+        assert!(
+            fc.contains("end=80.000000,atempo=2.0,atempo=2.0,atempo=2.0000,aresample"),
+            "graph: {fc}"
+        );
+        assert!(fc.contains("[1:v]scale=1920:1080"), "graph: {fc}");
+        assert!(fc.contains("[1:a]aresample"), "graph: {fc}");
+        assert!(
+            fc.contains("[cat]setpts=0.5000*PTS,fps=30,format=yuv420p[v]"),
+            "graph: {fc}"
+        );
+        assert!(fc.contains("[acat]atempo=2.0000[a]"), "graph: {fc}");
+        Ok(())
+    }
+
+    #[test]
+    fn trim_seeks_the_input() {
+        let args = args_of(
+            &FFmpegCommand::new("in.mp4", "out.mp4")
+                .trim(5.0, 80.0)
+                .build(),
+        );
+        let i = args.iter().position(|a| a == "-i");
+        let ss = args.iter().position(|a| a == "-ss");
+        assert!(ss < i, "-ss must precede -i: {args:?}");
+        assert!(has_pair(&args, "-ss", "5.000000"), "args: {args:?}");
+        assert!(has_pair(&args, "-to", "80.000000"), "args: {args:?}");
+    }
+
+    #[test]
+    fn all_cut_transitions_keep_the_single_concat() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let args = args_of(
+            &FFmpegCommand::new_multi(inputs, "out.mp4")
+                .concat_normalize(1920, 1080, "30")
+                .concat_transitions(vec![Transition::Cut], vec![5.0, 5.0])
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(fc.contains("[v0][v1]concat=n=2:v=1[cat]"), "graph: {fc}");
+        Ok(())
+    }
+
+    #[test]
+    fn concat_audio_applies_atempo_once_after_the_join() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let args = args_of(
+            &FFmpegCommand::new_multi(inputs, "out.mp4")
+                .concat_normalize(1920, 1080, "30")
+                .concat_audio(vec![(true, 10.0), (true, 10.0)])
+                .speed(4.0, true, Some("30"))
+                .build(),
+        );
+        let fc = filter_complex(&args).context("expected -filter_complex")?;
+        assert!(
+            fc.ends_with(";[acat]atempo=2.0,atempo=2.0000[a]"),
+            "graph: {fc}"
+        );
+        assert_eq!(fc.matches("atempo=2.0,").count(), 1, "graph: {fc}");
+        Ok(())
+    }
+
     #[test]
     fn concat_normalize_without_filters_still_outputs_yuv420p() -> Result<()> {
         let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
@@ -0,0 +1,260 @@
+//! Serializable job specifications.
+//!
+//! A [`JobSpec`] holds every setting of a [`VideoProcessor`] as plain data, so
+//! a job can be written to TOML or JSON, versioned, replayed exactly, or
+//! generated by other tools. Fields left out of a spec take the processor's
+//! defaults; unknown fields are rejected so a typo cannot be silently ignored.
+//!
+//! Paths in a spec are used as written: relative ones resolve against the
+//! working directory of the process running the job.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, SpeedyError};
+use crate::{
+    Canvas, Clip, ColorProfile, Easing, Fill, Interpolation, SpeedRamp, Transition, VideoProcessor,
+};
+
+/// Everything [`VideoProcessor`] can be configured with, as data. Convert with
+/// [`VideoProcessor::from_job`] and [`VideoProcessor::job_spec`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobSpec {
+    /// Input clips, stitched in order when there are several. Each is a path,
+    /// or a table trimming it: `{ path = "a.mp4", start = "00:05", end = 80 }`
+    /// (or `duration` instead of `end`; times in seconds or `[HH:]MM:SS`),
+    /// optionally with a `speed` of its own and the `fps` it is resampled to.
+    pub inputs: Vec<Clip>,
+    pub output: PathBuf,
+    pub speed: f64,
+    /// A speed changing over time, as `time:speed` keyframes on the source
+    /// timeline (e.g. `"0:1,5:8,12:8,14:1"`), in place of `speed`.
+    pub speed_ramp: Option<SpeedRamp>,
+    /// How a speed ramp moves between keyframes: `linear` or `ease`.
+    pub ramp_easing: Easing,
+    /// Target frame rate for a speed change (e.g. `"30000/1001"`).
+    pub output_fps: Option<String>,
+    /// How a slow-down fills in frames: `none`, `blend` or `mci`.
+    pub interpolate: Interpolation,
+    /// Shutter angle in degrees (e.g. `180`) for motion blur on a speed-up.
+    pub shutter_angle: Option<f64>,
+    /// Seconds the output should last; the speed is chosen to fit, in place
+    /// of `speed`.
+    pub fit_duration: Option<f64>,
+    /// Play the timeline backwards.
+    pub reverse: bool,
+    /// Play the timeline forward then backwards, this many times over.
+    pub boomerang: Option<u32>,
+    /// Encoder name; the CLI aliases (`h264`, `h265`, `prores`, ...) work too.
+    pub codec: String,
+    /// Video bitrate in Mbps.
+    pub bitrate: Option<u32>,
+    /// CRF quality (0-51, lower is better).
+    pub quality: u8,
+    pub threads: Option<usize>,
+    pub hw_accel: bool,
+    pub profile: ColorProfile,
+    pub lut: Option<PathBuf>,
+    /// How much of the LUT's result is mixed in, from 0.0 to 1.0.
+    pub lut_strength: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    pub vibrance: Option<f32>,
+    pub dehaze: Option<f32>,
+    pub curves: Option<String>,
+    pub hue_shift: Option<f32>,
+    pub color_balance: Option<ColorBalance>,
+    pub selective_color: Option<String>,
+    pub denoise: Option<u8>,
+    pub sharpen: Option<f32>,
+    /// Output resolution, e.g. `"1920x1080"` or `"1920:-1"`.
+    pub scale: Option<String>,
+    pub auto_rotate: bool,
+    pub stabilize: bool,
+    /// vidstab smoothing window in frames; `None` uses the tuned default.
+    pub stabilize_smoothing: Option<u32>,
+    /// Transitions between stitched clips (e.g. `["fade:0.5"]`): one for every
+    /// boundary, or one per boundary. Empty joins them with hard cuts.
+    pub transitions: Vec<Transition>,
+    /// Stitched frame: `"smallest"`, `"largest"`, `"first"`, `"common"` or
+    /// `"WxH"`.
+    pub canvas: Canvas,
+    /// How a clip of another shape fills the canvas: `pad`, `blur` or `crop`.
+    pub fill: Fill,
+    /// Join compatible clips losslessly when nothing changes their content.
+    pub stream_copy: bool,
+    /// Treat consecutive chapter files of one camera recording as one
+    /// continuous clip (no transition, one stabilization segment).
+    pub merge_chapters: bool,
+}
+
+/// Color balance adjustments as `[r, g, b]` per tonal range (each -1..1).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorBalance {
+    pub shadows: [f32; 3],
+    pub midtones: [f32; 3],
+    pub highlights: [f32; 3],
+}
+
+impl Default for JobSpec {
+    fn default() -> Self {
+        // Taken from a fresh processor, so the two defaults cannot drift.
+        VideoProcessor::new_multi(Vec::new(), PathBuf::new()).job_spec()
+    }
+}
+
+impl JobSpec {
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| invalid_job(e.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| invalid_job(e.to_string()))
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| invalid_job(e.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| invalid_job(e.to_string()))
+    }
+
+    /// Read a spec from a file: JSON for a `.json` extension, TOML otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            SpeedyError::io(
+                format!("Failed to read job spec {path}", path = path.display()),
+                e,
+            )
+        })?;
+        let parsed = if is_json(path) {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        };
+        parsed.map_err(|e| match e {
+            SpeedyError::InvalidJob { message } => {
+                invalid_job(format!("{path}: {message}", path = path.display()))
+            }
+            other => other,
+        })
+    }
+
+    /// Write the spec to a file, in the format [`load`](Self::load) expects
+    /// for its extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = if is_json(path) {
+            self.to_json()?
+        } else {
+            self.to_toml()?
+        };
+        std::fs::write(path, text).map_err(|e| {
+            SpeedyError::io(
+                format!("Failed to write job spec {path}", path = path.display()),
+                e,
+            )
+        })
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+fn invalid_job(message: String) -> SpeedyError {
+    SpeedyError::InvalidJob { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_toml_fills_in_processor_defaults() -> anyhow::Result<()> {
+        let spec = JobSpec::from_toml(
+            r#"
+            inputs = ["a.mp4", "b.mp4"]
+            output = "out.mp4"
+            speed = 10.0
+            profile = "d-log"
+
+            [color_balance]
+            shadows = [0.1, 0.0, -0.1]
+            midtones = [0.0, 0.0, 0.0]
+            highlights = [-0.1, 0.0, 0.1]
+            "#,
+        )?;
+        assert_eq!(spec.inputs.len(), 2);
+        assert_eq!(spec.speed, 10.0);
+        assert_eq!(spec.profile, ColorProfile::DLog);
+        assert_eq!(spec.codec, "libx264");
+        assert_eq!(spec.quality, 23);
+        assert!(spec.auto_rotate);
+        assert_eq!(
+            spec.color_balance.map(|b| b.shadows),
+            Some([0.1, 0.0, -0.1])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inputs_take_paths_or_trimmed_tables() -> anyhow::Result<()> {
+        let spec = JobSpec::from_toml(
+            r#"
+            inputs = [
+                "takeoff.mp4",
+                { path = "dji.mp4", start = "00:05", end = 80 },
+                { path = "pan.mp4", duration = 12.5 },
+                { path = "transit.mp4", speed = 20, fps = "30" },
+            ]
+            "#,
+        )?;
+        assert_eq!(
+            spec.inputs,
+            vec![
+                Clip::new("takeoff.mp4"),
+                Clip::new("dji.mp4").start(5.0).end(80.0),
+                Clip::new("pan.mp4").duration(12.5),
+                Clip::new("transit.mp4").speed(20.0).fps("30"),
+            ]
+        );
+        assert_eq!(JobSpec::from_toml(&spec.to_toml()?)?, spec);
+        assert!(
+            JobSpec::from_toml(r#"inputs = [{ path = "a.mp4", end = 5, duration = 5 }]"#).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let r = JobSpec::from_toml("speeed = 2.0");
+        assert!(matches!(r, Err(SpeedyError::InvalidJob { .. })), "{r:?}");
+    }
+
+    #[test]
+    fn spec_round_trips_through_a_processor_and_both_formats() -> anyhow::Result<()> {
+        let processor = VideoProcessor::new_multi(
+            vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")],
+            "out.mp4",
+        )
+        .speed(4.0)
+        .codec("h265")
+        .dehaze(0.5)
+        .color_balance_str("0.1:-0.1:0,0:0:0,-0.1:0:0.1")
+        .stabilize(true)
+        .stabilize_smoothing(30)
+        .transition("dip-to-white:0.75".parse()?);
+        let spec = processor.job_spec();
+        assert_eq!(spec.codec, "libx265");
+        assert_eq!(VideoProcessor::from_job(spec.clone()).job_spec(), spec);
+        assert_eq!(JobSpec::from_toml(&spec.to_toml()?)?, spec);
+        assert_eq!(JobSpec::from_json(&spec.to_json()?)?, spec);
+        Ok(())
+    }
+}
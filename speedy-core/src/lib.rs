@@ -2,26 +2,60 @@
 //!
 //! This library provides tools for video processing by wrapping the FFmpeg
 //! command-line tool, including:
-//! - Speed adjustment with automatic audio pitch correction
-//! - Color grading and enhancement (vibrance, curves, color balance)
+//! - Speed adjustment with automatic audio pitch correction, constant or
+//!   ramped over keyframes, and frame interpolation for smooth slow motion
+//! - Color grading and enhancement (vibrance, curves, color balance), with
+//!   `.cube` LUTs checked before they reach FFmpeg
 //! - Hardware acceleration support
 //! - Multiple codec support (H.264, H.265, VP9, AV1, ProRes)
 //! - Video stabilization and denoising
 //! - Smart presets for common workflows
 
+pub mod cancel;
+pub mod canvas;
+pub mod chapters;
+pub mod clip;
+pub mod error;
 pub mod ffmpeg_wrapper;
+pub mod job;
+pub mod lut;
+pub mod order;
+mod pipeline;
+pub mod plan;
 pub mod presets;
+pub mod progress;
+pub mod ramp;
+pub mod retime;
 pub mod stabilize;
+pub mod transition;
 pub mod video_processor;
 
 // Re-export commonly used types at the crate root
+pub use cancel::CancellationToken;
+pub use canvas::{Canvas, Fill};
+pub use clip::Clip;
+pub use error::{Result, SpeedyError};
 pub use ffmpeg_wrapper::{FFmpegCommand, VideoInfo, check_ffmpeg, get_video_info};
+#[cfg(feature = "async")]
+pub use ffmpeg_wrapper::{check_ffmpeg_async, get_video_info_async};
+pub use job::JobSpec;
+pub use lut::CubeLut;
+pub use order::InputOrder;
+pub use plan::PlannedCommand;
 pub use presets::Preset;
+pub use progress::ProgressEvent;
+#[cfg(feature = "async")]
+pub use progress::ProgressStream;
+pub use ramp::{Easing, SpeedRamp};
+pub use retime::Interpolation;
+pub use transition::Transition;
 pub use video_processor::VideoProcessor;
 
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, ValueEnum, PartialEq)]
+#[derive(Clone, Debug, ValueEnum, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum ColorProfile {
     Standard,
     DLog,
@@ -0,0 +1,319 @@
+//! Adobe `.cube` LUTs, read on the Rust side so that a broken file is
+//! reported before ffmpeg is spawned rather than halfway through an encode.
+//!
+//! A `.cube` file is text: keyword lines (`TITLE "name"`, `LUT_3D_SIZE 33` or
+//! `LUT_1D_SIZE 1024`, and optionally `DOMAIN_MIN r g b` / `DOMAIN_MAX r g b`)
+//! followed by one `r g b` line per table entry, with `#` comment lines and
+//! blank lines allowed anywhere. A 3D table holds `size³` entries, red changing
+//! fastest, then green, then blue; a 1D table holds `size` entries, one per
+//! input level, each channel mapped on its own.
+
+use std::io::{BufRead, BufReader};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::error::{Result, SpeedyError};
+
+/// A parsed `.cube` LUT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeLut {
+    /// The `TITLE`, when the file gives one.
+    pub title: Option<String>,
+    pub kind: LutKind,
+    /// Points along each axis (3D) or entries in all (1D).
+    pub size: usize,
+    /// The input value mapped by the first entry, per channel.
+    pub domain_min: [f64; 3],
+    /// The input value mapped by the last entry, per channel.
+    pub domain_max: [f64; 3],
+    /// The output of every entry, in file order.
+    pub table: Vec<[f64; 3]>,
+}
+
+/// Whether a LUT maps colours as a whole or each channel on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LutKind {
+    /// `LUT_1D_SIZE`: one curve per channel (ffmpeg's `lut1d`).
+    OneD,
+    /// `LUT_3D_SIZE`: a lattice over the colour cube (ffmpeg's `lut3d`).
+    ThreeD,
+}
+
+/// Sizes the `.cube` specification allows for a 3D LUT.
+pub const LUT_3D_SIZES: RangeInclusive<usize> = 2..=256;
+
+/// Sizes the `.cube` specification allows for a 1D LUT.
+pub const LUT_1D_SIZES: RangeInclusive<usize> = 2..=65536;
+
+impl CubeLut {
+    /// Read and check the `.cube` file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            SpeedyError::io(
+                format!("Failed to read LUT {path}", path = path.display()),
+                e,
+            )
+        })?;
+        Self::parse(path, &text)
+    }
+
+    /// Parse and check `.cube` text read from `path` (which errors name).
+    pub fn parse(path: &Path, text: &str) -> Result<Self> {
+        let invalid = |line: Option<usize>, message: String| SpeedyError::InvalidLut {
+            path: path.to_path_buf(),
+            line,
+            message,
+        };
+        let mut title = None;
+        let mut shape: Option<(LutKind, usize)> = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let at = |message: String| invalid(Some(line), message);
+            // Only a line that starts with `#` is a comment; a `#` later on is
+            // text (as in `TITLE "Grade #2"`).
+            let content = raw.trim();
+            if content.starts_with('#') {
+                continue;
+            }
+            let Some(keyword) = content.split_whitespace().next() else {
+                continue;
+            };
+            let rest = content[keyword.len()..].trim();
+
+            // Table entries start with a number; everything else is a keyword.
+            if keyword.parse::<f64>().is_ok() {
+                if shape.is_none() {
+                    return Err(at(
+                        "table entry before LUT_3D_SIZE or LUT_1D_SIZE".to_string()
+                    ));
+                }
+                let rgb = triple(content).ok_or_else(|| {
+                    at(format!(
+                        "{content:?} is not an entry; expected three numbers \"R G B\""
+                    ))
+                })?;
+                table.push(rgb);
+                continue;
+            }
+            if !table.is_empty() {
+                return Err(at(format!("{keyword} after the table began")));
+            }
+            match keyword {
+                "TITLE" => {
+                    let text = rest
+                        .strip_prefix('"')
+                        .and_then(|t| t.strip_suffix('"'))
+                        .ok_or_else(|| at(format!("TITLE {rest} must be a quoted string")))?;
+                    if title.replace(text.to_string()).is_some() {
+                        return Err(at("TITLE given twice".to_string()));
+                    }
+                }
+                "LUT_3D_SIZE" | "LUT_1D_SIZE" => {
+                    let (kind, sizes) = if keyword == "LUT_3D_SIZE" {
+                        (LutKind::ThreeD, LUT_3D_SIZES)
+                    } else {
+                        (LutKind::OneD, LUT_1D_SIZES)
+                    };
+                    let size = rest
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|size| sizes.contains(size))
+                        .ok_or_else(|| {
+                            at(format!(
+                                "{keyword} {rest} must be a whole number from {min} to {max}",
+                                min = sizes.start(),
+                                max = sizes.end()
+                            ))
+                        })?;
+                    if shape.replace((kind, size)).is_some() {
+                        return Err(at(
+                            "a file holds one table: give LUT_3D_SIZE or LUT_1D_SIZE once"
+                                .to_string(),
+                        ));
+                    }
+                }
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let bounds = triple(rest).ok_or_else(|| {
+                        at(format!("{keyword} {rest} must be three numbers \"R G B\""))
+                    })?;
+                    if keyword == "DOMAIN_MIN" {
+                        domain_min = bounds;
+                    } else {
+                        domain_max = bounds;
+                    }
+                }
+                _ => log::debug!(
+                    "{path} line {line}: skipping unknown keyword {keyword}",
+                    path = path.display()
+                ),
+            }
+        }
+
+        let Some((kind, size)) = shape else {
+            return Err(invalid(
+                None,
+                "no LUT_3D_SIZE or LUT_1D_SIZE; not a .cube LUT".to_string(),
+            ));
+        };
+        for channel in 0..3 {
+            let (min, max) = (domain_min[channel], domain_max[channel]);
+            if min >= max {
+                return Err(invalid(
+                    None,
+                    format!(
+                        "DOMAIN_MIN {min} must be below DOMAIN_MAX {max} for the {name} channel",
+                        name = ["red", "green", "blue"][channel]
+                    ),
+                ));
+            }
+        }
+        let expected = match kind {
+            LutKind::ThreeD => size.pow(3),
+            LutKind::OneD => size,
+        };
+        if table.len() != expected {
+            return Err(invalid(
+                None,
+                format!(
+                    "expected {expected} entries for a {size}-point {dimension} LUT, found {found}",
+                    dimension = match kind {
+                        LutKind::ThreeD => "3D",
+                        LutKind::OneD => "1D",
+                    },
+                    found = table.len()
+                ),
+            ));
+        }
+        Ok(Self {
+            title,
+            kind,
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    /// The output at lattice point `(r, g, b)` of a 3D LUT (each below
+    /// `size`), or at entry `r` of a 1D LUT.
+    pub fn entry(&self, r: usize, g: usize, b: usize) -> Option<[f64; 3]> {
+        let index = match self.kind {
+            LutKind::ThreeD if g < self.size && b < self.size && r < self.size => {
+                r + self.size * (g + self.size * b)
+            }
+            LutKind::ThreeD => return None,
+            LutKind::OneD => r,
+        };
+        self.table.get(index).copied()
+    }
+}
+
+/// Whether `path` names a `.cube` file, which [`CubeLut`] reads; ffmpeg also
+/// takes other LUT formats, which are passed on unchecked.
+pub fn is_cube_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cube"))
+}
+
+/// The kind of the `.cube` LUT at `path`, from its header alone (the first
+/// `LUT_3D_SIZE` or `LUT_1D_SIZE`), for picking the ffmpeg filter without
+/// reading the whole table; `None` when it cannot be told.
+pub fn cube_kind(path: &Path) -> Option<LutKind> {
+    let file = std::fs::File::open(path).ok()?;
+    for line in BufReader::new(file).lines() {
+        let line = line.ok()?;
+        match line.split_whitespace().next() {
+            Some("LUT_3D_SIZE") => return Some(LutKind::ThreeD),
+            Some("LUT_1D_SIZE") => return Some(LutKind::OneD),
+            Some(word) if word.parse::<f64>().is_ok() => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Three finite numbers separated by whitespace.
+fn triple(text: &str) -> Option<[f64; 3]> {
+    let mut values = text.split_whitespace().map(|v| v.parse::<f64>().ok());
+    let rgb = [values.next()??, values.next()??, values.next()??];
+    (values.next().is_none() && rgb.iter().all(|v| v.is_finite())).then_some(rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<CubeLut> {
+        CubeLut::parse(Path::new("grade.cube"), text)
+    }
+
+    /// A 2-point identity 3D LUT, red changing fastest.
+    const IDENTITY: &str = "# made by hand\n\
+        TITLE \"Identity\"\n\
+        LUT_3D_SIZE 2\n\
+        DOMAIN_MIN 0 0 0\n\
+        DOMAIN_MAX 1 1 1\n\
+        \n\
+        0 0 0\n1 0 0\n0 1 0\n1 1 0\n\
+        0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+
+    #[test]
+    fn cube_files_parse_into_an_indexable_table() -> anyhow::Result<()> {
+        let lut = parse(IDENTITY)?;
+        assert_eq!(lut.title.as_deref(), Some("Identity"));
+        assert_eq!(
+            (lut.kind, lut.size, lut.table.len()),
+            (LutKind::ThreeD, 2, 8)
+        );
+        assert_eq!(lut.entry(1, 0, 1), Some([1.0, 0.0, 1.0]));
+        assert_eq!(lut.entry(2, 0, 0), None);
+
+        let curve = parse("LUT_1D_SIZE 3\nDOMAIN_MAX 2 2 2\n0 0 0\n0.5 0.5 0.5\n1 1 1\n")?;
+        assert_eq!((curve.kind, curve.size), (LutKind::OneD, 3));
+        assert_eq!(curve.domain_max, [2.0; 3]);
+        assert_eq!(curve.entry(1, 0, 0), Some([0.5; 3]));
+
+        // A `#` inside the title is part of it, not a comment.
+        let text = IDENTITY.replace("\"Identity\"", "\"Grade #2\"");
+        assert_eq!(parse(&text)?.title.as_deref(), Some("Grade #2"));
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_files_name_the_problem_and_its_line() {
+        // The line an invalid LUT is reported at; `None` if it parses.
+        let line_of = |text: &str| match parse(text) {
+            Err(SpeedyError::InvalidLut { line, .. }) => Some(line),
+            _ => None,
+        };
+        // An entry short of a channel, on line 4.
+        assert_eq!(line_of("LUT_3D_SIZE 2\n0 0 0\n1 0 0\n0 1\n"), Some(Some(4)));
+        assert_eq!(line_of("0 0 0\nLUT_1D_SIZE 2\n"), Some(Some(1)));
+        assert_eq!(line_of("LUT_3D_SIZE 1\n"), Some(Some(1)));
+        assert_eq!(line_of("LUT_3D_SIZE 2\nLUT_1D_SIZE 2\n"), Some(Some(2)));
+        assert_eq!(line_of("TITLE untitled\nLUT_1D_SIZE 2\n"), Some(Some(1)));
+        assert_eq!(
+            line_of("LUT_1D_SIZE 2\n0 0 0\nDOMAIN_MAX 1 1 1\n1 1 1\n"),
+            Some(Some(3))
+        );
+        // Whole-file problems have no line.
+        assert_eq!(line_of("TITLE \"empty\"\n"), Some(None));
+        assert_eq!(line_of("LUT_1D_SIZE 2\n0 0 0\n"), Some(None));
+        assert_eq!(
+            line_of("LUT_1D_SIZE 2\nDOMAIN_MIN 1 0 0\n0 0 0\n1 1 1\n"),
+            Some(None)
+        );
+
+        let short = IDENTITY.strip_suffix("1 1 1\n").unwrap_or_default();
+        let message = parse(short).map_err(|e| e.to_string());
+        assert_eq!(
+            message,
+            Err("invalid LUT grade.cube: expected 8 entries for a 2-point 3D LUT, found 7".into())
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! The order stitched inputs are joined in.
+//!
+//! Inputs are normally used in the order given, with a directory expanded in
+//! file-name order. That breaks down when two cameras are mixed or a file
+//! counter rolls over; [`sort_clips`] orders clips by capture time instead,
+//! from the probed container creation time or start timecode, or by file
+//! modification time.
+
+use clap::ValueEnum;
+use std::time::UNIX_EPOCH;
+
+use crate::chapters::{name_key, parse_creation_time, timecode_seconds};
+use crate::error::{Result, SpeedyError};
+use crate::{Clip, VideoInfo, get_video_info};
+
+/// What to order stitched inputs by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InputOrder {
+    /// File name, with each GoPro recording's chapters in order.
+    Name,
+    /// Creation time from the container metadata.
+    CreationTime,
+    /// Start timecode.
+    Timecode,
+    /// File modification time.
+    Mtime,
+}
+
+/// Sort `clips` by `order`, and return what each clip (in its new place) was
+/// sorted by, for showing the order: `None` for [`InputOrder::Name`], and for
+/// a clip without that metadata. Such clips go last, by name; ties (e.g. the
+/// chapters of one recording sharing a creation time) are broken by name.
+pub fn sort_clips(clips: &mut [Clip], order: InputOrder) -> Result<Vec<Option<String>>> {
+    let keys = clips
+        .iter()
+        .map(|clip| sort_key(clip, order))
+        .collect::<Result<Vec<_>>>()?;
+    let mut keyed: Vec<(Clip, Option<(f64, String)>)> = clips.iter().cloned().zip(keys).collect();
+    keyed.sort_by(|(a, a_key), (b, b_key)| {
+        let time = |key: &Option<(f64, String)>| key.as_ref().map(|(seconds, _)| *seconds);
+        match (time(a_key), time(b_key)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            (a_time, b_time) => b_time.is_some().cmp(&a_time.is_some()),
+        }
+        .then_with(|| name_key(&a.path).cmp(&name_key(&b.path)))
+    });
+    let mut shown = Vec::with_capacity(keyed.len());
+    for (slot, (clip, key)) in clips.iter_mut().zip(keyed) {
+        *slot = clip;
+        shown.push(key.map(|(_, text)| text));
+    }
+    Ok(shown)
+}
+
+/// A clip's place in `order` in seconds, with the value it came from as shown
+/// to the user.
+fn sort_key(clip: &Clip, order: InputOrder) -> Result<Option<(f64, String)>> {
+    match order {
+        InputOrder::Name => Ok(None),
+        InputOrder::CreationTime | InputOrder::Timecode => {
+            Ok(capture_key(&get_video_info(&clip.path)?, order))
+        }
+        InputOrder::Mtime => {
+            let modified = std::fs::metadata(&clip.path)
+                .and_then(|m| m.modified())
+                .map_err(|e| {
+                    SpeedyError::io(
+                        format!(
+                            "Failed to read the modification time of {path}",
+                            path = clip.path.display()
+                        ),
+                        e,
+                    )
+                })?;
+            // A time before the epoch only comes from a broken clock; sort it
+            // with the clips that have no time.
+            Ok(modified
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since| since.as_secs_f64())
+                .map(|seconds| (seconds, format_utc(seconds))))
+        }
+    }
+}
+
+/// The probed capture time (creation time or timecode) `order` sorts by.
+fn capture_key(info: &VideoInfo, order: InputOrder) -> Option<(f64, String)> {
+    match order {
+        InputOrder::CreationTime => {
+            let text = info.creation_time.as_deref()?;
+            Some((parse_creation_time(text)?, text.to_string()))
+        }
+        InputOrder::Timecode => Some((timecode_seconds(info)?, info.timecode.clone()?)),
+        InputOrder::Name | InputOrder::Mtime => None,
+    }
+}
+
+/// Seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS UTC`.
+fn format_utc(seconds: f64) -> String {
+    let total = seconds as i64;
+    let (days, time) = (total.div_euclid(86400), total.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}-{month:02}-{day:02} {hours:02}:{minutes:02}:{secs:02} UTC",
+        hours = time / 3600,
+        minutes = time / 60 % 60,
+        secs = time % 60
+    )
+}
+
+/// The proleptic Gregorian date `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn capture_keys_come_from_creation_time_or_timecode() {
+        let info = VideoInfo {
+            fps: 25.0,
+            creation_time: Some("2024-05-01T10:00:00.000000Z".to_string()),
+            timecode: Some("10:00:01:05".to_string()),
+            ..VideoInfo::default()
+        };
+        let (seconds, text) = capture_key(&info, InputOrder::CreationTime).unwrap_or_default();
+        assert_eq!(format_utc(seconds), "2024-05-01 10:00:00 UTC");
+        assert_eq!(text, "2024-05-01T10:00:00.000000Z");
+        assert_eq!(
+            capture_key(&info, InputOrder::Timecode),
+            Some((36001.2, "10:00:01:05".to_string()))
+        );
+        assert_eq!(
+            capture_key(&VideoInfo::default(), InputOrder::Timecode),
+            None
+        );
+    }
+
+    #[test]
+    fn mtime_orders_files_with_ties_broken_by_name() -> anyhow::Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("speedy_order_test_{pid}", pid = std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_557_600);
+        // A second camera's counter: its file names sort before the first's.
+        let files = [
+            ("DJI_0001.MP4", 60),
+            ("GX020042.MP4", 0),
+            ("GX010042.MP4", 0),
+            ("A001.MP4", 30),
+        ];
+        let mut clips = Vec::new();
+        for (name, offset) in files {
+            let path: PathBuf = dir.join(name);
+            std::fs::File::create(&path)?.set_modified(base + Duration::from_secs(offset))?;
+            clips.push(Clip::new(path));
+        }
+        let shown = sort_clips(&mut clips, InputOrder::Mtime);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let shown = shown?;
+        let names: Vec<String> = clips
+            .iter()
+            .filter_map(|c| c.path.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect();
+        assert_eq!(
+            names,
+            ["GX010042.MP4", "GX020042.MP4", "A001.MP4", "DJI_0001.MP4"]
+        );
+        assert_eq!(shown[0].as_deref(), Some("2024-05-01 10:00:00 UTC"));
+        Ok(())
+    }
+}
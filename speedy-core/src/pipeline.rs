@@ -0,0 +1,403 @@
+//! A processing job as a flat list of ffmpeg steps.
+//!
+//! [`crate::VideoProcessor`] turns its settings into a [`Pipeline`] once, and
+//! the same steps are then run either blocking ([`Pipeline::run`]) or on the
+//! tokio runtime ([`Pipeline::run_async`], with the `async` feature). Every
+//! step carries its progress stage name and weight, so both runners report the
+//! same overall percentage, and [`Pipeline::plan`] lists them for a dry run.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::cancel::CancellationToken;
+use crate::error::{Result, SpeedyError};
+use crate::ffmpeg_wrapper::{STITCH_AUDIO_RATE, fps_string_value};
+use crate::plan::PlannedCommand;
+use crate::progress::{JobProgress, ProgressEvent, Stage};
+use crate::ramp::RampSegment;
+use crate::stabilize::{self, Check, CheckedPass, ConcatPass, PassControl};
+use crate::{FFmpegCommand, get_video_info};
+
+/// The steps of one job, run in order.
+pub(crate) struct Pipeline {
+    pub(crate) steps: Vec<Step>,
+    /// Scratch directories for intermediates: created before the first step
+    /// and removed after the last, whether the job succeeded or not.
+    pub(crate) temp_dirs: Vec<PathBuf>,
+    /// Attempts for each retried (validated) pass.
+    pub(crate) attempts: u32,
+}
+
+/// One step: what it runs, and how it shows up in the progress.
+pub(crate) struct Step {
+    pub(crate) stage: String,
+    /// Share of the job's work, relative to the sum over all steps.
+    pub(crate) weight: f64,
+    pub(crate) action: Action,
+}
+
+pub(crate) enum Action {
+    /// A single ffmpeg run.
+    Run(FFmpegCommand),
+    /// A pass retried until its output validates (the vidstab passes).
+    Checked(CheckedPass),
+    /// A stream-copy join of segments.
+    Concat(ConcatPass),
+    /// Audio fitted to a segment whose duration is only known once it exists.
+    FitAudio(FitAudio),
+}
+
+/// Grade a clip's audio into a PCM intermediate as long as a stabilized video
+/// segment: retimed with the job's speed, resampled to the stitch format, and
+/// padded/trimmed to the segment's exact duration. A clip without audio yields
+/// silence instead.
+pub(crate) struct FitAudio {
+    /// Source clip, or `None` for silence.
+    pub(crate) clip: Option<PathBuf>,
+    /// `(start, end)` seconds of the clip to use, when it is trimmed.
+    pub(crate) trim: Option<(f64, f64)>,
+    /// The segment to match.
+    pub(crate) video: PathBuf,
+    /// The segment's constant frame rate, when known; its duration is then
+    /// exact (frame count / rate) rather than the container's.
+    pub(crate) fps: Option<String>,
+    /// Expected duration of the segment, shown by a dry run.
+    pub(crate) estimate: f64,
+    /// The clip's own speed, applied before `speed` (or the ramp).
+    pub(crate) clip_speed: f64,
+    pub(crate) speed: f64,
+    /// Constant-speed spans of a speed ramp over the clip, used instead of
+    /// `speed` when not empty.
+    pub(crate) ramp: Vec<RampSegment>,
+    pub(crate) output: PathBuf,
+}
+
+impl FitAudio {
+    /// The ffmpeg command writing `seconds` of audio.
+    fn command(&self, seconds: f64) -> FFmpegCommand {
+        let Some(clip) = &self.clip else {
+            return stabilize::silence_command(&self.output, seconds);
+        };
+        let mut cmd = FFmpegCommand::new(clip, &self.output);
+        if let Some((start, end)) = self.trim {
+            cmd = cmd.trim(start, end);
+        }
+        cmd = cmd.speed(self.clip_speed, true, None);
+        cmd = if self.ramp.is_empty() {
+            cmd.speed(self.speed, true, None)
+        } else {
+            cmd.speed_ramp(&self.ramp, true, None)
+        };
+        cmd.audio_only()
+            .audio_filter(&format!(
+                "aresample={STITCH_AUDIO_RATE},aformat=sample_fmts=s16:channel_layouts=stereo,\
+                 apad,atrim=duration={seconds:.6}"
+            ))
+            .audio_codec("pcm_s16le")
+            .total_duration(seconds)
+            .overwrite()
+    }
+
+    /// Duration of the segment in seconds.
+    fn seconds(&self) -> Result<f64> {
+        if let Some(rate) = self.fps.as_deref().and_then(fps_string_value)
+            && let Some(frames) = stabilize::frame_count(&self.video)
+        {
+            return Ok(frames as f64 / rate);
+        }
+        Ok(get_video_info(&self.video)?.duration)
+    }
+
+    /// [`seconds`](Self::seconds) without blocking the async runtime.
+    #[cfg(feature = "async")]
+    async fn seconds_async(&self) -> Result<f64> {
+        if let Some(rate) = self.fps.as_deref().and_then(fps_string_value)
+            && let Some(frames) = stabilize::frame_count_async(&self.video).await
+        {
+            return Ok(frames as f64 / rate);
+        }
+        Ok(crate::ffmpeg_wrapper::get_video_info_async(&self.video)
+            .await?
+            .duration)
+    }
+}
+
+impl Pipeline {
+    /// Run every step in order, blocking until the job is done.
+    pub(crate) fn run(
+        &self,
+        sink: &(dyn Fn(&ProgressEvent) + Sync),
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
+        self.create_temp_dirs()?;
+        let mut job = JobProgress::new(sink, self.total_weight());
+        let result = self.steps.iter().try_for_each(|step| {
+            check_cancelled(cancel)?;
+            let stage = job.stage(step.stage.as_str(), step.weight);
+            let done = AtomicBool::new(false);
+            let report = |event: &ProgressEvent| {
+                done.fetch_or(event.done, Ordering::Relaxed);
+                stage.report(event);
+            };
+            let ctl = self.control(&report, cancel);
+            match &step.action {
+                Action::Run(cmd) => ctl.attach(cmd.clone()).execute(ctl.on_progress),
+                Action::Checked(pass) => stabilize::run_checked(pass, &ctl),
+                Action::Concat(pass) => stabilize::run_concat(pass, &ctl),
+                Action::FitAudio(fit) => {
+                    let seconds = fit.seconds()?;
+                    ctl.attach(fit.command(seconds)).execute(ctl.on_progress)
+                }
+            }?;
+            finish_unless_done(&stage, &done);
+            Ok(())
+        });
+        self.remove_temp_dirs();
+        result
+    }
+
+    /// [`run`](Self::run) on the tokio runtime.
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_async(
+        &self,
+        sink: &(dyn Fn(&ProgressEvent) + Sync),
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
+        self.create_temp_dirs()?;
+        let mut job = JobProgress::new(sink, self.total_weight());
+        let mut result = Ok(());
+        for step in &self.steps {
+            result = self.run_step_async(step, &mut job, cancel).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        self.remove_temp_dirs();
+        result
+    }
+
+    #[cfg(feature = "async")]
+    async fn run_step_async(
+        &self,
+        step: &Step,
+        job: &mut JobProgress<'_>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
+        check_cancelled(cancel)?;
+        let stage = job.stage(step.stage.as_str(), step.weight);
+        let done = AtomicBool::new(false);
+        let report = |event: &ProgressEvent| {
+            done.fetch_or(event.done, Ordering::Relaxed);
+            stage.report(event);
+        };
+        let ctl = self.control(&report, cancel);
+        match &step.action {
+            Action::Run(cmd) => ctl.attach(cmd.clone()).run_async(ctl.on_progress).await,
+            Action::Checked(pass) => stabilize::run_checked_async(pass, &ctl).await,
+            Action::Concat(pass) => stabilize::run_concat_async(pass, &ctl).await,
+            Action::FitAudio(fit) => {
+                let seconds = fit.seconds_async().await?;
+                ctl.attach(fit.command(seconds))
+                    .run_async(ctl.on_progress)
+                    .await
+            }
+        }?;
+        finish_unless_done(&stage, &done);
+        Ok(())
+    }
+
+    /// The commands [`run`](Self::run) would spawn, in order.
+    pub(crate) fn plan(&self) -> Vec<PlannedCommand> {
+        let mut planned = Vec::new();
+        for step in &self.steps {
+            let stage = step.stage.as_str();
+            match &step.action {
+                Action::Run(cmd) => planned.push(PlannedCommand::from_command(stage, &cmd.build())),
+                Action::Checked(pass) => {
+                    let until = match &pass.check {
+                        Check::NonEmpty(path) => {
+                            format!("{path} is written", path = path.display())
+                        }
+                        Check::SameFrameCount { input, .. } => format!(
+                            "the output has as many frames as {input}",
+                            input = input.display()
+                        ),
+                    };
+                    planned.push(
+                        PlannedCommand::from_command(stage, &pass.command.build()).with_note(
+                            format!(
+                                "retried up to {attempts} times until {until}",
+                                attempts = self.attempts
+                            ),
+                        ),
+                    );
+                }
+                Action::Concat(pass) => planned.push(
+                    PlannedCommand::from_command(stage, &pass.command.build()).with_note(format!(
+                        "reads {list}, written beforehand as:\n{body}",
+                        list = pass.list.display(),
+                        body = pass.body.trim_end()
+                    )),
+                ),
+                Action::FitAudio(fit) => {
+                    if fit.fps.is_some() {
+                        planned.push(PlannedCommand::from_command(
+                            stage,
+                            &stabilize::frame_count_command(&fit.video),
+                        ));
+                    } else {
+                        planned.push(PlannedCommand::from_command(
+                            stage,
+                            &crate::ffmpeg_wrapper::probe_command(&fit.video),
+                        ));
+                    }
+                    planned.push(
+                        PlannedCommand::from_command(stage, &fit.command(fit.estimate).build())
+                            .with_note(format!(
+                                "duration ({estimate:.3}s expected) is measured from {video} once it exists",
+                                estimate = fit.estimate,
+                                video = fit.video.display()
+                            )),
+                    );
+                }
+            }
+        }
+        planned
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.steps.iter().map(|step| step.weight).sum()
+    }
+
+    fn control<'a>(
+        &self,
+        on_progress: &'a (dyn Fn(&ProgressEvent) + Sync),
+        cancel: Option<&'a CancellationToken>,
+    ) -> PassControl<'a> {
+        PassControl {
+            attempts: self.attempts,
+            on_progress,
+            cancel,
+        }
+    }
+
+    fn create_temp_dirs(&self) -> Result<()> {
+        for tmp in &self.temp_dirs {
+            std::fs::create_dir_all(tmp).map_err(|e| {
+                SpeedyError::io(
+                    format!("Failed to create temp dir {tmp}", tmp = tmp.display()),
+                    e,
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn remove_temp_dirs(&self) {
+        for tmp in &self.temp_dirs {
+            if let Err(e) = std::fs::remove_dir_all(tmp) {
+                log::debug!("could not clean temp dir {tmp}: {e}", tmp = tmp.display());
+            }
+        }
+    }
+}
+
+/// Stop between steps once cancelled; running ffmpeg passes are killed by
+/// [`FFmpegCommand::execute`] itself.
+fn check_cancelled(cancel: Option<&CancellationToken>) -> Result<()> {
+    if cancel.is_some_and(CancellationToken::is_cancelled) {
+        return Err(SpeedyError::Cancelled);
+    }
+    Ok(())
+}
+
+/// Close a stage that ended without a final event of its own (e.g. a
+/// stream-copy pass, whose duration ffmpeg does not know up front).
+fn finish_unless_done(stage: &Stage, done: &AtomicBool) {
+    if !done.load(Ordering::Relaxed) {
+        stage.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn encode_step(stage: &str, weight: f64) -> Step {
+        Step {
+            stage: stage.to_string(),
+            weight,
+            action: Action::Run(FFmpegCommand::new("/no/such/in.mp4", "/no/such/out.mp4")),
+        }
+    }
+
+    #[test]
+    fn cancelled_pipeline_runs_no_step_and_cleans_its_temp_dir() {
+        let tmp = std::env::temp_dir().join(format!(
+            "speedy-pipeline-test-{pid}",
+            pid = std::process::id()
+        ));
+        let pipeline = Pipeline {
+            steps: vec![encode_step("grade", 1.0), encode_step("mux", 0.1)],
+            temp_dirs: vec![tmp.clone()],
+            attempts: 1,
+        };
+        let token = CancellationToken::new();
+        token.cancel();
+        let seen = Mutex::new(Vec::new());
+        let sink = |e: &ProgressEvent| {
+            if let Ok(mut seen) = seen.lock() {
+                seen.push(e.stage.clone());
+            }
+        };
+        let r = pipeline.run(&sink, Some(&token));
+        assert!(matches!(r, Err(SpeedyError::Cancelled)), "{r:?}");
+        assert!(seen.into_inner().unwrap_or_default().is_empty());
+        assert!(!tmp.exists(), "temp dir must be removed");
+    }
+
+    #[test]
+    fn plan_lists_each_command_with_its_stage_and_notes() {
+        let pass = stabilize::detect_pass(
+            std::path::Path::new("/clips/a.mp4"),
+            std::path::Path::new("/tmp/stab/t_0.trf"),
+            &stabilize::VidstabParams::default(),
+        );
+        let pipeline = Pipeline {
+            steps: vec![
+                encode_step("grade", 1.0),
+                Step {
+                    stage: "detect".to_string(),
+                    weight: 0.6,
+                    action: Action::Checked(pass),
+                },
+            ],
+            temp_dirs: Vec::new(),
+            attempts: 6,
+        };
+        let planned = pipeline.plan();
+        let stages: Vec<&str> = planned.iter().map(|p| p.stage.as_str()).collect();
+        assert_eq!(stages, ["grade", "detect"]);
+        assert_eq!(planned[0].argv.first().map(String::as_str), Some("ffmpeg"));
+        assert_eq!(planned[1].current_dir, Some(PathBuf::from("/tmp/stab")));
+        assert_eq!(
+            planned[1].note.as_deref(),
+            Some("retried up to 6 times until /tmp/stab/t_0.trf is written")
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_runner_stops_on_cancellation_too() {
+        let pipeline = Pipeline {
+            steps: vec![encode_step("encode", 1.0)],
+            temp_dirs: Vec::new(),
+            attempts: 1,
+        };
+        let token = CancellationToken::new();
+        token.cancel();
+        let r = pipeline.run_async(&|_| {}, Some(&token)).await;
+        assert!(matches!(r, Err(SpeedyError::Cancelled)), "{r:?}");
+    }
+}
@@ -0,0 +1,104 @@
+//! Dry runs: the commands a job would run, for review before a long encode.
+//!
+//! [`crate::VideoProcessor::plan`] lists every ffprobe/ffmpeg invocation of a
+//! job, in order, without running any ffmpeg. Each [`PlannedCommand`] renders
+//! as a shell line that can be pasted and run by hand.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// One ffprobe/ffmpeg invocation of a planned job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedCommand {
+    /// Progress stage it belongs to (e.g. `"probe"`, `"transform 2/3"`).
+    pub stage: String,
+    /// Program followed by its arguments.
+    pub argv: Vec<String>,
+    /// Directory the program runs from, when it is not the caller's.
+    pub current_dir: Option<PathBuf>,
+    /// What the command line alone does not tell: retries, files written
+    /// beforehand, values only known at run time.
+    pub note: Option<String>,
+}
+
+impl PlannedCommand {
+    pub(crate) fn from_command(stage: impl Into<String>, cmd: &Command) -> Self {
+        let argv = std::iter::once(cmd.get_program())
+            .chain(cmd.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        Self {
+            stage: stage.into(),
+            argv,
+            current_dir: cmd.get_current_dir().map(PathBuf::from),
+            note: None,
+        }
+    }
+
+    pub(crate) fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// The command as one POSIX shell line, with arguments quoted as needed
+    /// and a `cd` into its working directory, if any.
+    pub fn shell(&self) -> String {
+        let line = self
+            .argv
+            .iter()
+            .map(|arg| quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match &self.current_dir {
+            Some(dir) => format!("cd {dir} && {line}", dir = quote(&dir.to_string_lossy())),
+            None => line,
+        }
+    }
+}
+
+/// Renders as `# stage` and note comment lines, then the shell line.
+impl fmt::Display for PlannedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# {stage}", stage = self.stage)?;
+        if let Some(note) = &self.note {
+            for line in note.lines() {
+                writeln!(f, "#   {line}")?;
+            }
+        }
+        write!(f, "{shell}", shell = self.shell())
+    }
+}
+
+fn quote(arg: &str) -> Cow<'_, str> {
+    // Only a NUL byte fails to quote, and no process argument can hold one.
+    shlex::try_quote(arg).unwrap_or(Cow::Borrowed(arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_line_quotes_arguments_that_need_it() {
+        let mut cmd = Command::new("ffmpeg");
+        cmd.args(["-i", "my clip.mp4", "-vf", "lut3d='a.cube'", "out.mp4"])
+            .current_dir("/tmp/with space");
+        let planned = PlannedCommand::from_command("encode", &cmd);
+        assert_eq!(
+            planned.shell(),
+            r#"cd '/tmp/with space' && ffmpeg -i 'my clip.mp4' -vf "lut3d='a.cube'" out.mp4"#
+        );
+    }
+
+    #[test]
+    fn display_prefixes_stage_and_note_as_comments() {
+        let planned = PlannedCommand::from_command("concat", &Command::new("ffmpeg"))
+            .with_note("writes list.txt:\nfile 'a.mkv'");
+        assert_eq!(
+            planned.to_string(),
+            "# concat\n#   writes list.txt:\n#   file 'a.mkv'\nffmpeg"
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Structured progress reporting.
+//!
+//! ffmpeg runs with `-progress pipe:1`, which prints machine-readable
+//! `key=value` blocks on stdout, each terminated by a `progress=continue` (or
+//! `progress=end`) line. [`ProgressParser`] turns those blocks into
+//! [`ProgressEvent`]s. [`JobProgress`] then maps the events of each pass of a
+//! multi-pass job (grade, detect and transform per segment, then concat) onto
+//! one overall percentage. With the `async` feature, [`ProgressStream`]
+//! delivers the same events as a [`tokio_stream::Stream`].
+
+use std::time::Duration;
+
+/// One progress update from a running job.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgressEvent {
+    /// Name of the pass this update belongs to (e.g. `"encode"`,
+    /// `"transform 2/3"`).
+    pub stage: String,
+    /// Frames written so far by this pass.
+    pub frame: u64,
+    /// Current encoding rate in frames per second.
+    pub fps: f64,
+    /// Output timestamp reached by this pass, in seconds.
+    pub out_time: f64,
+    /// Current output bitrate in kbit/s, when ffmpeg knows it.
+    pub bitrate_kbps: Option<f64>,
+    /// Processing speed relative to real time (`2.0` = twice real time).
+    pub speed: Option<f64>,
+    /// Estimated wall-clock time left for this pass.
+    pub eta: Option<Duration>,
+    /// Completion of this pass (0-100), when its duration is known.
+    pub stage_percent: Option<f64>,
+    /// Completion of the whole job (0-100). Equals `stage_percent` for a
+    /// single ffmpeg run.
+    pub percent: Option<f64>,
+    /// Set on the final event of a pass.
+    pub done: bool,
+}
+
+/// Incremental parser for ffmpeg's `-progress` output.
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    /// Expected output duration in seconds, for percent and ETA.
+    total_duration: Option<f64>,
+    current: ProgressEvent,
+}
+
+impl ProgressParser {
+    pub fn new(total_duration: Option<f64>) -> Self {
+        Self {
+            total_duration: total_duration.filter(|d| *d > 0.0),
+            current: ProgressEvent::default(),
+        }
+    }
+
+    /// Set the expected duration once it becomes known (e.g. parsed from the
+    /// `Duration:` line on stderr).
+    pub fn set_total_duration(&mut self, seconds: f64) {
+        if seconds > 0.0 {
+            self.total_duration = Some(seconds);
+        }
+    }
+
+    /// Feed one stdout line; returns an event when it completes a block.
+    pub fn feed(&mut self, line: &str) -> Option<ProgressEvent> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "frame" => self.current.frame = value.parse().unwrap_or(self.current.frame),
+            "fps" => self.current.fps = value.parse().unwrap_or(self.current.fps),
+            "bitrate" => {
+                self.current.bitrate_kbps = value
+                    .strip_suffix("kbits/s")
+                    .and_then(|v| v.trim().parse().ok());
+            }
+            // Both are microseconds (`out_time_ms` is misnamed by ffmpeg);
+            // prefer them over the formatted `out_time`. Early blocks report
+            // N/A or a negative time before the first frame.
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.current.out_time = us.max(0) as f64 / 1_000_000.0;
+                }
+            }
+            "speed" => {
+                self.current.speed = value
+                    .strip_suffix('x')
+                    .and_then(|v| v.trim().parse().ok())
+                    .filter(|s: &f64| *s > 0.0);
+            }
+            "progress" => return Some(self.finish_block(value == "end")),
+            _ => {}
+        }
+        None
+    }
+
+    fn finish_block(&mut self, done: bool) -> ProgressEvent {
+        let mut event = self.current.clone();
+        event.done = done;
+        if let Some(total) = self.total_duration {
+            let percent = if done {
+                100.0
+            } else {
+                (event.out_time / total * 100.0).clamp(0.0, 100.0)
+            };
+            event.stage_percent = Some(percent);
+            event.percent = Some(percent);
+            if let Some(speed) = event.speed {
+                let remaining = (total - event.out_time).max(0.0) / speed;
+                event.eta = Some(Duration::from_secs_f64(remaining));
+            }
+        }
+        if done {
+            event.eta = Some(Duration::ZERO);
+        }
+        event
+    }
+}
+
+/// Maps the passes of a multi-pass job onto one overall percentage.
+///
+/// Every pass is registered with a weight (roughly its share of the work, e.g.
+/// proportional to the clip duration it processes) when it starts; the overall
+/// percent is the weighted sum of finished passes plus the running pass's
+/// fraction, relative to the job's `total_weight`.
+pub(crate) struct JobProgress<'a> {
+    sink: &'a (dyn Fn(&ProgressEvent) + Sync),
+    total_weight: f64,
+    done_weight: f64,
+}
+
+impl<'a> JobProgress<'a> {
+    pub(crate) fn new(sink: &'a (dyn Fn(&ProgressEvent) + Sync), total_weight: f64) -> Self {
+        Self {
+            sink,
+            total_weight: total_weight.max(f64::MIN_POSITIVE),
+            done_weight: 0.0,
+        }
+    }
+
+    /// Start the next pass. Its events are reported through the returned
+    /// [`Stage`]; the pass's weight counts as done for the following stage.
+    pub(crate) fn stage(&mut self, name: impl Into<String>, weight: f64) -> Stage<'a> {
+        let stage = Stage {
+            sink: self.sink,
+            name: name.into(),
+            base: self.done_weight,
+            weight,
+            total_weight: self.total_weight,
+        };
+        self.done_weight += weight;
+        stage
+    }
+}
+
+/// One pass of a [`JobProgress`].
+pub(crate) struct Stage<'a> {
+    sink: &'a (dyn Fn(&ProgressEvent) + Sync),
+    name: String,
+    base: f64,
+    weight: f64,
+    total_weight: f64,
+}
+
+impl Stage<'_> {
+    /// Forward a pass-level event, labelled with this stage and carrying the
+    /// job-level percent.
+    pub(crate) fn report(&self, event: &ProgressEvent) {
+        let fraction = event.stage_percent.unwrap_or(0.0) / 100.0;
+        let overall = (self.base + self.weight * fraction) / self.total_weight * 100.0;
+        let mut event = event.clone();
+        event.stage = self.name.clone();
+        event.percent = Some(overall.clamp(0.0, 100.0));
+        (self.sink)(&event);
+    }
+
+    /// Report this pass as complete (for passes that emit no progress of their
+    /// own, like a stream-copy concat).
+    pub(crate) fn finish(&self) {
+        self.report(&ProgressEvent {
+            stage_percent: Some(100.0),
+            done: true,
+            ..ProgressEvent::default()
+        });
+    }
+}
+
+/// Progress of an async job as a [`Stream`](tokio_stream::Stream) of
+/// [`ProgressEvent`]s. The stream ends once the job's future completes.
+#[cfg(feature = "async")]
+pub struct ProgressStream {
+    events: tokio_stream::wrappers::UnboundedReceiverStream<ProgressEvent>,
+}
+
+#[cfg(feature = "async")]
+impl ProgressStream {
+    /// A sink for the job to report through, and the stream it feeds. The
+    /// stream ends when the sink is dropped.
+    pub(crate) fn channel() -> (impl Fn(&ProgressEvent) + Send + Sync + 'static, Self) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let sink = move |event: &ProgressEvent| {
+            // A dropped stream just means nobody is watching.
+            let _ = tx.send(event.clone());
+        };
+        let stream = Self {
+            events: tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+        };
+        (sink, stream)
+    }
+}
+
+#[cfg(feature = "async")]
+impl tokio_stream::Stream for ProgressStream {
+    type Item = ProgressEvent;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<ProgressEvent>> {
+        std::pin::Pin::new(&mut self.events).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const BLOCK: &str = "frame=120\nfps=59.94\nstream_0_0_q=28.0\nbitrate=1234.5kbits/s\n\
+                         total_size=123456\nout_time_us=2000000\nout_time_ms=2000000\n\
+                         out_time=00:00:02.000000\ndup_frames=0\ndrop_frames=0\nspeed=2.00x\n\
+                         progress=continue\n";
+
+    #[test]
+    fn parser_emits_one_event_per_block() {
+        let mut parser = ProgressParser::new(Some(10.0));
+        let events: Vec<ProgressEvent> = BLOCK.lines().filter_map(|l| parser.feed(l)).collect();
+        assert_eq!(events.len(), 1);
+        let e = &events[0];
+        assert_eq!(e.frame, 120);
+        assert_eq!(e.fps, 59.94);
+        assert_eq!(e.bitrate_kbps, Some(1234.5));
+        assert_eq!(e.out_time, 2.0);
+        assert_eq!(e.speed, Some(2.0));
+        assert_eq!(e.percent, Some(20.0));
+        // 8 s of media left at 2x real time.
+        assert_eq!(e.eta, Some(Duration::from_secs(4)));
+        assert!(!e.done);
+    }
+
+    #[test]
+    fn parser_tolerates_na_values_and_unknown_duration() {
+        let mut parser = ProgressParser::new(None);
+        let block =
+            "frame=0\nfps=0.00\nbitrate=N/A\nout_time_us=N/A\nspeed=N/A\nprogress=continue\n";
+        let e = block
+            .lines()
+            .find_map(|l| parser.feed(l))
+            .unwrap_or_default();
+        assert_eq!((e.frame, e.out_time), (0, 0.0));
+        assert_eq!((e.bitrate_kbps, e.speed, e.percent), (None, None, None));
+    }
+
+    #[test]
+    fn end_block_reports_done_at_100_percent() {
+        let mut parser = ProgressParser::new(None);
+        parser.set_total_duration(30.0);
+        let e = "out_time_us=29960000\nprogress=end\n"
+            .lines()
+            .find_map(|l| parser.feed(l))
+            .unwrap_or_default();
+        assert!(e.done);
+        assert_eq!(e.percent, Some(100.0));
+    }
+
+    #[test]
+    fn job_progress_weights_passes_into_one_percentage() {
+        let seen = Mutex::new(Vec::new());
+        let sink = |e: &ProgressEvent| {
+            if let Ok(mut seen) = seen.lock() {
+                seen.push((e.stage.clone(), e.percent));
+            }
+        };
+        let mut job = JobProgress::new(&sink, 4.0);
+        let half = ProgressEvent {
+            stage_percent: Some(50.0),
+            ..ProgressEvent::default()
+        };
+        job.stage("grade 1/2", 1.0).report(&half);
+        job.stage("transform 1/2", 3.0).report(&half);
+        let seen = seen.into_inner().unwrap_or_default();
+        assert_eq!(
+            seen,
+            vec![
+                ("grade 1/2".to_string(), Some(12.5)),
+                ("transform 1/2".to_string(), Some(62.5)),
+            ]
+        );
+    }
+}
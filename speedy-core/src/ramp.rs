@@ -0,0 +1,288 @@
+//! Speed ramps: a speed that changes over the clip.
+//!
+//! A [`SpeedRamp`] is a list of `time:speed` keyframes (e.g.
+//! `0:1,5:8,12:8,14:1`: real time, up to 8× over five seconds, hold, then back
+//! down), with times in seconds of the source timeline (the trimmed, stitched
+//! clips before any speed change). Between keyframes the speed is interpolated
+//! as the [`Easing`] says; before the first and after the last it holds.
+//!
+//! ffmpeg has no variable-speed filter, so a ramp is rendered as a run of
+//! short constant-speed [`RampSegment`]s: one `setpts` expression retimes the
+//! video across all of them, and the audio is cut at the same points and
+//! retimed segment by segment with `atempo`.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Result, SpeedyError};
+
+/// A speed curve given by keyframes, in increasing time order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SpeedRamp {
+    keyframes: Vec<Keyframe>,
+}
+
+/// The speed at one point of the source timeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// Seconds into the source timeline.
+    pub time: f64,
+    /// Speed multiplier from here on (until the next keyframe takes over).
+    pub speed: f64,
+}
+
+/// How the speed moves from one keyframe to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    /// At a constant rate.
+    #[default]
+    Linear,
+    /// Slowly away from each keyframe and slowly into the next (smoothstep),
+    /// so a ramp starts and lands without a visible kink.
+    Ease,
+}
+
+/// A span of a clip played at one constant speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RampSegment {
+    /// Seconds into the clip where the span begins.
+    pub start: f64,
+    /// Seconds into the clip where the span ends.
+    pub end: f64,
+    pub speed: f64,
+}
+
+/// Longest span a changing speed is held constant for. Short enough that the
+/// steps cannot be seen in the video or heard in the audio.
+const RAMP_STEP: f64 = 0.25;
+
+impl SpeedRamp {
+    /// A ramp through `keyframes`, which must be at least two, at strictly
+    /// increasing non-negative times, with positive finite speeds.
+    pub fn new(keyframes: Vec<Keyframe>) -> Result<Self> {
+        if keyframes.len() < 2 {
+            return Err(SpeedyError::invalid(
+                "speed ramp",
+                "needs at least two time:speed keyframes; use --speed for a constant speed",
+            ));
+        }
+        for (i, key) in keyframes.iter().enumerate() {
+            if !key.time.is_finite() || key.time < 0.0 {
+                return Err(SpeedyError::invalid(
+                    "speed ramp",
+                    format!(
+                        "keyframe time {time} must be a time in the source",
+                        time = key.time
+                    ),
+                ));
+            }
+            if !key.speed.is_finite() || key.speed <= 0.0 {
+                return Err(SpeedyError::invalid(
+                    "speed ramp",
+                    format!(
+                        "speed {speed} at {time}s must be a positive, finite number",
+                        speed = key.speed,
+                        time = key.time
+                    ),
+                ));
+            }
+            if i > 0 && key.time <= keyframes[i - 1].time {
+                return Err(SpeedyError::invalid(
+                    "speed ramp",
+                    format!(
+                        "keyframe at {time}s must come after the one at {previous}s",
+                        time = key.time,
+                        previous = keyframes[i - 1].time
+                    ),
+                ));
+            }
+        }
+        Ok(Self { keyframes })
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// The speed at `time` seconds into the source timeline.
+    pub fn speed_at(&self, time: f64, easing: Easing) -> f64 {
+        let (first, last) = (self.keyframes[0], self.keyframes[self.keyframes.len() - 1]);
+        if time <= first.time {
+            return first.speed;
+        }
+        if time >= last.time {
+            return last.speed;
+        }
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = (self.keyframes[next - 1], self.keyframes[next]);
+        let x = (time - a.time) / (b.time - a.time);
+        let x = match easing {
+            Easing::Linear => x,
+            Easing::Ease => x * x * (3.0 - 2.0 * x),
+        };
+        a.speed + (b.speed - a.speed) * x
+    }
+
+    /// The constant-speed spans covering a clip that starts `offset` seconds
+    /// into the source timeline and lasts `duration` seconds, with times
+    /// relative to the clip. A hold is one span; a changing speed is split
+    /// into steps of at most [`RAMP_STEP`], each at the speed at its middle.
+    pub fn segments(&self, offset: f64, duration: f64, easing: Easing) -> Vec<RampSegment> {
+        let end = offset + duration;
+        let mut cuts = vec![offset];
+        cuts.extend(
+            self.keyframes
+                .iter()
+                .map(|k| k.time)
+                .filter(|&t| t > offset && t < end),
+        );
+        cuts.push(end);
+
+        let mut segments: Vec<RampSegment> = Vec::new();
+        for span in cuts.windows(2) {
+            let (from, to) = (span[0], span[1]);
+            let held = self.speed_at(from, easing) == self.speed_at(to, easing);
+            let steps = if held {
+                1
+            } else {
+                ((to - from) / RAMP_STEP).ceil().max(1.0) as usize
+            };
+            let step = (to - from) / steps as f64;
+            for n in 0..steps {
+                let start = from + step * n as f64;
+                let speed = self.speed_at(start + step / 2.0, easing);
+                match segments.last_mut() {
+                    // Consecutive spans at one speed (e.g. across a keyframe
+                    // that does not change it) are merged.
+                    Some(last) if last.speed == speed => last.end = start + step - offset,
+                    _ => segments.push(RampSegment {
+                        start: start - offset,
+                        end: start + step - offset,
+                        speed,
+                    }),
+                }
+            }
+        }
+        segments
+    }
+
+    /// How long a clip starting `offset` seconds into the source timeline and
+    /// lasting `duration` seconds plays for once ramped.
+    pub fn output_duration(&self, offset: f64, duration: f64, easing: Easing) -> f64 {
+        self.segments(offset, duration, easing)
+            .iter()
+            .map(|s| (s.end - s.start) / s.speed)
+            .sum()
+    }
+}
+
+impl FromStr for SpeedRamp {
+    type Err = SpeedyError;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let keyframes = spec
+            .split(',')
+            .map(|key| {
+                let (time, speed) = key
+                    .split_once(':')
+                    .and_then(|(t, s)| Some((t.trim().parse().ok()?, s.trim().parse().ok()?)))
+                    .ok_or_else(|| {
+                        SpeedyError::invalid(
+                            "speed ramp",
+                            format!(
+                                "{key:?} in {spec:?}; expected time:speed keyframes, e.g. \"0:1,5:8,12:8,14:1\""
+                            ),
+                        )
+                    })?;
+                Ok(Keyframe { time, speed })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        SpeedRamp::new(keyframes)
+    }
+}
+
+impl fmt::Display for SpeedRamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.keyframes.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{time}:{speed}", time = key.time, speed = key.speed)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for SpeedRamp {
+    type Error = SpeedyError;
+
+    fn try_from(spec: String) -> Result<Self> {
+        spec.parse()
+    }
+}
+
+impl From<SpeedRamp> for String {
+    fn from(ramp: SpeedRamp) -> Self {
+        ramp.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_round_trip_and_reject_bad_keyframes() -> anyhow::Result<()> {
+        let ramp: SpeedRamp = "0:1, 5:8,12:8,14:1".parse()?;
+        assert_eq!(ramp.keyframes().len(), 4);
+        assert_eq!(ramp.to_string(), "0:1,5:8,12:8,14:1");
+        for spec in ["0:1", "0:1,5:0", "5:1,2:4", "0:1,5", "0:1,x:2", "0:1,5:-2"] {
+            assert!(spec.parse::<SpeedRamp>().is_err(), "{spec} should fail");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn speed_is_interpolated_and_held_outside_the_keyframes() -> anyhow::Result<()> {
+        let ramp: SpeedRamp = "2:1,6:9".parse()?;
+        assert_eq!(ramp.speed_at(0.0, Easing::Linear), 1.0);
+        assert_eq!(ramp.speed_at(4.0, Easing::Linear), 5.0);
+        assert_eq!(ramp.speed_at(4.0, Easing::Ease), 5.0);
+        // Easing lingers near the keyframes.
+        assert!(ramp.speed_at(3.0, Easing::Ease) < ramp.speed_at(3.0, Easing::Linear));
+        assert_eq!(ramp.speed_at(10.0, Easing::Ease), 9.0);
+        Ok(())
+    }
+
+    #[test]
+    fn segments_hold_steady_spans_and_step_through_ramps() -> anyhow::Result<()> {
+        let ramp: SpeedRamp = "0:1,1:2,3:2".parse()?;
+        let segments = ramp.segments(0.0, 5.0, Easing::Linear);
+        // Four quarter-second steps up to 2x, then one span at 2x to the end.
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[0].speed, 1.125);
+        assert_eq!(
+            segments[4],
+            RampSegment {
+                start: 1.0,
+                end: 5.0,
+                speed: 2.0
+            }
+        );
+        // A clip later on the timeline sees the ramp from its own start.
+        assert_eq!(
+            ramp.segments(2.0, 3.0, Easing::Linear),
+            vec![RampSegment {
+                start: 0.0,
+                end: 3.0,
+                speed: 2.0
+            }]
+        );
+        assert_eq!(ramp.output_duration(2.0, 3.0, Easing::Linear), 1.5);
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! How retimed footage is resampled to the output frame rate.
+//!
+//! A speed change only rescales timestamps; the stream is then resampled to
+//! the output rate. A speed-up drops frames there, and a slow-down has to
+//! invent the ones the source never had: by repeating frames (the default,
+//! which stutters), by cross-fading neighbours, or by motion-compensated
+//! interpolation, as the [`Interpolation`] says.
+//!
+//! Dropping frames on a big speed-up strobes: every output frame is a sharp
+//! instant with a jump to the next. A motion blur averages the frames around
+//! each kept one instead, as a camera shutter open for part of the output
+//! frame would (see [`blur_frames`]).
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// How a slow-down fills in the frames between source frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Interpolation {
+    /// Repeat the nearest source frame.
+    #[default]
+    None,
+    /// Cross-fade the two nearest source frames (`framerate`): smooth, with
+    /// some ghosting on fast motion.
+    Blend,
+    /// Motion-compensated interpolation (`minterpolate`, overlapped block
+    /// motion compensation): true in-between frames, at a much slower encode
+    /// and with occasional warping artifacts.
+    Mci,
+}
+
+/// Shutter angles a motion blur can emulate, in degrees: 360 keeps the
+/// shutter open for the whole output frame; film's usual look is 180.
+pub const SHUTTER_ANGLES: RangeInclusive<f64> = 0.0..=360.0;
+
+/// Most frames `tmix` averages at once.
+const MAX_BLUR_FRAMES: f64 = 1024.0;
+
+/// How many consecutive source frames to average into each output frame of a
+/// `speed`× speed-up resampled from `source_fps` to `output_fps`, so that the
+/// blur spans `shutter_angle` degrees of the output frame: a 10× speed-up at
+/// an unchanged rate with a 180° shutter averages 5 frames. At least 1 (no
+/// blur).
+pub fn blur_frames(speed: f64, source_fps: f64, output_fps: f64, shutter_angle: f64) -> u32 {
+    let per_output_frame = speed * source_fps / output_fps;
+    let frames = per_output_frame * shutter_angle / 360.0;
+    if frames.is_finite() {
+        frames.round().clamp(1.0, MAX_BLUR_FRAMES) as u32
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blur_window_spans_the_shutter_angle_of_each_output_frame() {
+        assert_eq!(blur_frames(10.0, 30.0, 30.0, 180.0), 5);
+        assert_eq!(blur_frames(10.0, 30.0, 30.0, 360.0), 10);
+        // 60 fps footage resampled to 30 fps drops twice as many frames.
+        assert_eq!(blur_frames(10.0, 60.0, 30.0, 180.0), 10);
+        // Nothing to blend on a mild speed-up or a closed shutter.
+        assert_eq!(blur_frames(1.5, 30.0, 30.0, 180.0), 1);
+        assert_eq!(blur_frames(10.0, 30.0, 30.0, 0.0), 1);
+        assert_eq!(blur_frames(10.0, 30.0, 0.0, 180.0), 1);
+    }
+}
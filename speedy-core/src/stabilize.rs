@@ -17,13 +17,19 @@
 //! Per-segment stabilization (stabilizing each clip independently before
 //! concatenation) lives in [`crate::video_processor`]; it relies on these
 //! primitives so that smoothing never crosses a hard cut between clips.
+//!
+//! The vidstab passes are video-only. Audio is graded on the side into PCM
+//! intermediates fitted to each stabilized segment's exact duration, joined
+//! with [`concat`], and put back with [`mux_audio`].
 
-use anyhow::{Result, bail};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use crate::FFmpegCommand;
-use crate::ffmpeg_wrapper::is_mp4_family;
+use crate::cancel::CancellationToken;
+use crate::error::{Result, SpeedyError};
+use crate::ffmpeg_wrapper::{STITCH_AUDIO_RATE, is_mp4_family};
+use crate::progress::ProgressEvent;
 
 /// Tunables for the two `vidstab` passes.
 #[derive(Debug, Clone, Copy)]
@@ -56,6 +62,26 @@ pub struct EncodeOpts<'a> {
     pub threads: Option<usize>,
 }
 
+/// How a stabilization pass is driven: how often it is retried, where the
+/// progress of each attempt goes, and what can cancel it.
+#[derive(Clone, Copy)]
+pub struct PassControl<'a> {
+    pub attempts: u32,
+    pub on_progress: &'a (dyn Fn(&ProgressEvent) + Sync),
+    /// Tripping it kills the running attempt and stops retrying.
+    pub cancel: Option<&'a CancellationToken>,
+}
+
+impl PassControl<'_> {
+    /// Apply the cancellation token, if any, to one attempt's command.
+    pub(crate) fn attach(&self, cmd: FFmpegCommand) -> FFmpegCommand {
+        match self.cancel {
+            Some(token) => cmd.cancellation(token.clone()),
+            None => cmd,
+        }
+    }
+}
+
 /// The trailing filename of a path (for referencing a `.trf` by name from the
 /// ffmpeg working directory), falling back to the full path string.
 fn file_name_str(path: &Path) -> String {
@@ -80,29 +106,74 @@ fn work_dir(path: &Path) -> Option<&Path> {
 /// container-agnostic and fast — unlike `nb_frames`, which Matroska does not
 /// populate (our stabilization intermediates are `.mkv`).
 pub fn frame_count(path: &Path) -> Option<u64> {
-    let output = Command::new("ffprobe")
-        .args([
-            "-v",
-            "error",
-            "-select_streams",
-            "v:0",
-            "-count_packets",
-            "-show_entries",
-            "stream=nb_read_packets",
-            "-of",
-            "csv=p=0",
-        ])
-        .arg(path)
+    let output = frame_count_command(path).output().ok()?;
+    parse_frame_count(&output.stdout)
+}
+
+/// [`frame_count`] without blocking the async runtime.
+#[cfg(feature = "async")]
+pub async fn frame_count_async(path: &Path) -> Option<u64> {
+    let output = tokio::process::Command::from(frame_count_command(path))
         .output()
+        .await
         .ok()?;
-    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    parse_frame_count(&output.stdout)
+}
+
+/// The ffprobe invocation behind [`frame_count`].
+pub(crate) fn frame_count_command(path: &Path) -> Command {
+    let mut cmd = Command::new("ffprobe");
+    cmd.args([
+        "-v",
+        "error",
+        "-select_streams",
+        "v:0",
+        "-count_packets",
+        "-show_entries",
+        "stream=nb_read_packets",
+        "-of",
+        "csv=p=0",
+    ])
+    .arg(path)
+    .stdin(Stdio::null());
+    cmd
+}
+
+fn parse_frame_count(stdout: &[u8]) -> Option<u64> {
+    String::from_utf8_lossy(stdout).trim().parse().ok()
+}
+
+/// How a retried pass's output is validated before it is accepted.
+#[derive(Debug, Clone)]
+pub(crate) enum Check {
+    /// The file must be written non-empty. A stale copy is removed before each
+    /// attempt so a previous run's file cannot pass for this one's.
+    NonEmpty(PathBuf),
+    /// The output must have as many video frames as the input.
+    SameFrameCount { input: PathBuf, output: PathBuf },
+}
+
+/// One vidstab pass: its command, how its output is validated, and what to
+/// name in the error when every attempt fails.
+#[derive(Debug, Clone)]
+pub(crate) struct CheckedPass {
+    pub(crate) command: FFmpegCommand,
+    pub(crate) check: Check,
+    pub(crate) pass: &'static str,
+    pub(crate) path: PathBuf,
 }
 
 /// Pass 1: detect camera motion into a transforms file (`.trf`).
 ///
 /// Detection runs on a brightness-normalized copy so exposure changes do not
-/// register as motion. Retried until the `.trf` is written non-empty.
-pub fn detect(input: &Path, trf: &Path, params: &VidstabParams, attempts: u32) -> Result<()> {
+/// register as motion. Retried up to `ctl.attempts` times until the `.trf` is
+/// written non-empty.
+pub fn detect(input: &Path, trf: &Path, params: &VidstabParams, ctl: &PassControl) -> Result<()> {
+    run_checked(&detect_pass(input, trf, params), ctl)
+}
+
+/// The `vidstabdetect` pass behind [`detect`].
+pub(crate) fn detect_pass(input: &Path, trf: &Path, params: &VidstabParams) -> CheckedPass {
     // Reference the .trf by filename and run from its directory, so an absolute
     // path with colons/backslashes (e.g. a Windows temp dir) never reaches the
     // filtergraph parser (which mis-parses such paths even when escaped/quoted).
@@ -115,53 +186,62 @@ pub fn detect(input: &Path, trf: &Path, params: &VidstabParams, attempts: u32) -
     // Absolutize input now: ffmpeg runs from the .trf directory below, so a
     // relative input would otherwise resolve against that, not the caller's cwd.
     let input_abs = std::path::absolute(input).unwrap_or_else(|_| input.to_path_buf());
-    for attempt in 1..=attempts {
-        if let Err(e) = std::fs::remove_file(trf)
-            && trf.exists()
-        {
-            log::debug!("could not remove stale trf {trf}: {e}", trf = trf.display());
-        }
-        let mut command = Command::new("ffmpeg");
-        if let Some(dir) = work_dir(trf) {
-            command.current_dir(dir);
-        }
-        let status = command
-            .args(["-y", "-hide_banner", "-loglevel", "error"])
-            .arg("-i")
-            .arg(&input_abs)
-            .args(["-vf", &vf, "-f", "null", "-"])
-            .status();
-        let wrote = trf.metadata().map(|m| m.len() > 0).unwrap_or(false);
-        if matches!(&status, Ok(s) if s.success()) && wrote {
-            return Ok(());
-        }
-        log::warn!(
-            "vidstabdetect attempt {attempt}/{attempts} failed for {path}; retrying",
-            path = input.display()
-        );
+    // Analysis only: decode, filter and discard via the null muxer.
+    let mut command = FFmpegCommand::new(&input_abs, "-")
+        .video_filter(&vf)
+        .video_only()
+        .custom_args(vec!["-f".to_string(), "null".to_string()])
+        .overwrite();
+    if let Some(dir) = work_dir(trf) {
+        command = command.current_dir(dir);
     }
-    bail!(
-        "vidstabdetect failed for {path} after {attempts} attempts",
-        path = input.display()
-    );
+    CheckedPass {
+        command,
+        check: Check::NonEmpty(trf.to_path_buf()),
+        pass: "vidstabdetect",
+        path: input.to_path_buf(),
+    }
+}
+
+/// Whether a failed pass is worth retrying. Intermittent crashes are; a missing
+/// ffmpeg or encoder will fail identically on every attempt, and a cancelled
+/// job must stop.
+fn is_retryable(err: &SpeedyError) -> bool {
+    !matches!(
+        err,
+        SpeedyError::FfmpegNotFound { .. }
+            | SpeedyError::EncoderUnavailable { .. }
+            | SpeedyError::Cancelled
+    )
 }
 
 /// Pass 2: warp the frames with the detected transforms and encode.
 ///
 /// Retried until the output's frame count matches the input — guarding against
-/// intermittent filter/encoder crashes that leave a truncated file.
+/// intermittent filter/encoder crashes that leave a truncated file. Up to
+/// `ctl.attempts` attempts are made.
 pub fn transform(
     input: &Path,
     output: &Path,
     trf: &Path,
     enc: &EncodeOpts,
     params: &VidstabParams,
-    attempts: u32,
+    ctl: &PassControl,
 ) -> Result<()> {
+    run_checked(&transform_pass(input, output, trf, enc, params), ctl)
+}
+
+/// The `vidstabtransform` pass behind [`transform`].
+pub(crate) fn transform_pass(
+    input: &Path,
+    output: &Path,
+    trf: &Path,
+    enc: &EncodeOpts,
+    params: &VidstabParams,
+) -> CheckedPass {
     // Absolutize input/output now: ffmpeg runs from the .trf directory below,
     // so relative paths would otherwise resolve against that, not the cwd.
     let input_abs = std::path::absolute(input).unwrap_or_else(|_| input.to_path_buf());
-    let want = frame_count(&input_abs);
     // optzoom=1 crops just enough to hide the stabilization borders; the unsharp
     // counters the softening introduced by the warp interpolation. The trailing
     // format is added by FFmpegCommand for encoder compatibility. The .trf is
@@ -174,36 +254,145 @@ pub fn transform(
         smoothing = params.smoothing,
     );
     let output_abs = std::path::absolute(output).unwrap_or_else(|_| output.to_path_buf());
-    for attempt in 1..=attempts {
-        let mut cmd = FFmpegCommand::new(&input_abs, &output_abs)
-            .video_filter(&vf)
-            .video_codec(enc.codec)
-            .quality(enc.quality)
-            .video_only()
-            .overwrite();
-        if let Some(dir) = work_dir(trf) {
-            cmd = cmd.current_dir(dir);
+    let mut command = FFmpegCommand::new(&input_abs, &output_abs)
+        .video_filter(&vf)
+        .video_codec(enc.codec)
+        .quality(enc.quality)
+        .video_only()
+        .overwrite();
+    if let Some(dir) = work_dir(trf) {
+        command = command.current_dir(dir);
+    }
+    if let Some(bitrate) = enc.bitrate {
+        command = command.bitrate(bitrate);
+    }
+    if let Some(threads) = enc.threads {
+        command = command.threads(threads);
+    }
+    CheckedPass {
+        command,
+        check: Check::SameFrameCount {
+            input: input_abs,
+            output: output_abs,
+        },
+        pass: "vidstabtransform",
+        path: input.to_path_buf(),
+    }
+}
+
+/// Run a pass, retrying until its output validates (or the error is one a
+/// retry cannot fix).
+pub(crate) fn run_checked(pass: &CheckedPass, ctl: &PassControl) -> Result<()> {
+    let want = match &pass.check {
+        Check::SameFrameCount { input, .. } => frame_count(input),
+        Check::NonEmpty(_) => None,
+    };
+    for attempt in 1..=ctl.attempts {
+        if let Check::NonEmpty(path) = &pass.check {
+            remove_stale(path);
         }
-        if let Some(bitrate) = enc.bitrate {
-            cmd = cmd.bitrate(bitrate);
+        let ran = ctl.attach(pass.command.clone()).execute(ctl.on_progress);
+        let got = match &pass.check {
+            Check::SameFrameCount { output, .. } => frame_count(output),
+            Check::NonEmpty(_) => None,
+        };
+        if pass_result(pass, ran, want, got, attempt, ctl.attempts)? {
+            return Ok(());
         }
-        if let Some(threads) = enc.threads {
-            cmd = cmd.threads(threads);
+    }
+    Err(retries_exhausted(pass, ctl.attempts))
+}
+
+/// [`run_checked`] on the async runtime.
+#[cfg(feature = "async")]
+pub(crate) async fn run_checked_async(pass: &CheckedPass, ctl: &PassControl<'_>) -> Result<()> {
+    let want = match &pass.check {
+        Check::SameFrameCount { input, .. } => frame_count_async(input).await,
+        Check::NonEmpty(_) => None,
+    };
+    for attempt in 1..=ctl.attempts {
+        if let Check::NonEmpty(path) = &pass.check {
+            remove_stale(path);
         }
-        let ran = cmd.execute(|_, _| {});
-        let got = frame_count(&output_abs);
-        if ran.is_ok() && want.is_some() && got == want {
+        let ran = ctl
+            .attach(pass.command.clone())
+            .run_async(ctl.on_progress)
+            .await;
+        let got = match &pass.check {
+            Check::SameFrameCount { output, .. } => frame_count_async(output).await,
+            Check::NonEmpty(_) => None,
+        };
+        if pass_result(pass, ran, want, got, attempt, ctl.attempts)? {
             return Ok(());
         }
-        log::warn!(
-            "vidstabtransform attempt {attempt}/{attempts} for {path}: expected {want:?} frames, got {got:?}; retrying",
-            path = input.display()
-        );
     }
-    bail!(
-        "vidstabtransform failed for {path} after {attempts} attempts",
-        path = input.display()
-    );
+    Err(retries_exhausted(pass, ctl.attempts))
+}
+
+fn remove_stale(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path)
+        && path.exists()
+    {
+        log::debug!("could not remove stale {path}: {e}", path = path.display());
+    }
+}
+
+/// Judge one attempt: `Ok(true)` if it validated, `Ok(false)` to retry, or the
+/// error when retrying cannot help. `want`/`got` are the input/output frame
+/// counts for [`Check::SameFrameCount`].
+fn pass_result(
+    pass: &CheckedPass,
+    ran: Result<()>,
+    want: Option<u64>,
+    got: Option<u64>,
+    attempt: u32,
+    attempts: u32,
+) -> Result<bool> {
+    let name = pass.pass;
+    let ran_ok = match ran {
+        Ok(()) => true,
+        Err(e) if !is_retryable(&e) => return Err(e),
+        Err(e) => {
+            log::debug!("{name} attempt {attempt} error: {e}");
+            false
+        }
+    };
+    let path = pass.path.display();
+    match &pass.check {
+        Check::NonEmpty(file) => {
+            let wrote = file.metadata().map(|m| m.len() > 0).unwrap_or(false);
+            if ran_ok && wrote {
+                return Ok(true);
+            }
+            log::warn!("{name} attempt {attempt}/{attempts} failed for {path}; retrying");
+        }
+        Check::SameFrameCount { .. } => {
+            if ran_ok && want.is_some() && got == want {
+                return Ok(true);
+            }
+            log::warn!(
+                "{name} attempt {attempt}/{attempts} for {path}: expected {want:?} frames, got {got:?}; retrying"
+            );
+        }
+    }
+    Ok(false)
+}
+
+fn retries_exhausted(pass: &CheckedPass, attempts: u32) -> SpeedyError {
+    SpeedyError::StabilizationFailed {
+        pass: pass.pass,
+        path: pass.path.clone(),
+        attempts,
+    }
+}
+
+/// A stream-copy join: the concat-demuxer list to write, and the ffmpeg
+/// command that reads it.
+#[derive(Debug, Clone)]
+pub(crate) struct ConcatPass {
+    pub(crate) list: PathBuf,
+    pub(crate) body: String,
+    pub(crate) command: FFmpegCommand,
 }
 
 /// Concatenate already-encoded segments (same codec/params) without re-encoding,
@@ -215,21 +404,34 @@ pub fn transform(
 /// in that same per-run temp dir, so concurrent runs don't share it. The output
 /// is absolutized so the working-directory change can't redirect it.
 pub fn concat(segments: &[PathBuf], output: &Path) -> Result<()> {
+    let ctl = PassControl {
+        attempts: 1,
+        on_progress: &|_| {},
+        cancel: None,
+    };
+    run_concat(&concat_pass(segments, output)?, &ctl)
+}
+
+/// The list and command behind [`concat`]. Errors if `segments` is empty or
+/// spread over several directories.
+pub(crate) fn concat_pass(segments: &[PathBuf], output: &Path) -> Result<ConcatPass> {
     if segments.is_empty() {
-        bail!("no segments to concat");
+        return Err(SpeedyError::invalid("segments", "no segments to concat"));
     }
     // We reference segments by filename and run from one directory, so they must
     // all live in it; otherwise a basename could resolve to the wrong file.
     let parent0 = segments[0].parent();
     if segments.iter().any(|s| s.parent() != parent0) {
-        bail!("all concat segments must be in the same directory");
+        return Err(SpeedyError::invalid(
+            "segments",
+            "all concat segments must be in the same directory",
+        ));
     }
     // A bare filename has parent Some(""); current_dir("") fails with ENOENT, so
     // fall back to "." (the current directory) like work_dir() does.
     let dir = parent0
         .filter(|p| !p.as_os_str().is_empty())
         .unwrap_or_else(|| Path::new("."));
-    let list = dir.join("concat-list.txt");
     let mut body = String::new();
     for seg in segments {
         let name = seg
@@ -238,39 +440,156 @@ pub fn concat(segments: &[PathBuf], output: &Path) -> Result<()> {
             .unwrap_or_else(|| seg.to_string_lossy().into_owned());
         body.push_str(&format!("file '{name}'\n"));
     }
-    std::fs::write(&list, &body)?;
 
+    Ok(ConcatPass {
+        list: dir.join(CONCAT_LIST),
+        body,
+        command: copy_join_command(Path::new(CONCAT_LIST), output).current_dir(dir),
+    })
+}
+
+/// A stream-copy join of `files` wherever they live. Unlike
+/// [`concat_pass`], the list names every file by its absolute path and is
+/// written to `list_dir` rather than next to the files, whose directories may
+/// be read-only (e.g. a camera card). Errors for a path the list cannot hold:
+/// not valid UTF-8, or containing a line break.
+pub(crate) fn concat_files_pass(
+    files: &[PathBuf],
+    list_dir: &Path,
+    output: &Path,
+) -> Result<ConcatPass> {
+    if files.is_empty() {
+        return Err(SpeedyError::invalid("segments", "no segments to concat"));
+    }
+    let mut body = String::new();
+    for file in files {
+        let path = std::path::absolute(file).unwrap_or_else(|_| file.clone());
+        let Some(name) = path.to_str().filter(|p| !p.contains(['\n', '\r'])) else {
+            return Err(SpeedyError::invalid(
+                "segments",
+                format!("{path:?} cannot be named in a concat list"),
+            ));
+        };
+        // In ffconcat's single quotes only a quote is special: close the
+        // string, add an escaped quote, and reopen it.
+        body.push_str(&format!(
+            "file '{name}'\n",
+            name = name.replace('\'', r"'\''")
+        ));
+    }
+    let list = list_dir.join(CONCAT_LIST);
+    Ok(ConcatPass {
+        command: copy_join_command(&list, output),
+        list,
+        body,
+    })
+}
+
+/// ffmpeg reading the concat list `list` and stream-copying every segment
+/// into `output`.
+fn copy_join_command(list: &Path, output: &Path) -> FFmpegCommand {
     let output_abs = std::path::absolute(output).unwrap_or_else(|_| output.to_path_buf());
-    let mut cmd = Command::new("ffmpeg");
-    cmd.current_dir(dir).args([
-        "-y",
-        "-hide_banner",
-        "-loglevel",
-        "error",
-        "-f",
-        "concat",
-        "-safe",
-        "0",
-        "-i",
-        "concat-list.txt",
-        "-c",
-        "copy",
-    ]);
+    let mut args = vec!["-c".to_string(), "copy".to_string()];
     // -movflags +faststart is MP4/MOV-only; skip it for other containers.
     if is_mp4_family(&output_abs) {
-        cmd.args(["-movflags", "+faststart"]);
+        args.extend(["-movflags".to_string(), "+faststart".to_string()]);
     }
-    let status = cmd.arg(&output_abs).status();
-    if let Err(e) = std::fs::remove_file(&list) {
+    FFmpegCommand::new(list, &output_abs)
+        .input_options(["-f", "concat", "-safe", "0"].map(String::from).to_vec())
+        .custom_args(args)
+        .overwrite()
+}
+
+/// File name of the concat-demuxer list, written next to the segments.
+const CONCAT_LIST: &str = "concat-list.txt";
+
+/// Write the list, run the join, and remove the list again.
+pub(crate) fn run_concat(pass: &ConcatPass, ctl: &PassControl) -> Result<()> {
+    write_concat_list(pass)?;
+    let ran = ctl.attach(pass.command.clone()).execute(ctl.on_progress);
+    remove_concat_list(pass);
+    ran
+}
+
+/// [`run_concat`] on the async runtime.
+#[cfg(feature = "async")]
+pub(crate) async fn run_concat_async(pass: &ConcatPass, ctl: &PassControl<'_>) -> Result<()> {
+    write_concat_list(pass)?;
+    let ran = ctl
+        .attach(pass.command.clone())
+        .run_async(ctl.on_progress)
+        .await;
+    remove_concat_list(pass);
+    ran
+}
+
+fn write_concat_list(pass: &ConcatPass) -> Result<()> {
+    std::fs::write(&pass.list, &pass.body).map_err(|e| {
+        SpeedyError::io(
+            format!(
+                "Failed to write concat list {list}",
+                list = pass.list.display()
+            ),
+            e,
+        )
+    })
+}
+
+fn remove_concat_list(pass: &ConcatPass) {
+    if let Err(e) = std::fs::remove_file(&pass.list) {
         log::debug!(
             "could not remove concat list {list}: {e}",
-            list = list.display()
+            list = pass.list.display()
         );
     }
-    match status {
-        Ok(s) if s.success() => Ok(()),
-        other => bail!("concat failed: {other:?}"),
+}
+
+/// Write `seconds` of silent PCM audio (in the same format as the graded audio
+/// intermediates), standing in for a clip without audio so the joined track
+/// stays aligned with the stabilized video.
+pub fn silence(output: &Path, seconds: f64) -> Result<()> {
+    silence_command(output, seconds).execute(|_| {})
+}
+
+/// The ffmpeg command behind [`silence`].
+pub(crate) fn silence_command(output: &Path, seconds: f64) -> FFmpegCommand {
+    FFmpegCommand::new(format!("anullsrc=r={STITCH_AUDIO_RATE}:cl=stereo"), output)
+        .input_options(vec!["-f".to_string(), "lavfi".to_string()])
+        .custom_args(vec!["-t".to_string(), format!("{seconds:.6}")])
+        .audio_codec("pcm_s16le")
+        .total_duration(seconds)
+        .overwrite()
+}
+
+/// Mux a graded audio track back into the stabilized (video-only) output.
+///
+/// The video is stream-copied so the stabilized encode is untouched; the PCM
+/// audio is encoded with the container's default audio codec, as on the
+/// single-command path. `-shortest` guards against the audio running past the
+/// final frame.
+pub fn mux_audio(video: &Path, audio: &Path, output: &Path) -> Result<()> {
+    mux_command(video, audio, output).execute(|_| {})
+}
+
+/// The ffmpeg command behind [`mux_audio`].
+pub(crate) fn mux_command(video: &Path, audio: &Path, output: &Path) -> FFmpegCommand {
+    let mut args: Vec<String> = [
+        "-map",
+        "0:v:0",
+        "-map",
+        "1:a:0",
+        "-c:v",
+        "copy",
+        "-shortest",
+    ]
+    .map(String::from)
+    .to_vec();
+    if is_mp4_family(output) {
+        args.extend(["-movflags".to_string(), "+faststart".to_string()]);
     }
+    FFmpegCommand::new_multi(vec![video.to_path_buf(), audio.to_path_buf()], output)
+        .custom_args(args)
+        .overwrite()
 }
 
 #[cfg(test)]
@@ -301,6 +620,40 @@ mod tests {
         assert!(concat(&segs, Path::new("/tmp/out.mp4")).is_err());
     }
 
+    #[test]
+    fn concat_files_list_names_files_by_quoted_absolute_path() -> anyhow::Result<()> {
+        let files = [
+            PathBuf::from("/card/DJI_0001.MP4"),
+            PathBuf::from("/home/o'neil/b.mp4"),
+        ];
+        let pass = concat_files_pass(&files, Path::new("/tmp/run"), Path::new("/out/trip.mp4"))?;
+        assert_eq!(
+            pass.body,
+            "file '/card/DJI_0001.MP4'\nfile '/home/o'\\''neil/b.mp4'\n"
+        );
+        assert_eq!(pass.list, Path::new("/tmp/run").join(CONCAT_LIST));
+        assert_eq!(pass.command.build().get_current_dir(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_pass_fails_fast_without_retrying() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let ctl = PassControl {
+            attempts: 6,
+            on_progress: &|_| {},
+            cancel: Some(&token),
+        };
+        let r = detect(
+            Path::new("/no/such/in.mkv"),
+            Path::new("/no/such/t_0.trf"),
+            &VidstabParams::default(),
+            &ctl,
+        );
+        assert!(matches!(r, Err(SpeedyError::Cancelled)), "{r:?}");
+    }
+
     #[test]
     fn trf_is_referenced_by_bare_filename() {
         // The filter must reference the .trf by name (no directory), so an
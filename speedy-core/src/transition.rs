@@ -0,0 +1,213 @@
+//! Transitions between stitched clips.
+//!
+//! A [`Transition`] is written as `kind:seconds` (e.g. `fade:0.5`,
+//! `fadeblack:1`), or `cut` for a hard cut. `kind` is any ffmpeg `xfade`
+//! transition, plus a few friendlier aliases (`crossfade`, `dip-to-black`,
+//! `dip-to-white`, `wipe`). The audio of a transition is crossfaded over the
+//! same duration with `acrossfade`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Result, SpeedyError};
+
+/// How one stitched clip hands over to the next.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Transition {
+    /// A hard cut.
+    #[default]
+    Cut,
+    /// An `xfade` transition. The clips overlap for `duration` seconds, which
+    /// shortens the output by as much.
+    Xfade { kind: String, duration: f64 },
+}
+
+/// Duration used when a spec names only the kind (e.g. `fade`).
+const DEFAULT_DURATION: f64 = 0.5;
+
+/// Every transition ffmpeg's `xfade` filter knows.
+const XFADE_KINDS: &[&str] = &[
+    "fade",
+    "fadefast",
+    "fadeslow",
+    "fadeblack",
+    "fadewhite",
+    "fadegrays",
+    "dissolve",
+    "distance",
+    "pixelize",
+    "radial",
+    "hblur",
+    "zoomin",
+    "wipeleft",
+    "wiperight",
+    "wipeup",
+    "wipedown",
+    "wipetl",
+    "wipetr",
+    "wipebl",
+    "wipebr",
+    "slideleft",
+    "slideright",
+    "slideup",
+    "slidedown",
+    "smoothleft",
+    "smoothright",
+    "smoothup",
+    "smoothdown",
+    "coverleft",
+    "coverright",
+    "coverup",
+    "coverdown",
+    "revealleft",
+    "revealright",
+    "revealup",
+    "revealdown",
+    "circlecrop",
+    "rectcrop",
+    "circleopen",
+    "circleclose",
+    "vertopen",
+    "vertclose",
+    "horzopen",
+    "horzclose",
+    "diagtl",
+    "diagtr",
+    "diagbl",
+    "diagbr",
+    "hlslice",
+    "hrslice",
+    "vuslice",
+    "vdslice",
+    "hlwind",
+    "hrwind",
+    "vuwind",
+    "vdwind",
+    "squeezeh",
+    "squeezev",
+];
+
+impl Transition {
+    /// An `xfade` transition of the given kind (or alias) and duration.
+    pub fn xfade(kind: &str, duration: f64) -> Result<Self> {
+        let kind = match kind {
+            "crossfade" => "fade",
+            "dip-to-black" => "fadeblack",
+            "dip-to-white" => "fadewhite",
+            "wipe" => "wipeleft",
+            other => other,
+        };
+        if !XFADE_KINDS.contains(&kind) {
+            return Err(SpeedyError::invalid(
+                "transition",
+                format!(
+                    "unknown kind {kind:?}; expected cut, crossfade, dip-to-black, dip-to-white, wipe or an xfade transition"
+                ),
+            ));
+        }
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err(SpeedyError::invalid(
+                "transition",
+                format!("duration {duration}; must be a positive number of seconds"),
+            ));
+        }
+        Ok(Transition::Xfade {
+            kind: kind.to_string(),
+            duration,
+        })
+    }
+
+    /// How long the two clips overlap (zero for a cut).
+    pub fn overlap(&self) -> f64 {
+        match self {
+            Transition::Cut => 0.0,
+            Transition::Xfade { duration, .. } => *duration,
+        }
+    }
+
+    /// The same transition with its duration multiplied by `factor` (to move
+    /// it between the output and the source timeline of a speed change).
+    pub(crate) fn scaled(&self, factor: f64) -> Self {
+        match self {
+            Transition::Cut => Transition::Cut,
+            Transition::Xfade { kind, duration } => Transition::Xfade {
+                kind: kind.clone(),
+                duration: duration * factor,
+            },
+        }
+    }
+}
+
+impl FromStr for Transition {
+    type Err = SpeedyError;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if matches!(spec, "cut" | "none") {
+            return Ok(Transition::Cut);
+        }
+        let (kind, duration) = match spec.split_once(':') {
+            Some((kind, seconds)) => {
+                let duration = seconds.trim().parse().map_err(|_| {
+                    SpeedyError::invalid(
+                        "transition",
+                        format!("{spec:?}; expected kind:seconds, e.g. \"fade:0.5\""),
+                    )
+                })?;
+                (kind.trim(), duration)
+            }
+            None => (spec, DEFAULT_DURATION),
+        };
+        Transition::xfade(kind, duration)
+    }
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::Cut => f.write_str("cut"),
+            Transition::Xfade { kind, duration } => write!(f, "{kind}:{duration}"),
+        }
+    }
+}
+
+impl TryFrom<String> for Transition {
+    type Error = SpeedyError;
+
+    fn try_from(spec: String) -> Result<Self> {
+        spec.parse()
+    }
+}
+
+impl From<Transition> for String {
+    fn from(transition: Transition) -> Self {
+        transition.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_parse_with_aliases_and_default_duration() -> anyhow::Result<()> {
+        assert_eq!("cut".parse::<Transition>()?, Transition::Cut);
+        assert_eq!(
+            "dip-to-black:1".parse::<Transition>()?,
+            Transition::xfade("fadeblack", 1.0)?
+        );
+        let fade: Transition = "crossfade".parse()?;
+        assert_eq!(fade.to_string(), "fade:0.5");
+        assert_eq!(fade.to_string().parse::<Transition>()?, fade);
+        Ok(())
+    }
+
+    #[test]
+    fn bad_specs_are_rejected() {
+        for spec in ["sparkle:1", "fade:-1", "fade:x", "fade:0"] {
+            assert!(spec.parse::<Transition>().is_err(), "{spec} should fail");
+        }
+    }
+}
@@ -1,20 +1,49 @@
-use anyhow::{Context, Result};
 use indicatif::{ProgressBar, ProgressStyle};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-
+use std::time::SystemTime;
+
+use crate::canvas::{Canvas, Fill};
+use crate::chapters;
+use crate::clip::Clip;
+use crate::error::{Result, SpeedyError};
+#[cfg(feature = "async")]
+use crate::ffmpeg_wrapper::{check_ffmpeg_async, get_video_info_async};
+use crate::ffmpeg_wrapper::{fps_string_value, probe_command};
+use crate::job::{ColorBalance, JobSpec};
+use crate::lut::{self, CubeLut, LutKind};
+use crate::pipeline::{Action, FitAudio, Pipeline, Step};
+use crate::plan::PlannedCommand;
+use crate::progress::ProgressEvent;
+#[cfg(feature = "async")]
+use crate::progress::ProgressStream;
+use crate::ramp::{Easing, RampSegment, SpeedRamp};
+use crate::retime::{Interpolation, SHUTTER_ANGLES, blur_frames};
 use crate::stabilize::{self, VidstabParams};
-use crate::{ColorProfile, FFmpegCommand, check_ffmpeg, get_video_info};
+use crate::transition::Transition;
+use crate::{
+    CancellationToken, ColorProfile, FFmpegCommand, VideoInfo, check_ffmpeg, get_video_info,
+};
 
 // Type alias for color balance values (shadows RGB, midtones RGB, highlights RGB)
 type ColorBalanceValues = (f32, f32, f32, f32, f32, f32, f32, f32, f32);
 
+// Type alias for a caller-supplied progress callback
+type ProgressCallback = dyn Fn(&ProgressEvent) + Send + Sync;
+
 pub struct VideoProcessor {
-    /// One or more input clips. When more than one is given they are stitched
-    /// together (in order) into a single output via the concat filter, with each
-    /// clip normalized to a common resolution first.
-    inputs: Vec<PathBuf>,
+    /// One or more input clips, each whole or trimmed to part of its source.
+    /// When more than one is given they are stitched together (in order) into a
+    /// single output via the concat filter, with each clip normalized to a
+    /// common resolution (and audio format) first.
+    inputs: Vec<Clip>,
     output_path: PathBuf,
     speed_multiplier: f64,
+    /// A speed that changes over the source timeline, in place of the
+    /// constant `speed_multiplier`.
+    speed_ramp: Option<SpeedRamp>,
+    /// How the ramp's speed moves between keyframes.
+    ramp_easing: Easing,
     codec: String,
     bitrate: Option<u32>,
     quality: u8,
@@ -22,6 +51,9 @@ pub struct VideoProcessor {
     saturation: f32,
     profile: ColorProfile,
     lut_file: Option<PathBuf>,
+    /// How much of the LUT's result is mixed into the ungraded frame, from
+    /// 0.0 (none) to 1.0 (all of it).
+    lut_strength: f32,
     hw_accel: bool,
     threads: Option<usize>,
     stabilize: bool,
@@ -34,6 +66,18 @@ pub struct VideoProcessor {
     hue_shift: Option<f32>,
     color_balance: Option<ColorBalanceValues>,
     selective_color: Option<String>,
+    /// How a slow-down fills in frames between source frames.
+    interpolate: Interpolation,
+    /// Shutter angle (degrees) emulated by blending the frames a speed-up
+    /// drops. `None` drops them outright.
+    shutter_angle: Option<f64>,
+    /// Output length in seconds to fit the timeline into, by choosing the
+    /// speed (in place of `speed_multiplier`).
+    fit_duration: Option<f64>,
+    /// Play the timeline backwards.
+    reverse: bool,
+    /// Play the timeline forward then backwards, this many times over.
+    boomerang: Option<u32>,
     /// Target output frame rate used when the speed is changed. `None` defaults
     /// to the source frame rate, which makes a speed-up drop frames instead of
     /// inflating the frame rate.
@@ -43,6 +87,25 @@ pub struct VideoProcessor {
     /// vidstab smoothing window (frames) used when `stabilize` is set. `None`
     /// uses the tuned default.
     stabilize_smoothing: Option<u32>,
+    /// How stitched clips hand over to each other: empty for hard cuts, one
+    /// transition for every boundary, or one per boundary. Durations are on
+    /// the output timeline.
+    transitions: Vec<Transition>,
+    /// How the stitched frame size and frame rate are picked from the clips.
+    canvas: Canvas,
+    /// How a clip of another shape is fitted into the stitched frame.
+    fill: Fill,
+    /// Join compatible clips losslessly (concat demuxer, `-c copy`) when no
+    /// setting changes their content. On by default.
+    stream_copy: bool,
+    /// Treat consecutive chapter files of one recording (see
+    /// [`chapters`](crate::chapters)) as one continuous clip.
+    merge_chapters: bool,
+    /// Receives overall progress across every ffmpeg pass. `None` draws a
+    /// terminal progress bar instead.
+    progress_callback: Option<Box<ProgressCallback>>,
+    /// Stops the job (killing ffmpeg and cleaning up) once tripped.
+    cancel: Option<CancellationToken>,
 }
 
 impl VideoProcessor {
@@ -53,17 +116,26 @@ impl VideoProcessor {
     /// Create a processor that stitches multiple input clips into one output.
     /// The clips are concatenated in the order given.
     pub fn new_multi(inputs: Vec<PathBuf>, output: impl AsRef<Path>) -> Self {
+        Self::from_clips(inputs.into_iter().map(Clip::from).collect(), output)
+    }
+
+    /// Create a processor that stitches the given clips, each whole or trimmed
+    /// to part of its source, into one output, in order.
+    pub fn from_clips(inputs: Vec<Clip>, output: impl AsRef<Path>) -> Self {
         Self {
             inputs,
             output_path: output.as_ref().to_path_buf(),
             speed_multiplier: 1.0,
-            codec: "libx264".to_string(),
+            speed_ramp: None,
+            ramp_easing: Easing::default(),
+            codec: DEFAULT_CODEC.to_string(),
             bitrate: None,
-            quality: 23,
+            quality: DEFAULT_QUALITY,
             contrast: 1.0,
             saturation: 1.0,
             profile: ColorProfile::Standard,
             lut_file: None,
+            lut_strength: 1.0,
             hw_accel: false,
             threads: None,
             stabilize: false,
@@ -77,8 +149,118 @@ impl VideoProcessor {
             color_balance: None,
             selective_color: None,
             output_fps: None,
+            interpolate: Interpolation::default(),
+            shutter_angle: None,
+            fit_duration: None,
+            reverse: false,
+            boomerang: None,
             dehaze: None,
             stabilize_smoothing: None,
+            transitions: Vec::new(),
+            canvas: Canvas::default(),
+            fill: Fill::default(),
+            stream_copy: true,
+            merge_chapters: false,
+            progress_callback: None,
+            cancel: None,
+        }
+    }
+
+    /// Create a processor configured by a [`JobSpec`].
+    pub fn from_job(spec: JobSpec) -> Self {
+        let base = Self::from_clips(spec.inputs, spec.output).codec(&spec.codec);
+        Self {
+            speed_multiplier: spec.speed,
+            speed_ramp: spec.speed_ramp,
+            ramp_easing: spec.ramp_easing,
+            output_fps: spec.output_fps,
+            interpolate: spec.interpolate,
+            shutter_angle: spec.shutter_angle,
+            fit_duration: spec.fit_duration,
+            reverse: spec.reverse,
+            boomerang: spec.boomerang,
+            bitrate: spec.bitrate,
+            quality: spec.quality,
+            threads: spec.threads,
+            hw_accel: spec.hw_accel,
+            profile: spec.profile,
+            lut_file: spec.lut,
+            lut_strength: spec.lut_strength,
+            contrast: spec.contrast,
+            saturation: spec.saturation,
+            vibrance: spec.vibrance,
+            dehaze: spec.dehaze,
+            curves: spec.curves,
+            hue_shift: spec.hue_shift,
+            color_balance: spec.color_balance.map(|b| {
+                let ([rs, gs, bs], [rm, gm, bm], [rh, gh, bh]) =
+                    (b.shadows, b.midtones, b.highlights);
+                (rs, gs, bs, rm, gm, bm, rh, gh, bh)
+            }),
+            selective_color: spec.selective_color,
+            denoise: spec.denoise,
+            sharpen: spec.sharpen,
+            scale: spec.scale,
+            auto_rotate: spec.auto_rotate,
+            stabilize: spec.stabilize,
+            stabilize_smoothing: spec.stabilize_smoothing,
+            transitions: spec.transitions,
+            canvas: spec.canvas,
+            fill: spec.fill,
+            stream_copy: spec.stream_copy,
+            merge_chapters: spec.merge_chapters,
+            ..base
+        }
+    }
+
+    /// The [`JobSpec`] equivalent to this processor's settings (everything but
+    /// the progress callback and cancellation token).
+    pub fn job_spec(&self) -> JobSpec {
+        JobSpec {
+            inputs: self.inputs.clone(),
+            output: self.output_path.clone(),
+            speed: self.speed_multiplier,
+            speed_ramp: self.speed_ramp.clone(),
+            ramp_easing: self.ramp_easing,
+            output_fps: self.output_fps.clone(),
+            interpolate: self.interpolate,
+            shutter_angle: self.shutter_angle,
+            fit_duration: self.fit_duration,
+            reverse: self.reverse,
+            boomerang: self.boomerang,
+            codec: self.codec.clone(),
+            bitrate: self.bitrate,
+            quality: self.quality,
+            threads: self.threads,
+            hw_accel: self.hw_accel,
+            profile: self.profile.clone(),
+            lut: self.lut_file.clone(),
+            lut_strength: self.lut_strength,
+            contrast: self.contrast,
+            saturation: self.saturation,
+            vibrance: self.vibrance,
+            dehaze: self.dehaze,
+            curves: self.curves.clone(),
+            hue_shift: self.hue_shift,
+            color_balance: self
+                .color_balance
+                .map(|(rs, gs, bs, rm, gm, bm, rh, gh, bh)| ColorBalance {
+                    shadows: [rs, gs, bs],
+                    midtones: [rm, gm, bm],
+                    highlights: [rh, gh, bh],
+                }),
+            selective_color: self.selective_color.clone(),
+            denoise: self.denoise,
+            sharpen: self.sharpen,
+            scale: self.scale.clone(),
+            auto_rotate: self.auto_rotate,
+            stabilize: self.stabilize,
+            stabilize_smoothing: self.stabilize_smoothing,
+            transitions: self.transitions.clone(),
+            canvas: self.canvas,
+            fill: self.fill,
+            stream_copy: self.stream_copy,
+            merge_chapters: self.merge_chapters,
         }
     }
 
@@ -87,6 +269,32 @@ impl VideoProcessor {
         self
     }
 
+    /// Change the speed over time along `ramp` instead of by one constant
+    /// multiplier (leave [`speed`](Self::speed) at 1). Keyframe times are
+    /// seconds of the source timeline: the trimmed clips, stitched, before the
+    /// speed change. Transition durations then count source seconds too.
+    pub fn speed_ramp(mut self, ramp: SpeedRamp) -> Self {
+        self.speed_ramp = Some(ramp);
+        self
+    }
+
+    /// Pick the speed so the output lasts `seconds`, from the probed length
+    /// of the (trimmed, stitched) clips, in place of [`speed`](Self::speed): a
+    /// 10-minute flight with a 30-second target plays at 20x. Transitions and a
+    /// boomerang are taken into account; the result is resampled to the output
+    /// frame rate like any speed change.
+    pub fn fit_duration(mut self, seconds: f64) -> Self {
+        self.fit_duration = Some(seconds);
+        self
+    }
+
+    /// Choose how a [`speed_ramp`](Self::speed_ramp) moves between keyframes
+    /// (default [`Easing::Linear`]).
+    pub fn ramp_easing(mut self, easing: Easing) -> Self {
+        self.ramp_easing = easing;
+        self
+    }
+
     /// Set the target output frame rate used when the speed is changed (e.g.
     /// `"30"` or `"30000/1001"`). Defaults to the source frame rate, so a
     /// speed-up drops frames rather than producing a higher-fps file.
@@ -95,6 +303,42 @@ impl VideoProcessor {
         self
     }
 
+    /// Choose how a slow-down fills in the frames the source does not have
+    /// (default [`Interpolation::None`]: repeat them). Has no effect on a
+    /// speed-up. Pair with [`output_fps`](Self::output_fps) above the source
+    /// rate for smoother slow motion still.
+    pub fn interpolate(mut self, interpolation: Interpolation) -> Self {
+        self.interpolate = interpolation;
+        self
+    }
+
+    /// Blend the frames a speed-up drops into the ones it keeps, like a
+    /// camera shutter open for `degrees` of each output frame (180 is the
+    /// natural film look, 360 the smoothest): a 10x hyperlapse then shows
+    /// motion blur instead of strobing. The number of frames blended follows
+    /// from the speed and the source and output frame rates. Not applied
+    /// along a speed ramp.
+    pub fn shutter_angle(mut self, degrees: f64) -> Self {
+        self.shutter_angle = Some(degrees);
+        self
+    }
+
+    /// Play the (trimmed, stitched) timeline backwards, sound included. The
+    /// speed change and grade apply to it as played, so a speed ramp's
+    /// keyframes count from the end of the footage.
+    pub fn reverse(mut self, enabled: bool) -> Self {
+        self.reverse = enabled;
+        self
+    }
+
+    /// Play the timeline forward and then backwards, `loops` times over
+    /// (a 3 s clip with 2 loops plays for 12 s). Speed and grade apply to the
+    /// whole boomerang.
+    pub fn boomerang(mut self, loops: u32) -> Self {
+        self.boomerang = Some(loops);
+        self
+    }
+
     /// Enable haze removal at the given strength (~0.5 medium, 1.0 strong).
     /// Pulls the black point, adds contrast, and restores saturation/vibrance.
     pub fn dehaze(mut self, strength: f32) -> Self {
@@ -109,6 +353,76 @@ impl VideoProcessor {
         self
     }
 
+    /// Join stitched clips with `transition` at every boundary instead of a
+    /// hard cut.
+    pub fn transition(self, transition: Transition) -> Self {
+        self.transitions(vec![transition])
+    }
+
+    /// Set the transition at each boundary between stitched clips, in order:
+    /// one per boundary, or a single one used for all of them. Durations are
+    /// in output seconds (after the speed change); each transition overlaps
+    /// its two clips, shortening the output by its duration.
+    pub fn transitions(mut self, transitions: Vec<Transition>) -> Self {
+        self.transitions = transitions;
+        self
+    }
+
+    /// Choose the frame stitched clips are fitted into (default
+    /// [`Canvas::Smallest`]): its size, and the frame rate every clip is
+    /// normalized to. Clips of another size or orientation are scaled to fit
+    /// and padded.
+    pub fn canvas(mut self, canvas: Canvas) -> Self {
+        self.canvas = canvas;
+        self
+    }
+
+    /// Choose how a stitched clip of another aspect ratio is fitted into the
+    /// canvas (default [`Fill::Pad`]): black bars, a blurred and darkened copy
+    /// of the clip behind it, or a center crop that fills the frame.
+    pub fn fill(mut self, fill: Fill) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Allow stitching without re-encoding (default on): when nothing changes
+    /// the clips' content and they share codec, resolution, pixel format, time
+    /// base, frame rate and audio format, they are joined with the concat
+    /// demuxer and `-c copy` in seconds. A codec, quality or bitrate other than
+    /// the default also asks for an encode; turn this off to transcode such
+    /// clips with the default settings anyway.
+    pub fn stream_copy(mut self, enabled: bool) -> Self {
+        self.stream_copy = enabled;
+        self
+    }
+
+    /// Treat the chapter files a camera splits one long recording into (see
+    /// [`chapters`](crate::chapters)) as one continuous clip: the boundaries
+    /// between them are always hard cuts, and with stabilization each
+    /// recording is joined first and smoothed as one segment, so the camera
+    /// path carries across a chapter split but still restarts at a real cut.
+    pub fn merge_chapters(mut self, enabled: bool) -> Self {
+        self.merge_chapters = enabled;
+        self
+    }
+
+    /// Receive structured progress (overall percent across all passes, the
+    /// current stage, fps, speed, ETA) instead of the default progress bar.
+    pub fn on_progress(
+        mut self,
+        callback: impl Fn(&ProgressEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Make the job cancellable: tripping `token` (from any thread) makes
+    /// [`process`](Self::process) stop and return [`SpeedyError::Cancelled`].
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
     pub fn codec(mut self, codec: &str) -> Self {
         self.codec = match codec {
             "h264" => "libx264",
@@ -152,6 +466,15 @@ impl VideoProcessor {
         self
     }
 
+    /// Apply the LUT (explicit or the colour profile's) at `strength`, from
+    /// 0.0 to 1.0 (the default): the graded frame is mixed with the ungraded
+    /// one, to tone down a conversion LUT that looks too punchy at full
+    /// strength.
+    pub fn lut_strength(mut self, strength: f32) -> Self {
+        self.lut_strength = strength;
+        self
+    }
+
     pub fn hardware_accel(mut self, enabled: bool) -> Self {
         self.hw_accel = enabled;
         self
@@ -259,26 +582,225 @@ impl VideoProcessor {
         }
     }
 
-    /// Process the video using FFmpeg CLI
+    /// The LUT the grade applies: the explicit one, else the colour profile's.
+    fn grade_lut(&self) -> Option<PathBuf> {
+        self.lut_file.clone().or_else(|| self.get_profile_lut())
+    }
+
+    /// Process the video using FFmpeg CLI.
+    ///
+    /// If the [`cancellation`](Self::cancellation) token is tripped, the running
+    /// ffmpeg is killed, intermediates and the partial output are removed, and
+    /// [`SpeedyError::Cancelled`] is returned.
     pub fn process(&self) -> Result<()> {
-        // Guard the indexing below: library callers can construct an empty
-        // processor via `new_multi`, which the CLI never does.
-        if self.inputs.is_empty() {
-            anyhow::bail!("No input files provided");
-        }
+        let started = SystemTime::now();
+        let result = self.run();
+        self.clean_up_if_cancelled(&result, started);
+        result
+    }
 
-        // Reject a speed that would produce garbage or hang: setpts=inf and an
-        // infinite atempo chaining loop for 0 / negative / non-finite speeds.
-        validate_speed(self.speed_multiplier)?;
+    /// List the ffprobe/ffmpeg commands [`process`](Self::process) would run,
+    /// in order (including the per-segment grade/detect/transform passes and
+    /// the joins), without running any ffmpeg.
+    ///
+    /// The inputs are probed with ffprobe (read-only), since their durations,
+    /// frame rates and sizes shape the commands.
+    pub fn plan(&self) -> Result<Vec<PlannedCommand>> {
+        self.validate()?;
+        let mut planned: Vec<PlannedCommand> = self
+            .inputs
+            .iter()
+            .map(|clip| PlannedCommand::from_command("probe", &probe_command(&clip.path)))
+            .collect();
+        let infos = self
+            .inputs
+            .iter()
+            .map(|clip| get_video_info(&clip.path))
+            .collect::<Result<Vec<_>>>()?;
+        planned.extend(self.pipeline(&infos)?.plan());
+        Ok(planned)
+    }
+
+    /// Async twin of [`process`](Self::process), with the `async` feature.
+    ///
+    /// Every ffmpeg/ffprobe run goes through `tokio::process`, so no thread
+    /// blocks on the job. Progress arrives on the returned [`ProgressStream`]
+    /// (and at the [`on_progress`](Self::on_progress) callback, if one is set)
+    /// instead of a terminal progress bar. Await or spawn the returned future to
+    /// run the job; dropping it kills the running ffmpeg.
+    #[cfg(feature = "async")]
+    pub fn process_async(
+        self,
+    ) -> (
+        ProgressStream,
+        impl Future<Output = Result<()>> + Send + 'static,
+    ) {
+        let (sink, stream) = ProgressStream::channel();
+        let job = async move {
+            let started = SystemTime::now();
+            let report = |event: &ProgressEvent| {
+                if let Some(callback) = &self.progress_callback {
+                    callback(event);
+                }
+                sink(event);
+            };
+            let result = self.run_async(&report).await;
+            self.clean_up_if_cancelled(&result, started);
+            result
+        };
+        (stream, job)
+    }
+
+    fn run(&self) -> Result<()> {
+        self.validate()?;
 
         // Check FFmpeg availability
         let ffmpeg_version = check_ffmpeg()?;
         log::info!("Using FFmpeg version: {}", ffmpeg_version);
 
-        // Get video info from the first clip (all stitched clips are assumed to
-        // share the same format, as they come from the same camera/source).
         log::info!("Analyzing input video...");
-        let info = get_video_info(&self.inputs[0])?;
+        let infos = self
+            .inputs
+            .iter()
+            .map(|clip| get_video_info(&clip.path))
+            .collect::<Result<Vec<_>>>()?;
+        let pipeline = self.pipeline(&infos)?;
+        self.with_progress(|sink| pipeline.run(sink, self.cancel.as_ref()))?;
+
+        log::info!("Video processing completed successfully!");
+        log::info!("Output saved to: {:?}", self.output_path);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    async fn run_async(&self, sink: &(dyn Fn(&ProgressEvent) + Sync)) -> Result<()> {
+        self.validate()?;
+
+        let ffmpeg_version = check_ffmpeg_async().await?;
+        log::info!("Using FFmpeg version: {ffmpeg_version}");
+
+        log::info!("Analyzing input video...");
+        let mut infos = Vec::with_capacity(self.inputs.len());
+        for clip in &self.inputs {
+            infos.push(get_video_info_async(&clip.path).await?);
+        }
+        let pipeline = self.pipeline(&infos)?;
+        pipeline.run_async(sink, self.cancel.as_ref()).await?;
+
+        log::info!("Video processing completed successfully!");
+        log::info!("Output saved to: {:?}", self.output_path);
+        Ok(())
+    }
+
+    /// Checks that need no ffmpeg: run before anything is spawned.
+    fn validate(&self) -> Result<()> {
+        // Guard the indexing in `pipeline`: library callers can construct an
+        // empty processor via `new_multi`, which the CLI never does.
+        if self.inputs.is_empty() {
+            return Err(SpeedyError::invalid("inputs", "no input files provided"));
+        }
+
+        // Reject a speed that would produce garbage or hang: setpts=inf and an
+        // infinite atempo chaining loop for 0 / negative / non-finite speeds.
+        validate_speed(self.speed_multiplier)?;
+        if self.speed_ramp.is_some() && self.speed_multiplier != 1.0 {
+            return Err(SpeedyError::invalid(
+                "speed ramp",
+                format!(
+                    "replaces the constant speed; leave the speed at 1, not {speed}",
+                    speed = self.speed_multiplier
+                ),
+            ));
+        }
+        if let Some(angle) = self.shutter_angle
+            && !SHUTTER_ANGLES.contains(&angle)
+        {
+            return Err(SpeedyError::invalid(
+                "shutter angle",
+                format!("{angle}; must be between 0 and 360 degrees"),
+            ));
+        }
+        if let Some(seconds) = self.fit_duration {
+            if !seconds.is_finite() || seconds <= 0.0 {
+                return Err(SpeedyError::invalid(
+                    "fit duration",
+                    format!("{seconds}; must be a positive number of seconds"),
+                ));
+            }
+            if self.speed_multiplier != 1.0 || self.speed_ramp.is_some() {
+                return Err(SpeedyError::invalid(
+                    "fit duration",
+                    "picks the speed itself; leave out the speed and the speed ramp",
+                ));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.lut_strength) {
+            return Err(SpeedyError::invalid(
+                "LUT strength",
+                format!(
+                    "{strength}; must be between 0.0 and 1.0",
+                    strength = self.lut_strength
+                ),
+            ));
+        }
+        if self.boomerang == Some(0) {
+            return Err(SpeedyError::invalid("boomerang", "needs at least one loop"));
+        }
+        if self.reverse && self.boomerang.is_some() {
+            return Err(SpeedyError::invalid(
+                "reverse",
+                "cannot be combined with a boomerang, which already plays both ways",
+            ));
+        }
+        for clip in &self.inputs {
+            clip.check()?;
+        }
+        // A broken LUT would otherwise only surface as an ffmpeg failure once
+        // the encode is under way.
+        let grade_lut = self.grade_lut();
+        if grade_lut.is_none() && self.lut_strength != 1.0 {
+            log::warn!("the LUT strength has no effect without a LUT");
+        }
+        if let Some(path) = grade_lut
+            && lut::is_cube_file(&path)
+        {
+            let cube = CubeLut::load(&path)?;
+            log::debug!(
+                "LUT {path}: {title}, {size}-point {kind:?}",
+                path = path.display(),
+                title = cube.title.as_deref().unwrap_or("untitled"),
+                size = cube.size,
+                kind = cube.kind
+            );
+        }
+        self.check_cancelled()
+    }
+
+    /// Turn the settings into the ffmpeg steps to run, given the probed info
+    /// of every input clip (in order).
+    fn pipeline(&self, infos: &[VideoInfo]) -> Result<Pipeline> {
+        // A fitted duration becomes a plain speed change, planned as such.
+        if let Some(seconds) = self.fit_duration {
+            let speed = self.fitting_speed(infos, seconds)?;
+            let mut spec = self.job_spec();
+            spec.speed = speed;
+            spec.fit_duration = None;
+            return VideoProcessor::from_job(spec).pipeline(infos);
+        }
+        // Reversal buffers whole clips; footage too long for that (or about
+        // to be stabilized clip by clip) is reversed in chunks first.
+        if (self.reverse || self.boomerang.is_some())
+            && (self.stabilize || !self.reverses_in_memory(infos)?)
+        {
+            return self.chunked_reverse_pipeline(infos);
+        }
+        // From here on a trimmed clip is planned as the part of its source it
+        // uses.
+        let trims = self.clip_trims(infos)?;
+        let infos = &trimmed_infos(infos, &trims);
+        // All stitched clips are assumed to share the same format, as they
+        // come from the same camera/source; the first one is representative.
+        let info = &infos[0];
         log::info!(
             "Video info: {}x{}, {:.2} fps, {:.2}s duration, rotation: {}°, audio: {}",
             info.width,
@@ -288,49 +810,250 @@ impl VideoProcessor {
             info.rotation,
             if info.has_audio { "yes" } else { "no" }
         );
+        log::debug!(
+            "Video stream: codec {codec}, {pix_fmt}, {bits}-bit, primaries {primaries}, transfer {transfer}, matrix {matrix}",
+            codec = info.video_codec.as_deref().unwrap_or("?"),
+            pix_fmt = info.pix_fmt.as_deref().unwrap_or("?"),
+            bits = info.bit_depth.unwrap_or(8),
+            primaries = info.color_primaries.as_deref().unwrap_or("unknown"),
+            transfer = info.color_transfer.as_deref().unwrap_or("unknown"),
+            matrix = info.color_space.as_deref().unwrap_or("unknown"),
+        );
 
         // Smoothing only affects the stabilization path; warn if it's a no-op
         // here, where the effective stabilize state (incl. presets) is known.
         if self.stabilize_smoothing.is_some() && !self.stabilize {
             log::warn!("stabilize_smoothing has no effect without stabilization enabled");
         }
+        if self.transitions.iter().any(|t| *t != Transition::Cut) {
+            if infos.len() < 2 {
+                log::warn!("transitions have no effect without several clips to stitch");
+            } else if self.stabilize {
+                log::warn!(
+                    "transitions are not applied on the stabilization path; clips are joined with hard cuts"
+                );
+            }
+        }
+
+        let slows_down = self.speed_multiplier < 1.0
+            || self
+                .speed_ramp
+                .as_ref()
+                .is_some_and(|ramp| ramp.keyframes().iter().any(|k| k.speed < 1.0));
+        if self.interpolate != Interpolation::None && !slows_down {
+            log::warn!("interpolation has no effect without a slow-down");
+        }
+        if self.shutter_angle.is_some() {
+            if self.speed_ramp.is_some() {
+                log::warn!("motion blur is not applied along a speed ramp");
+            } else if self.speed_multiplier <= 1.0 {
+                log::warn!("motion blur has no effect without a speed-up");
+            }
+        }
+        if self.canvas != Canvas::default() && infos.len() < 2 {
+            log::warn!(
+                "the canvas has no effect without several clips to stitch; use --scale to resize"
+            );
+        }
+        if self.fill != Fill::default() && infos.len() < 2 {
+            log::warn!("the fill mode has no effect without several clips to stitch");
+        }
 
-        // Stabilization needs a different pipeline (per-clip, two-pass vidstab),
-        // so route it out before building the single stitch/grade command.
+        // Clips with a speed of their own are retimed before anything else sees
+        // them, so the job's speed, transitions and ramp work on the timeline
+        // as it plays after that.
+        let infos = &retimed_infos(infos, &self.inputs);
+
+        // Stabilization needs a different pipeline (per-clip, two-pass vidstab)
+        // than the single stitch/grade command.
         if self.stabilize {
-            return self.process_stabilized(&info);
+            return self.stabilize_pipeline(infos, &trims);
+        }
+        if let Some(pipeline) = self.stream_copy_pipeline(infos)? {
+            return Ok(pipeline);
+        }
+        self.encode_pipeline(infos, &trims)
+    }
+
+    /// The lossless path: join the clips with the concat demuxer and `-c copy`
+    /// when nothing asks for their content to change and their streams match,
+    /// so no frame is decoded. `None` when they have to be re-encoded.
+    fn stream_copy_pipeline(&self, infos: &[VideoInfo]) -> Result<Option<Pipeline>> {
+        if !self.stream_copy || infos.len() < 2 || self.alters_content() {
+            return Ok(None);
+        }
+        let extension = |path: &Path| {
+            path.extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+        };
+        let first_extension = extension(&self.inputs[0].path);
+        let mismatch = copy_mismatch(infos).or_else(|| {
+            (extension(&self.output_path) != first_extension)
+                .then(|| "the output container differs from the inputs'".to_string())
+        });
+        if let Some(reason) = mismatch {
+            log::info!("Re-encoding: the clips cannot be joined losslessly ({reason})");
+            return Ok(None);
         }
 
-        // When multiple clips are given, probe every clip so we can pick a
-        // common output resolution and sum the durations (for the progress bar).
-        let stitching = self.inputs.len() > 1;
+        let info = &infos[0];
+        log::info!(
+            "Clips share {codec} {width}x{height} {pix_fmt} at {fps} fps; joining them losslessly \
+             with stream copy",
+            codec = info.video_codec.as_deref().unwrap_or("?"),
+            width = info.width,
+            height = info.height,
+            pix_fmt = info.pix_fmt.as_deref().unwrap_or("?"),
+            fps = info.frame_rate.as_deref().unwrap_or("?"),
+        );
+        let tmp = run_temp_dir("copy");
+        let files: Vec<PathBuf> = self.inputs.iter().map(|c| absolutize(&c.path)).collect();
+        let mut pass = stabilize::concat_files_pass(&files, &tmp, &absolutize(&self.output_path))?;
+        let total: f64 = infos.iter().map(|i| i.duration).sum();
+        pass.command = pass.command.total_duration(total);
+        Ok(Some(Pipeline {
+            steps: vec![Step {
+                stage: "concat".to_string(),
+                weight: 1.0,
+                action: Action::Concat(pass),
+            }],
+            temp_dirs: vec![tmp],
+            attempts: RETRY_ATTEMPTS,
+        }))
+    }
+
+    /// Whether any setting changes the picture or sound (or which part of a
+    /// clip is used), or asks for an encoder, quality or bitrate of its own,
+    /// so the clips have to be decoded and re-encoded.
+    fn alters_content(&self) -> bool {
+        self.codec != DEFAULT_CODEC
+            || self.quality != DEFAULT_QUALITY
+            || self.bitrate.is_some()
+            || self.speed_multiplier != 1.0
+            || self.speed_ramp.is_some()
+            || self.reverse
+            || self.boomerang.is_some()
+            || self.output_fps.is_some()
+            || self.stabilize
+            || (self.lut_file.is_some() && self.lut_strength > 0.0)
+            || self.profile != ColorProfile::Standard
+            || self.dehaze.is_some_and(|strength| strength > 0.0)
+            || self.contrast != 1.0
+            || self.saturation != 1.0
+            || !self.auto_rotate
+            || self.denoise.is_some()
+            || self.sharpen.is_some()
+            || self.vibrance.is_some()
+            || self.curves.is_some()
+            || self.hue_shift.is_some()
+            || self.color_balance.is_some()
+            || self.selective_color.is_some()
+            || self.scale.is_some()
+            || self.transitions.iter().any(|t| *t != Transition::Cut)
+            || matches!(self.canvas, Canvas::Size(..))
+            || self.inputs.iter().any(Clip::is_trimmed)
+            || self.inputs.iter().any(Clip::is_retimed)
+    }
+
+    /// Each clip's `(start, end)` seconds in its source, or `None` for a clip
+    /// used whole.
+    fn clip_trims(&self, infos: &[VideoInfo]) -> Result<Vec<Option<(f64, f64)>>> {
+        self.inputs
+            .iter()
+            .zip(infos)
+            .map(|(clip, info)| {
+                if !clip.is_trimmed() {
+                    return Ok(None);
+                }
+                let (start, end) = clip.range(info.duration)?;
+                log::info!(
+                    "{clip}: using {used:.2}s of {source:.2}s",
+                    used = end - start,
+                    source = info.duration
+                );
+                Ok(Some((start, end)))
+            })
+            .collect()
+    }
+
+    /// Each clip's own `(speed, fps)`, or `None` for a clip played in real
+    /// time, given the clips' [retimed](retimed_infos) info.
+    fn clip_retimes(&self, infos: &[VideoInfo]) -> Vec<Option<(f64, String)>> {
+        self.inputs
+            .iter()
+            .zip(infos)
+            .map(|(clip, info)| clip.speed.map(|speed| (speed, probe_target_fps(info))))
+            .collect()
+    }
+
+    /// The clips that continue one camera recording, as ranges of inputs (see
+    /// [`chapters::group_chapters`]). Only with `merge_chapters`; a trimmed
+    /// or retimed clip is always a shot of its own.
+    fn chapter_groups(&self, infos: &[VideoInfo]) -> Vec<Range<usize>> {
+        if !self.merge_chapters {
+            return (0..self.inputs.len()).map(|i| i..i + 1).collect();
+        }
+        let paths: Vec<PathBuf> = self.inputs.iter().map(|c| c.path.clone()).collect();
+        let mut groups = Vec::new();
+        for recording in chapters::group_chapters(&paths, infos) {
+            let mut start = recording.start;
+            for i in recording.clone() {
+                if self.inputs[i].is_trimmed() || self.inputs[i].is_retimed() {
+                    groups.extend([start..i, i..i + 1].into_iter().filter(|g| !g.is_empty()));
+                    start = i + 1;
+                }
+            }
+            if start < recording.end {
+                groups.push(start..recording.end);
+            }
+        }
+        for group in groups.iter().filter(|g| g.len() > 1) {
+            log::info!(
+                "{first} and {more} more file(s) continue one recording",
+                first = self.inputs[group.start],
+                more = group.len() - 1
+            );
+        }
+        groups
+    }
+
+    /// The single-command path: stitch (when several clips are given) and
+    /// grade in one ffmpeg pass.
+    fn encode_pipeline(
+        &self,
+        infos: &[VideoInfo],
+        trims: &[Option<(f64, f64)>],
+    ) -> Result<Pipeline> {
+        // When stitching, pick a common output resolution and sum the
+        // durations (for the progress bar).
+        let info = &infos[0];
+        let stitching = infos.len() > 1;
         let stitch_plan = if stitching {
-            let infos = self
-                .inputs
-                .iter()
-                .map(get_video_info)
-                .collect::<Result<Vec<_>>>()?;
-            let total: f64 = infos.iter().map(|i| i.duration).sum();
-            // Target the smallest display size across clips so nothing is
-            // upscaled; clips of other sizes are scaled to fit and padded.
-            let (width, height) = infos
+            // Transitions overlap neighbouring clips, shortening the output.
+            let durations: Vec<f64> = infos
                 .iter()
-                .map(|i| target_dimensions(i, self.auto_rotate))
-                .reduce(|(aw, ah), (bw, bh)| (aw.min(bw), ah.min(bh)))
-                .unwrap_or((info.width, info.height));
+                .map(|i| i.video_duration.unwrap_or(i.duration))
+                .collect();
+            let transitions = self.boundary_transitions(&durations, &self.chapter_groups(infos))?;
+            let overlap: f64 = transitions.iter().map(Transition::overlap).sum();
+            let total = durations.iter().sum::<f64>() - overlap;
+            // Clips of other sizes are scaled to fit the canvas and padded.
+            let (width, height) = self.canvas_size(infos);
             log::info!(
                 "Stitching {} clips ({total:.2}s total) at {width}x{height} into {:?}",
                 self.inputs.len(),
                 self.output_path
             );
-            // Stitching currently produces a video-only output; warn loudly so
-            // dropped audio is never a silent surprise.
-            if infos.iter().any(|i| i.has_audio) {
-                log::warn!(
-                    "Some input clips have audio, but stitched output is video-only; audio will be dropped"
-                );
-            }
-            Some((width, height, total))
+            // Carry audio through the join when any clip has it; clips without
+            // audio are filled with silence so the track stays in sync.
+            let audio = infos.iter().any(|i| i.has_audio).then(|| {
+                infos
+                    .iter()
+                    .zip(&durations)
+                    .map(|(i, &duration)| (i.has_audio, duration))
+                    .collect()
+            });
+            Some((width, height, total, audio, transitions, durations))
         } else {
             None
         };
@@ -338,7 +1061,7 @@ impl VideoProcessor {
         // Build FFmpeg command. In stitch mode all inputs are passed together;
         // otherwise just the single clip. Use absolute input/output paths so the
         // LUT working-directory trick (see apply_grade) can't redirect them.
-        let abs_inputs: Vec<PathBuf> = self.inputs.iter().map(|p| absolutize(p)).collect();
+        let abs_inputs: Vec<PathBuf> = self.inputs.iter().map(|c| absolutize(&c.path)).collect();
         let abs_output = absolutize(&self.output_path);
         let mut cmd = if stitch_plan.is_some() {
             FFmpegCommand::new_multi(abs_inputs, &abs_output)
@@ -350,14 +1073,47 @@ impl VideoProcessor {
         .overwrite()
         .preserve_metadata();
 
-        if let Some((width, height, total)) = stitch_plan {
-            // Probe the first video stream's frame rate specifically, so a file
-            // whose first stream is audio/data does not feed a bogus fps into
-            // the concat graph.
-            let fps = probe_video_fps(&self.inputs[0], info.fps);
+        // The grade sees the stitched timeline's audio, not just the first
+        // clip's, so the atempo chain is built whenever any clip has audio.
+        let mut grade_info = info.clone();
+        // Output seconds, which weigh the encode against any earlier steps.
+        let mut seconds = self.output_seconds(0.0, info.duration * self.plays());
+        if let Some((width, height, total, audio, transitions, durations)) = stitch_plan {
+            // Use the video stream's frame rate specifically, so a file whose
+            // first stream is audio/data does not feed a bogus fps into the
+            // concat graph.
+            let fps = probe_video_fps(&infos[self.canvas.rate_clip(infos)]);
             cmd = cmd
                 .concat_normalize(width, height, &fps)
-                .total_duration(total);
+                .concat_fill(self.fill)
+                .concat_transitions(transitions, durations);
+            seconds = self.output_seconds(0.0, total * self.plays());
+            cmd = cmd.total_duration(seconds);
+            // The grade (and a speed ramp) sees the whole joined timeline, at
+            // the canvas frame rate.
+            grade_info.duration = total;
+            grade_info.fps = infos[self.canvas.rate_clip(infos)].fps;
+            grade_info.has_audio = audio.is_some();
+            if let Some(clips) = audio {
+                cmd = cmd.concat_audio(clips);
+            }
+            if trims.iter().any(Option::is_some) {
+                cmd = cmd.concat_trims(trims.to_vec());
+            }
+            if self.inputs.iter().any(Clip::is_retimed) {
+                cmd = cmd.concat_speeds(self.clip_retimes(infos));
+            }
+        } else {
+            if let Some(&Some((start, end))) = trims.first() {
+                cmd = cmd.trim(start, end);
+            }
+            // The clip's own speed goes ahead of the grade's.
+            if let Some(Some((speed, fps))) = self.clip_retimes(infos).first() {
+                cmd = cmd.speed(*speed, info.has_audio, Some(fps));
+            }
+            // Progress is measured on the output timeline, which a speed
+            // change shortens relative to the input's own duration.
+            cmd = cmd.total_duration(seconds);
         }
 
         // Set bitrate if specified
@@ -388,86 +1144,182 @@ impl VideoProcessor {
         }
 
         // Apply the grade: speed, LUT, dehaze, colour, rotation, scaling, etc.
-        let target_fps = self.resolve_target_fps(&info)?;
-        cmd = self.apply_grade(cmd, &info, target_fps.as_deref());
+        // A stitch is decimated from the canvas frame rate.
+        let target_fps = self.resolve_target_fps(&infos[self.canvas.rate_clip(infos)])?;
+        cmd = self.apply_grade(cmd, &grade_info, 0.0, target_fps.as_deref());
+
+        Ok(Pipeline {
+            steps: vec![Step {
+                stage: "encode".to_string(),
+                weight: GRADE_COST * seconds,
+                action: Action::Run(cmd),
+            }],
+            temp_dirs: Vec::new(),
+            attempts: RETRY_ATTEMPTS,
+        })
+    }
 
-        // Set up progress bar
+    /// Run `job` reporting to the caller's progress callback, or to a terminal
+    /// progress bar when none was set.
+    fn with_progress<T>(
+        &self,
+        job: impl FnOnce(&(dyn Fn(&ProgressEvent) + Sync)) -> Result<T>,
+    ) -> Result<T> {
+        if let Some(callback) = &self.progress_callback {
+            return job(callback.as_ref());
+        }
         let pb = ProgressBar::new(100);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}% {msg}")
-                .unwrap()
-                .progress_chars("#>-"),
-        );
-
-        // Execute FFmpeg with progress tracking
-        let pb_clone = pb.clone();
-        cmd.execute(move |progress, message| {
-            pb_clone.set_position(progress as u64);
-            if progress >= 100.0 {
-                pb_clone.finish_with_message("Processing complete!");
-            } else {
-                pb_clone.set_message(message);
-            }
-        })?;
-
-        log::info!("Video processing completed successfully!");
-        log::info!("Output saved to: {:?}", self.output_path);
-
-        Ok(())
+        match ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}% {msg}")
+        {
+            Ok(style) => pb.set_style(style.progress_chars("#>-")),
+            Err(e) => log::debug!("invalid progress bar template: {e}"),
+        }
+        let result = job(&|event: &ProgressEvent| {
+            pb.set_position(event.percent.unwrap_or(0.0) as u64);
+            pb.set_message(progress_message(event));
+        });
+        if result.is_ok() {
+            pb.finish_with_message("Processing complete!");
+        } else {
+            pb.abandon();
+        }
+        result
     }
 
     /// Resolve the decimation target frame rate for a speed change. `None` when
     /// the speed is unchanged or the source fps cannot be determined. Errors on
     /// an explicit but invalid `--output-fps`.
-    fn resolve_target_fps(&self, info: &crate::VideoInfo) -> Result<Option<String>> {
-        if self.speed_multiplier == 1.0 {
+    fn resolve_target_fps(&self, info: &VideoInfo) -> Result<Option<String>> {
+        if self.speed_multiplier == 1.0 && self.speed_ramp.is_none() {
             return Ok(None);
         }
         let target = match &self.output_fps {
             Some(fps) => {
                 if fps_string_value(fps).is_none() {
-                    anyhow::bail!(
-                        "Invalid --output-fps {fps:?}; expected a positive number like \"30\" or \"30000/1001\""
-                    );
+                    return Err(SpeedyError::invalid(
+                        "output fps",
+                        format!(
+                            "{fps:?}; expected a positive number like \"30\" or \"30000/1001\""
+                        ),
+                    ));
                 }
                 Some(fps.clone())
             }
             None => {
-                let probed = probe_target_fps(&self.inputs[0], info.fps);
+                let probed = probe_target_fps(info);
                 fps_string_value(&probed).map(|_| probed)
             }
         };
         Ok(target)
     }
 
+    /// How long `duration` seconds of source, starting `start` seconds into
+    /// the source timeline, play for after the speed change.
+    fn output_seconds(&self, start: f64, duration: f64) -> f64 {
+        match &self.speed_ramp {
+            Some(ramp) => ramp.output_duration(start, duration, self.ramp_easing),
+            None => duration / self.speed_multiplier,
+        }
+    }
+
+    /// The constant-speed spans of the speed ramp over `duration` seconds of
+    /// source starting `start` seconds into the source timeline; empty without
+    /// a ramp.
+    fn ramp_segments(&self, start: f64, duration: f64) -> Vec<RampSegment> {
+        self.speed_ramp
+            .as_ref()
+            .map(|ramp| ramp.segments(start, duration, self.ramp_easing))
+            .unwrap_or_default()
+    }
+
     /// Apply the colour/speed/geometry grade — everything except stitch
     /// normalization and stabilization — to a command in a fixed order. Shared
-    /// by the single-command path and the per-clip stabilization path.
+    /// by the single-command path and the per-clip stabilization path. `start`
+    /// places the graded clip (lasting `info.duration`) on the source
+    /// timeline, which a speed ramp's keyframes refer to.
     fn apply_grade(
         &self,
         mut cmd: FFmpegCommand,
-        info: &crate::VideoInfo,
+        info: &VideoInfo,
+        start: f64,
         target_fps: Option<&str>,
     ) -> FFmpegCommand {
-        // Speed (resampled to the target fps so a speed-up drops frames).
-        if self.speed_multiplier != 1.0 {
-            if let Some(fps) = target_fps {
+        // Direction first, so the speed change (and a ramp's keyframes) see
+        // the timeline as it plays.
+        if self.reverse {
+            cmd = cmd.reverse(info.has_audio);
+        } else if let Some(loops) = self.boomerang {
+            cmd = cmd.boomerang(loops, info.has_audio);
+        }
+
+        // Speed (resampled to the target fps so a speed-up drops frames, and
+        // a slow-down repeats them or, if asked, interpolates new ones).
+        let segments = self.ramp_segments(start, info.duration * self.plays());
+        let slows_down = if self.speed_ramp.is_some() {
+            segments.iter().any(|s| s.speed < 1.0)
+        } else {
+            self.speed_multiplier < 1.0
+        };
+        let interpolation = if slows_down {
+            self.interpolate
+        } else {
+            Interpolation::None
+        };
+        // A constant speed-up may average the frames it drops into the ones it
+        // keeps, for a shutter-like motion blur.
+        let blur = match (self.shutter_angle, target_fps.and_then(fps_string_value)) {
+            (Some(angle), Some(output_fps))
+                if self.speed_ramp.is_none() && self.speed_multiplier > 1.0 =>
+            {
+                blur_frames(self.speed_multiplier, info.fps, output_fps, angle)
+            }
+            _ => 1,
+        };
+        // Interpolating or blurring has to happen around the resample, so it
+        // is then split off the speed change.
+        let (resample_fps, deferred_fps) = if interpolation == Interpolation::None && blur == 1 {
+            (target_fps, None)
+        } else {
+            (None, target_fps)
+        };
+        if self.speed_ramp.is_some() {
+            if let Some(fps) = resample_fps {
+                log::info!("Resampling to {fps} fps after the speed ramp");
+            }
+            cmd = cmd.speed_ramp(&segments, info.has_audio, resample_fps);
+        } else if self.speed_multiplier != 1.0 {
+            if let Some(fps) = resample_fps {
+                log::info!(
+                    "Resampling to {fps} fps after a {speed}x speed change",
+                    speed = self.speed_multiplier
+                );
+            }
+            cmd = cmd.speed(self.speed_multiplier, info.has_audio, resample_fps);
+        }
+        if blur > 1 {
+            log::info!("Blending {blur} frames into each output frame for motion blur");
+            cmd = cmd.motion_blur(blur);
+        }
+        if let Some(fps) = deferred_fps {
+            if interpolation == Interpolation::None {
                 log::info!(
                     "Resampling to {fps} fps after a {speed}x speed change",
                     speed = self.speed_multiplier
                 );
+            } else {
+                log::info!("Interpolating the slowed-down frames up to {fps} fps");
             }
-            cmd = cmd.speed(self.speed_multiplier, info.has_audio, target_fps);
+            cmd = cmd.resample(fps, interpolation);
         }
 
         // LUT (explicit, else from the colour profile). Run ffmpeg from the
         // LUT's directory and reference it by basename, so a path with
         // colons/backslashes/commas (Windows drives, odd dirs) isn't mis-parsed
         // as filtergraph syntax. Input/output paths are absolute, so changing
-        // the working directory is safe.
-        let lut = self.lut_file.clone().or_else(|| self.get_profile_lut());
-        if let Some(lut) = lut {
+        // the working directory is safe. A 1D `.cube` LUT needs `lut1d`;
+        // anything else goes to `lut3d`. At no strength it is left out.
+        if let Some(lut) = self.grade_lut().filter(|_| self.lut_strength > 0.0) {
             if self.lut_file.is_none() {
                 log::info!(
                     "Applying {} profile LUT: {}",
@@ -475,14 +1327,27 @@ impl VideoProcessor {
                     lut.display()
                 );
             }
+            let kind = match lut::is_cube_file(&lut).then(|| lut::cube_kind(&lut)) {
+                Some(Some(LutKind::OneD)) => LutKind::OneD,
+                _ => LutKind::ThreeD,
+            };
+            if self.lut_strength < 1.0 {
+                log::info!(
+                    "Mixing the LUT in at {percent:.0}% strength",
+                    percent = self.lut_strength * 100.0
+                );
+            }
+            let apply = |cmd: FFmpegCommand, file: &Path| {
+                cmd.lut_at_strength(file, kind, self.lut_strength)
+            };
             match (
                 lut.parent().filter(|p| !p.as_os_str().is_empty()),
                 lut.file_name(),
             ) {
                 (Some(parent), Some(name)) => {
-                    cmd = cmd.current_dir(absolutize(parent)).lut3d(name);
+                    cmd = apply(cmd.current_dir(absolutize(parent)), Path::new(name));
                 }
-                _ => cmd = cmd.lut3d(&lut),
+                _ => cmd = apply(cmd, &lut),
             }
         }
 
@@ -543,12 +1408,87 @@ impl VideoProcessor {
         cmd
     }
 
+    /// The stitched frame size for `infos`, per the canvas policy.
+    fn canvas_size(&self, infos: &[VideoInfo]) -> (u32, u32) {
+        let dims: Vec<(u32, u32)> = infos
+            .iter()
+            .map(|i| target_dimensions(i, self.auto_rotate))
+            .collect();
+        self.canvas
+            .size(&dims)
+            .unwrap_or((infos[0].width, infos[0].height))
+    }
+
+    /// The transition at each boundary between clips of the given (source)
+    /// durations, on the source timeline; a boundary inside one of the
+    /// `recordings` (chapter files, see [`chapter_groups`](Self::chapter_groups))
+    /// is always a cut. Errors when the configured count is neither one nor
+    /// one per boundary, or a clip is too short to hold the transitions at both
+    /// of its ends.
+    fn boundary_transitions(
+        &self,
+        durations: &[f64],
+        recordings: &[Range<usize>],
+    ) -> Result<Vec<Transition>> {
+        let boundaries = durations.len().saturating_sub(1);
+        let mut chosen = match self.transitions.as_slice() {
+            [] => vec![Transition::Cut; boundaries],
+            [one] => vec![one.clone(); boundaries],
+            many if many.len() == boundaries => many.to_vec(),
+            many => {
+                return Err(SpeedyError::invalid(
+                    "transitions",
+                    format!(
+                        "{given} given for {boundaries} clip boundaries; give one for all of them or one per boundary",
+                        given = many.len()
+                    ),
+                ));
+            }
+        };
+        for recording in recordings {
+            for boundary in recording.start..recording.end.saturating_sub(1) {
+                if let Some(transition) = chosen.get_mut(boundary) {
+                    *transition = Transition::Cut;
+                }
+            }
+        }
+        // User durations are output seconds; the graph runs before the speed
+        // change, where the same span is `speed` times longer.
+        let transitions: Vec<Transition> = chosen
+            .iter()
+            .map(|t| t.scaled(self.speed_multiplier))
+            .collect();
+        for (i, (&duration, input)) in durations.iter().zip(&self.inputs).enumerate() {
+            let before = i
+                .checked_sub(1)
+                .and_then(|b| transitions.get(b))
+                .map_or(0.0, Transition::overlap);
+            let after = transitions.get(i).map_or(0.0, Transition::overlap);
+            if before + after >= duration {
+                return Err(SpeedyError::invalid(
+                    "transitions",
+                    format!(
+                        "{input} lasts {clip:.2}s in the output, too short for {needed:.2}s of transitions",
+                        clip = duration / self.speed_multiplier,
+                        needed = (before + after) / self.speed_multiplier
+                    ),
+                ));
+            }
+        }
+        Ok(transitions)
+    }
+
     /// Stabilize with two-pass `vidstab`. When stitching, each clip is graded
     /// and stabilized independently before concatenation, so smoothing never
     /// crosses a cut (no artificial pan at boundaries). Motion is detected on a
     /// brightness-normalized copy so exposure (EV) changes don't induce shake.
-    /// Stabilized output is video-only.
-    fn process_stabilized(&self, info: &crate::VideoInfo) -> Result<()> {
+    /// The vidstab passes are video-only; audio is graded separately (retimed
+    /// with the same speed) and muxed back into the final output.
+    fn stabilize_pipeline(
+        &self,
+        infos: &[VideoInfo],
+        trims: &[Option<(f64, f64)>],
+    ) -> Result<Pipeline> {
         if self.hw_accel {
             log::warn!(
                 "--hw-accel is not applied on the stabilization path; grade/detect/transform use the software codec"
@@ -560,43 +1500,11 @@ impl VideoProcessor {
                 .unwrap_or(VidstabParams::default().smoothing),
             ..VidstabParams::default()
         };
-        let target_fps = self.resolve_target_fps(info)?;
+        let rate_info = &infos[self.canvas.rate_clip(infos)];
+        let target_fps = self.resolve_target_fps(rate_info)?;
         // High-quality intermediates so the extra encode generation before the
         // warp does not visibly degrade the grade.
         let inter_q = self.quality.min(16);
-
-        // Unique per-call temp dir: the pid alone collides across concurrent
-        // VideoProcessor runs in one process, which would clobber intermediates.
-        let nonce = STAB_RUN_SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        let tmp = std::env::temp_dir().join(format!(
-            "speedy-stab-{pid}-{nonce}",
-            pid = std::process::id()
-        ));
-        std::fs::create_dir_all(&tmp)
-            .with_context(|| format!("Failed to create temp dir {}", tmp.display()))?;
-
-        let result = self.run_stabilize(info, &tmp, &params, target_fps.as_deref(), inter_q);
-
-        if let Err(e) = std::fs::remove_dir_all(&tmp) {
-            log::debug!("could not clean temp dir {tmp}: {e}", tmp = tmp.display());
-        }
-        result?;
-
-        log::info!("Video processing completed successfully!");
-        log::info!("Output saved to: {:?}", self.output_path);
-        Ok(())
-    }
-
-    /// Inner stabilization driver (grade -> detect -> transform [-> concat]),
-    /// writing intermediates under `tmp`.
-    fn run_stabilize(
-        &self,
-        info: &crate::VideoInfo,
-        tmp: &Path,
-        params: &VidstabParams,
-        target_fps: Option<&str>,
-        inter_q: u8,
-    ) -> Result<()> {
         // Final-encode settings, mirrored so --bitrate/--threads are honored.
         let enc = stabilize::EncodeOpts {
             codec: &self.codec,
@@ -604,93 +1512,452 @@ impl VideoProcessor {
             bitrate: self.bitrate,
             threads: self.threads,
         };
-        // Matroska intermediates accept every codec speedy supports (incl.
-        // ProRes/VP9/AV1), unlike an `.mp4` intermediate.
-        if self.inputs.len() == 1 {
+
+        let tmp = run_temp_dir("stab");
+        // Steps are weighted by each segment's output duration; the stream-copy
+        // joins (chapters, video, then audio + mux) scale with the total.
+        let mut steps = Vec::new();
+        let sources = self.stab_segments(infos, trims, &tmp, &mut steps)?;
+
+        let count = sources.len();
+        let stitching = count > 1;
+        let (width, height) = self.canvas_size(infos);
+        if stitching {
+            log::info!(
+                "Stabilizing {count} segments at {width}x{height} (two-pass vidstab, smoothing={smoothing})",
+                smoothing = params.smoothing
+            );
+        } else {
             log::info!(
                 "Stabilizing (two-pass vidstab, smoothing={})",
                 params.smoothing
             );
-            let graded = tmp.join("graded_0.mkv");
-            let mut clip_info = info.clone();
-            clip_info.has_audio = false;
-            let mut cmd = FFmpegCommand::new(absolutize(&self.inputs[0]), &graded)
+        }
+        // Normalize every segment to a common frame rate so the stream-copy
+        // concat sees matching time bases (mirrors the non-stabilized path).
+        let common_fps = probe_video_fps(rate_info);
+        // Every stitched segment is emitted at this constant rate, so its exact
+        // duration (for fitting its audio) is frames / rate.
+        let segment_fps = target_fps.clone().unwrap_or_else(|| common_fps.clone());
+        let with_audio = infos.iter().any(|i| i.has_audio);
+
+        let mut segments = Vec::with_capacity(count);
+        let mut audio_segments = Vec::new();
+        let mut total = 0.0;
+        // Where each segment starts on the source timeline, for a speed ramp.
+        let mut offset = 0.0;
+        for (i, source) in sources.iter().enumerate() {
+            let label = |pass: &str| {
+                if stitching {
+                    format!("{pass} {n}/{count}", n = i + 1)
+                } else {
+                    pass.to_string()
+                }
+            };
+            let seconds = self.output_seconds(offset, source.info.duration);
+            total += seconds;
+            let mut grade_info = source.info.clone();
+            grade_info.has_audio = false;
+            // Matroska intermediates accept every codec speedy supports (incl.
+            // ProRes/VP9/AV1), unlike an `.mp4` intermediate.
+            let graded = tmp.join(format!("graded_{i}.mkv"));
+            let mut cmd = FFmpegCommand::new(&source.path, &graded)
                 .video_codec(&self.codec)
                 .quality(inter_q)
                 .video_only()
+                .total_duration(seconds)
                 .overwrite();
+            if let Some((start, end)) = source.trim {
+                cmd = cmd.trim(start, end);
+            }
+            if let Some((speed, fps)) = &source.retime {
+                cmd = cmd.speed(*speed, false, Some(fps));
+            }
+            if stitching {
+                cmd = cmd.scale_fill(width, height, &common_fps, self.fill);
+            }
             if let Some(threads) = self.threads {
                 cmd = cmd.threads(threads);
             }
-            self.apply_grade(cmd, &clip_info, target_fps)
-                .execute(|_, _| {})?;
-            let trf = tmp.join("t_0.trf");
-            stabilize::detect(&graded, &trf, params, RETRY_ATTEMPTS)?;
-            stabilize::transform(
-                &graded,
-                &self.output_path,
-                &trf,
-                &enc,
-                params,
-                RETRY_ATTEMPTS,
-            )?;
-            return Ok(());
-        }
-
-        // Stitch + stabilize: grade and stabilize each clip independently.
-        let infos = self
-            .inputs
-            .iter()
-            .map(get_video_info)
-            .collect::<Result<Vec<_>>>()?;
-        let (width, height) = infos
+            steps.push(Step {
+                stage: label("grade"),
+                weight: GRADE_COST * seconds,
+                action: Action::Run(self.apply_grade(
+                    cmd,
+                    &grade_info,
+                    offset,
+                    target_fps.as_deref(),
+                )),
+            });
+            let trf = tmp.join(format!("t_{i}.trf"));
+            steps.push(Step {
+                stage: label("detect"),
+                weight: DETECT_COST * seconds,
+                action: Action::Checked(stabilize::detect_pass(&graded, &trf, &params)),
+            });
+            // A lone clip without audio is transformed straight into the output.
+            let stab = if stitching || with_audio {
+                tmp.join(format!("stab_{i}.mkv"))
+            } else {
+                self.output_path.clone()
+            };
+            steps.push(Step {
+                stage: label("transform"),
+                weight: TRANSFORM_COST * seconds,
+                action: Action::Checked(stabilize::transform_pass(
+                    &graded, &stab, &trf, &enc, &params,
+                )),
+            });
+            if with_audio {
+                // Fit this clip's audio (or silence) to the stabilized segment,
+                // so the joined track cannot drift across the concat.
+                let audio = tmp.join(format!("audio_{i}.wav"));
+                steps.push(Step {
+                    stage: label("audio"),
+                    weight: AUDIO_COST * seconds,
+                    action: Action::FitAudio(FitAudio {
+                        clip: source.info.has_audio.then(|| source.path.clone()),
+                        trim: source.trim,
+                        video: stab.clone(),
+                        fps: if stitching {
+                            Some(segment_fps.clone())
+                        } else {
+                            target_fps.clone()
+                        },
+                        estimate: seconds,
+                        clip_speed: source.retime.as_ref().map_or(1.0, |(speed, _)| *speed),
+                        speed: self.speed_multiplier,
+                        ramp: self.ramp_segments(offset, source.info.duration),
+                        output: audio.clone(),
+                    }),
+                });
+                audio_segments.push(audio);
+            }
+            segments.push(stab);
+            offset += source.info.duration;
+        }
+
+        if stitching && !with_audio {
+            steps.push(Step {
+                stage: "concat".to_string(),
+                weight: COPY_COST * total,
+                action: Action::Concat(stabilize::concat_pass(&segments, &self.output_path)?),
+            });
+        } else if with_audio {
+            let (video, audio) = if stitching {
+                let joined = tmp.join("joined.mkv");
+                let joined_audio = tmp.join("joined_audio.wav");
+                steps.push(Step {
+                    stage: "concat".to_string(),
+                    weight: COPY_COST * total,
+                    action: Action::Concat(stabilize::concat_pass(&segments, &joined)?),
+                });
+                steps.push(Step {
+                    stage: "concat audio".to_string(),
+                    weight: COPY_COST * total,
+                    action: Action::Concat(stabilize::concat_pass(&audio_segments, &joined_audio)?),
+                });
+                (joined, joined_audio)
+            } else {
+                (tmp.join("stab_0.mkv"), tmp.join("audio_0.wav"))
+            };
+            steps.push(Step {
+                stage: "mux".to_string(),
+                weight: COPY_COST * total,
+                action: Action::Run(stabilize::mux_command(&video, &audio, &self.output_path)),
+            });
+        }
+
+        Ok(Pipeline {
+            steps,
+            temp_dirs: vec![tmp],
+            attempts: RETRY_ATTEMPTS,
+        })
+    }
+
+    /// How long each clip plays for, as trimmed and at its own speed.
+    fn clip_seconds(&self, infos: &[VideoInfo]) -> Result<Vec<f64>> {
+        self.inputs
             .iter()
-            .map(|i| target_dimensions(i, self.auto_rotate))
-            .reduce(|(aw, ah), (bw, bh)| (aw.min(bw), ah.min(bh)))
-            .unwrap_or((info.width, info.height));
+            .zip(infos)
+            .map(|(clip, info)| {
+                let (start, end) = clip.range(info.duration)?;
+                Ok((end - start) / clip.speed.unwrap_or(1.0))
+            })
+            .collect()
+    }
+
+    /// The speed at which the timeline plays for `seconds`. Transitions keep
+    /// their output length whatever the speed, so they are set aside first;
+    /// a boomerang plays the timeline (transitions included) repeatedly.
+    fn fitting_speed(&self, infos: &[VideoInfo], seconds: f64) -> Result<f64> {
+        let durations = self.clip_seconds(infos)?;
+        let total: f64 = durations.iter().sum();
+        let overlap: f64 = if self.stabilize {
+            0.0
+        } else {
+            self.boundary_transitions(&durations, &self.chapter_groups(infos))?
+                .iter()
+                .map(Transition::overlap)
+                .sum()
+        };
+        let plays = self.plays();
+        let speed = total * plays / (seconds + overlap * plays);
+        validate_speed(speed)?;
         log::info!(
-            "Stabilizing {count} clips per-segment at {width}x{height} (two-pass vidstab, smoothing={smoothing})",
-            count = self.inputs.len(),
-            smoothing = params.smoothing
+            "Fitting {played:.2}s into {seconds}s: {speed:.3}x speed",
+            played = total * plays
         );
-        if infos.iter().any(|i| i.has_audio) {
-            log::warn!(
-                "Some clips have audio, but stabilized stitched output is video-only; audio will be dropped"
-            );
+        Ok(speed)
+    }
+
+    /// How many times the timeline plays: twice per boomerang loop, else once.
+    fn plays(&self) -> f64 {
+        self.boomerang.map_or(1.0, |loops| 2.0 * f64::from(loops))
+    }
+
+    /// Whether reversing the (trimmed, stitched) timeline in the filter graph
+    /// stays within [`REVERSE_MEMORY`]: a reversal holds every frame of it,
+    /// and a boomerang a copy for each pass but the first.
+    fn reverses_in_memory(&self, infos: &[VideoInfo]) -> Result<bool> {
+        let seconds: f64 = self.clip_seconds(infos)?.iter().sum();
+        let copies = (self.plays() - 1.0).max(1.0);
+        let (width, height) = self.canvas_size(infos);
+        let rate = &infos[self.canvas.rate_clip(infos)];
+        let deep = infos.iter().any(|i| i.bit_depth.unwrap_or(8) > 8);
+        Ok(seconds * copies <= reverse_chunk_seconds(width, height, rate.fps, deep))
+    }
+
+    /// Reverse each clip chunk by chunk into an intermediate (the frames of
+    /// one chunk fit in [`REVERSE_MEMORY`]; the chunks are reversed back to
+    /// front and joined by stream copy), then process the clips as they play
+    /// (the reversed ones, or the originals and reversed ones in turn for a
+    /// boomerang) like any other stitch.
+    fn chunked_reverse_pipeline(&self, infos: &[VideoInfo]) -> Result<Pipeline> {
+        let tmp = run_temp_dir("reverse");
+        let mut steps = Vec::new();
+        let mut reversed = Vec::with_capacity(infos.len());
+        for (i, (clip, info)) in self.inputs.iter().zip(infos).enumerate() {
+            let (start, end) = clip.range(info.duration)?;
+            let (width, height) = target_dimensions(info, self.auto_rotate);
+            let deep = info.bit_depth.unwrap_or(8) > 8;
+            let chunk = reverse_chunk_seconds(width, height, info.fps, deep);
+            let count = ((end - start) / chunk).ceil().max(1.0) as usize;
+            log::info!("Reversing {clip} in {count} chunk(s) of up to {chunk:.1}s");
+            let output = tmp.join(format!("reversed_{i}.mkv"));
+            let mut chunks = Vec::with_capacity(count);
+            for k in 0..count {
+                let to = end - chunk * k as f64;
+                let from = (to - chunk).max(start);
+                let path = if count == 1 {
+                    output.clone()
+                } else {
+                    tmp.join(format!("reversed_{i}_{k}.mkv"))
+                };
+                // High-quality intermediates, as on the stabilization path.
+                let mut cmd = FFmpegCommand::new(absolutize(&clip.path), &path)
+                    .trim(from, to)
+                    .video_codec(&self.codec)
+                    .quality(self.quality.min(16))
+                    .reverse(info.has_audio)
+                    .total_duration(to - from)
+                    .overwrite();
+                cmd = if info.has_audio {
+                    cmd.audio_codec("pcm_s16le")
+                } else {
+                    cmd.video_only()
+                };
+                if !self.auto_rotate {
+                    cmd = cmd.disable_autorotate();
+                }
+                if let Some(threads) = self.threads {
+                    cmd = cmd.threads(threads);
+                }
+                steps.push(Step {
+                    stage: format!("reverse {n}/{count}", n = k + 1),
+                    weight: GRADE_COST * (to - from),
+                    action: Action::Run(cmd),
+                });
+                chunks.push(path);
+            }
+            if count > 1 {
+                steps.push(Step {
+                    stage: "join reversed".to_string(),
+                    weight: COPY_COST * (end - start),
+                    action: Action::Concat(stabilize::concat_pass(&chunks, &output)?),
+                });
+            }
+            // The intermediate is stored upright, as the filters saw it.
+            let info = VideoInfo {
+                width,
+                height,
+                rotation: 0,
+                duration: end - start,
+                video_duration: Some(end - start),
+                audio_duration: info.has_audio.then_some(end - start),
+                audio_codec: info.has_audio.then(|| "pcm_s16le".to_string()),
+                ..info.clone()
+            };
+            // It still plays at the clip's own speed.
+            let mut played = Clip::new(output);
+            played.speed = clip.speed;
+            played.fps = clip.fps.clone();
+            reversed.push((played, info));
         }
-        // Normalize every segment to a common frame rate so the stream-copy
-        // concat sees matching time bases (mirrors the non-stabilized path).
-        let common_fps = probe_video_fps(&self.inputs[0], info.fps);
+        reversed.reverse();
 
-        let mut segments = Vec::with_capacity(self.inputs.len());
-        for (i, clip) in self.inputs.iter().enumerate() {
-            log::info!(
-                "Segment {n}/{total}: grade + stabilize",
-                n = i + 1,
-                total = self.inputs.len()
-            );
-            let mut clip_info = infos[i].clone();
-            clip_info.has_audio = false;
-            let graded = tmp.join(format!("graded_{i}.mkv"));
-            let mut cmd = FFmpegCommand::new(absolutize(clip), &graded)
-                .video_codec(&self.codec)
-                .quality(inter_q)
-                .video_only()
-                .overwrite()
-                .scale_pad(width, height, &common_fps);
-            if let Some(threads) = self.threads {
-                cmd = cmd.threads(threads);
+        let forward = self.inputs.iter().cloned().zip(infos.iter().cloned());
+        let played: Vec<(Clip, VideoInfo)> = match self.boomerang {
+            Some(loops) => {
+                let pass: Vec<_> = forward.chain(reversed).collect();
+                (0..loops).flat_map(|_| pass.clone()).collect()
             }
-            self.apply_grade(cmd, &clip_info, target_fps)
-                .execute(|_, _| {})?;
-            let trf = tmp.join(format!("t_{i}.trf"));
-            stabilize::detect(&graded, &trf, params, RETRY_ATTEMPTS)?;
-            let stab = tmp.join(format!("stab_{i}.mkv"));
-            stabilize::transform(&graded, &stab, &trf, &enc, params, RETRY_ATTEMPTS)?;
-            segments.push(stab);
+            None => reversed,
+        };
+        let (clips, played_infos): (Vec<Clip>, Vec<VideoInfo>) = played.into_iter().unzip();
+        let mut spec = self.job_spec();
+        spec.inputs = clips;
+        spec.reverse = false;
+        spec.boomerang = None;
+        spec.stream_copy = false;
+        let mut pipeline = VideoProcessor::from_job(spec).pipeline(&played_infos)?;
+        steps.append(&mut pipeline.steps);
+        pipeline.steps = steps;
+        pipeline.temp_dirs.insert(0, tmp);
+        Ok(pipeline)
+    }
+
+    /// What the stabilization path smooths, one segment each: every clip,
+    /// except that the chapters of one recording (see
+    /// [`chapter_groups`](Self::chapter_groups)) are first joined by stream
+    /// copy into one file under `tmp`, adding that step to `steps`.
+    fn stab_segments(
+        &self,
+        infos: &[VideoInfo],
+        trims: &[Option<(f64, f64)>],
+        tmp: &Path,
+        steps: &mut Vec<Step>,
+    ) -> Result<Vec<StabSegment>> {
+        let mut segments = Vec::new();
+        let retimes = self.clip_retimes(infos);
+        for (g, group) in self.chapter_groups(infos).into_iter().enumerate() {
+            let first = group.start;
+            if group.len() == 1 {
+                segments.push(StabSegment {
+                    path: absolutize(&self.inputs[first].path),
+                    info: infos[first].clone(),
+                    trim: trims.get(first).copied().flatten(),
+                    retime: retimes[first].clone(),
+                });
+                continue;
+            }
+            let files: Vec<PathBuf> = self.inputs[group.clone()]
+                .iter()
+                .map(|c| absolutize(&c.path))
+                .collect();
+            let chapters = &infos[group];
+            let info = VideoInfo {
+                duration: chapters.iter().map(|c| c.duration).sum(),
+                video_duration: chapters.iter().map(|c| c.video_duration).sum(),
+                audio_duration: chapters.iter().map(|c| c.audio_duration).sum(),
+                ..infos[first].clone()
+            };
+            let joined = tmp.join(format!("chapters_{g}.mkv"));
+            let mut pass = stabilize::concat_files_pass(&files, tmp, &joined)?;
+            pass.command = pass.command.total_duration(info.duration);
+            steps.push(Step {
+                stage: "join chapters".to_string(),
+                weight: COPY_COST * info.duration / self.speed_multiplier,
+                action: Action::Concat(pass),
+            });
+            segments.push(StabSegment {
+                path: joined,
+                info,
+                trim: None,
+                retime: None,
+            });
+        }
+        Ok(segments)
+    }
+
+    /// Stop before spawning anything once cancelled; between passes the
+    /// pipeline checks for itself.
+    fn check_cancelled(&self) -> Result<()> {
+        if self
+            .cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(SpeedyError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// After a cancelled run, remove the partial output it left behind.
+    fn clean_up_if_cancelled(&self, result: &Result<()>, started: SystemTime) {
+        if matches!(result, Err(SpeedyError::Cancelled)) {
+            log::info!("Processing cancelled");
+            self.remove_partial_output(started);
         }
-        stabilize::concat(&segments, &self.output_path)
     }
+
+    /// Remove the output file a cancelled job left behind. Only a file written
+    /// since `started` is removed, never one that predates this run.
+    fn remove_partial_output(&self, started: SystemTime) {
+        let written_by_us = std::fs::metadata(&self.output_path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= started);
+        if written_by_us {
+            match std::fs::remove_file(&self.output_path) {
+                Ok(()) => log::info!(
+                    "Removed partial output {path}",
+                    path = self.output_path.display()
+                ),
+                Err(e) => log::warn!(
+                    "could not remove partial output {path}: {e}",
+                    path = self.output_path.display()
+                ),
+            }
+        }
+    }
+}
+
+/// One segment of the stabilization path: a clip, or the chapters of one
+/// recording joined into a single file.
+struct StabSegment {
+    /// Absolute path of the file to grade.
+    path: PathBuf,
+    info: VideoInfo,
+    /// `(start, end)` seconds of `path` to use, when the clip is trimmed.
+    trim: Option<(f64, f64)>,
+    /// The clip's own `(speed, fps)`, when it has one.
+    retime: Option<(f64, String)>,
+}
+
+/// The encoder and CRF used unless the job asks for others.
+const DEFAULT_CODEC: &str = "libx264";
+const DEFAULT_QUALITY: u8 = 23;
+
+/// Relative cost of each stabilization pass per second of output, weighting
+/// the overall progress: the grade and transform passes decode, filter and
+/// encode; detection only decodes and analyses; the audio and stream-copy
+/// passes are near-instant by comparison.
+const GRADE_COST: f64 = 1.0;
+const DETECT_COST: f64 = 0.6;
+const TRANSFORM_COST: f64 = 1.2;
+const AUDIO_COST: f64 = 0.05;
+const COPY_COST: f64 = 0.02;
+
+/// Progress-bar message for an event: the stage, plus speed and ETA when known.
+fn progress_message(event: &ProgressEvent) -> String {
+    let mut message = event.stage.clone();
+    if let Some(speed) = event.speed {
+        message.push_str(&format!(" ({speed:.1}x)"));
+    }
+    if let Some(eta) = event.eta.filter(|_| !event.done) {
+        message.push_str(&format!(" ETA {secs}s", secs = eta.as_secs()));
+    }
+    message
 }
 
 /// Number of attempts for each stabilization ffmpeg pass before giving up.
@@ -698,79 +1965,99 @@ impl VideoProcessor {
 /// retry until the pass validates rather than trusting one exit code.
 const RETRY_ATTEMPTS: u32 = 6;
 
-/// Per-process counter making each stabilization run's temp dir unique, so
-/// concurrent `process()` calls in one process don't clobber each other.
-static STAB_RUN_SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
-
-/// Probe the first *video* stream's base frame rate (`r_frame_rate`) as an
-/// ffmpeg-ready string (e.g. `"30000/1001"`). Falls back to the formatted
-/// `default` when the value is missing or degenerate (e.g. a non-video first
-/// stream reporting `0/0`). Used to set a common CFR cadence when stitching.
-fn probe_video_fps(path: &Path, default: f64) -> String {
-    probe_stream_rate(path, "r_frame_rate").unwrap_or_else(|| format!("{default:.5}"))
+/// Memory the decoded frames of one reversal may take: a clip or chunk is
+/// reversed in one piece only while its frames fit.
+const REVERSE_MEMORY: f64 = 2.0 * 1024.0 * 1024.0 * 1024.0;
+
+/// Seconds of `width`x`height` footage at `fps` whose decoded 4:2:0 frames
+/// fit in [`REVERSE_MEMORY`] (with two bytes per sample when `deep`, i.e.
+/// over 8 bits), but at least one: about 6s of 8-bit 4K at 30 fps.
+fn reverse_chunk_seconds(width: u32, height: u32, fps: f64, deep: bool) -> f64 {
+    let bytes_per_pixel = if deep { 3.0 } else { 1.5 };
+    let per_second = f64::from(width) * f64::from(height) * bytes_per_pixel * fps.max(1.0);
+    (REVERSE_MEMORY / per_second).max(1.0)
 }
 
-/// Probe the decimation target for a speed change: the first video stream's
-/// average cadence (`avg_frame_rate`), falling back to the base `r_frame_rate`
-/// and then the formatted `default`. The average rate is the right target for
-/// variable-frame-rate sources — there `r_frame_rate` is only a timebase guess
-/// and can be far higher than the real cadence, which would otherwise keep too
-/// many frames after a speed-up.
-fn probe_target_fps(path: &Path, default: f64) -> String {
-    probe_stream_rate(path, "avg_frame_rate")
-        .or_else(|| probe_stream_rate(path, "r_frame_rate"))
-        .unwrap_or_else(|| format!("{default:.5}"))
+/// Per-process counter making each run's temp dir unique, so concurrent
+/// `process()` calls in one process don't clobber each other.
+static RUN_SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// A fresh temp dir path for one run's intermediates, e.g.
+/// `speedy-stab-{pid}-{n}`. The pid alone collides across concurrent
+/// VideoProcessor runs in one process.
+fn run_temp_dir(kind: &str) -> PathBuf {
+    let nonce = RUN_SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "speedy-{kind}-{pid}-{nonce}",
+        pid = std::process::id()
+    ))
 }
 
-/// Read a single rational rate entry (`r_frame_rate` or `avg_frame_rate`) for
-/// the first video stream, returning it verbatim only when it is a positive
-/// rational (`num > 0 && den > 0`); otherwise `None`.
-fn probe_stream_rate(path: &Path, entry: &str) -> Option<String> {
-    let show_entries = format!("stream={entry}");
-    let rate = std::process::Command::new("ffprobe")
-        .args([
-            "-v",
-            "error",
-            "-select_streams",
-            "v:0",
-            "-show_entries",
-            show_entries.as_str(),
-            "-of",
-            "default=noprint_wrappers=1:nokey=1",
-        ])
-        .arg(path)
-        .output()
-        .ok()
-        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())?;
-
-    if let Some((num, den)) = rate.split_once('/')
-        && let (Ok(num), Ok(den)) = (num.parse::<f64>(), den.parse::<f64>())
-        && num > 0.0
-        && den > 0.0
-    {
-        Some(rate)
-    } else {
-        None
+/// Why `infos` cannot be joined by stream copy, or `None` when they can: every
+/// clip must match the first in video codec, size, pixel format, time base,
+/// frame rate and rotation, and have the same audio (none, or the same codec,
+/// sample rate and channels). The concat demuxer copies packets as they are,
+/// so any difference would break playback at the join.
+fn copy_mismatch(infos: &[VideoInfo]) -> Option<String> {
+    let first = infos.first()?;
+    if first.video_codec.is_none() {
+        return Some("the video codec is unknown".to_string());
     }
-}
-
-/// Parse an ffmpeg frame-rate string (`"30000/1001"` or `"29.97"`) into a
-/// positive float, returning `None` when it is missing, malformed, or
-/// non-positive. Used to reject a degenerate source fps before feeding it to the
-/// `fps` filter (where `fps=0` would be invalid).
-fn fps_string_value(s: &str) -> Option<f64> {
-    if let Some((num, den)) = s.split_once('/') {
-        let num: f64 = num.trim().parse().ok()?;
-        let den: f64 = den.trim().parse().ok()?;
-        if num > 0.0 && den > 0.0 {
-            Some(num / den)
+    for (i, info) in infos.iter().enumerate().skip(1) {
+        let differs = if info.video_codec != first.video_codec {
+            Some("video codec")
+        } else if (info.width, info.height) != (first.width, first.height) {
+            Some("resolution")
+        } else if info.pix_fmt != first.pix_fmt {
+            Some("pixel format")
+        } else if info.time_base != first.time_base {
+            Some("time base")
+        } else if info.frame_rate != first.frame_rate {
+            Some("frame rate")
+        } else if info.rotation != first.rotation {
+            Some("rotation")
+        } else if info.has_audio != first.has_audio {
+            Some("audio presence")
+        } else if (
+            info.audio_codec.as_ref(),
+            info.audio_sample_rate,
+            info.audio_channels,
+        ) != (
+            first.audio_codec.as_ref(),
+            first.audio_sample_rate,
+            first.audio_channels,
+        ) {
+            Some("audio format")
         } else {
             None
+        };
+        if let Some(what) = differs {
+            return Some(format!("clip {n} differs in {what}", n = i + 1));
         }
-    } else {
-        let value: f64 = s.trim().parse().ok()?;
-        (value > 0.0).then_some(value)
     }
+    None
+}
+
+/// The first video stream's base frame rate (`r_frame_rate`) as an ffmpeg-ready
+/// string (e.g. `"30000/1001"`), from the probed info. Falls back to the
+/// formatted float `fps` when the rate is missing or degenerate. Used to set a
+/// common CFR cadence when stitching.
+fn probe_video_fps(info: &VideoInfo) -> String {
+    info.frame_rate
+        .clone()
+        .unwrap_or_else(|| format!("{fps:.5}", fps = info.fps))
+}
+
+/// The decimation target for a speed change: the first video stream's average
+/// cadence (`avg_frame_rate`), falling back to the base `r_frame_rate` and then
+/// the formatted float `fps`. The average rate is the right target for
+/// variable-frame-rate sources — there `r_frame_rate` is only a timebase guess
+/// and can be far higher than the real cadence, which would otherwise keep too
+/// many frames after a speed-up.
+fn probe_target_fps(info: &VideoInfo) -> String {
+    info.avg_frame_rate
+        .clone()
+        .unwrap_or_else(|| probe_video_fps(info))
 }
 
 /// Make a path absolute (without requiring it to exist), falling back to the
@@ -785,7 +2072,10 @@ fn absolutize(path: &Path) -> PathBuf {
 /// chaining loop can spin forever.
 fn validate_speed(multiplier: f64) -> Result<()> {
     if multiplier != 1.0 && (!multiplier.is_finite() || multiplier <= 0.0) {
-        anyhow::bail!("Invalid speed {multiplier}; must be a positive, finite number");
+        return Err(SpeedyError::invalid(
+            "speed",
+            format!("{multiplier}; must be a positive, finite number"),
+        ));
     }
     Ok(())
 }
@@ -803,7 +2093,7 @@ fn parse_scale(spec: &str) -> Option<(i32, i32)> {
 
 /// Display dimensions of a clip, accounting for a 90°/270° rotation flag
 /// (cameras often store rotated footage with a rotation tag).
-fn display_dimensions(info: &crate::VideoInfo) -> (u32, u32) {
+fn display_dimensions(info: &VideoInfo) -> (u32, u32) {
     if info.rotation.abs() % 180 == 90 {
         (info.height, info.width)
     } else {
@@ -811,12 +2101,54 @@ fn display_dimensions(info: &crate::VideoInfo) -> (u32, u32) {
     }
 }
 
+/// `infos` with each trimmed clip's durations cut to the span it uses.
+fn trimmed_infos(infos: &[VideoInfo], trims: &[Option<(f64, f64)>]) -> Vec<VideoInfo> {
+    infos
+        .iter()
+        .zip(trims)
+        .map(|(info, trim)| {
+            let mut info = info.clone();
+            if let Some((start, end)) = *trim {
+                info.duration = end - start;
+                info.video_duration = info
+                    .video_duration
+                    .map(|video| (video.min(end) - start).max(0.0));
+            }
+            info
+        })
+        .collect()
+}
+
+/// `infos` with each clip that has a speed of its own as it plays: durations
+/// divided by the speed, at the frame rate it is resampled to.
+fn retimed_infos(infos: &[VideoInfo], clips: &[Clip]) -> Vec<VideoInfo> {
+    infos
+        .iter()
+        .zip(clips)
+        .map(|(info, clip)| {
+            let mut info = info.clone();
+            if let Some(speed) = clip.speed {
+                info.duration /= speed;
+                info.video_duration = info.video_duration.map(|video| video / speed);
+                info.audio_duration = info.audio_duration.map(|audio| audio / speed);
+                let fps = clip.fps.clone().unwrap_or_else(|| probe_target_fps(&info));
+                if let Some(rate) = fps_string_value(&fps) {
+                    info.fps = rate;
+                    info.frame_rate = Some(fps.clone());
+                    info.avg_frame_rate = Some(fps);
+                }
+            }
+            info
+        })
+        .collect()
+}
+
 /// The frame size the scale/pad target should match for stitching. With
 /// autorotation on (default), filters see the rotated display frame, so use
 /// display dimensions. With `--no-auto-rotate`, ffmpeg keeps the stored frame,
 /// so use the stored dimensions — otherwise a rotated clip is scaled/padded into
 /// a swapped canvas and comes out sideways and letterboxed.
-fn target_dimensions(info: &crate::VideoInfo, auto_rotate: bool) -> (u32, u32) {
+fn target_dimensions(info: &VideoInfo, auto_rotate: bool) -> (u32, u32) {
     if auto_rotate {
         display_dimensions(info)
     } else {
@@ -827,16 +2159,14 @@ fn target_dimensions(info: &crate::VideoInfo, auto_rotate: bool) -> (u32, u32) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::VideoInfo;
 
     fn info(width: u32, height: u32, rotation: i32) -> VideoInfo {
         VideoInfo {
-            duration: 0.0,
             width,
             height,
             fps: 30.0,
             rotation,
-            has_audio: false,
+            ..VideoInfo::default()
         }
     }
 
@@ -865,14 +2195,18 @@ mod tests {
     }
 
     #[test]
-    fn fps_string_value_parses_rational_and_decimal() {
-        assert_eq!(fps_string_value("30000/1001"), Some(30000.0 / 1001.0));
-        assert_eq!(fps_string_value("30"), Some(30.0));
-        assert_eq!(fps_string_value("60.0"), Some(60.0));
-        // Degenerate or malformed rates are rejected so they never reach `fps=`.
-        assert_eq!(fps_string_value("0/0"), None);
-        assert_eq!(fps_string_value("0"), None);
-        assert_eq!(fps_string_value("abc"), None);
+    fn target_fps_prefers_average_cadence_then_base_rate() {
+        // VFR phone footage: r_frame_rate is a timebase guess far above the
+        // real cadence, so decimation targets the average rate.
+        let vfr = VideoInfo {
+            frame_rate: Some("90000/1".to_string()),
+            avg_frame_rate: Some("30000/1001".to_string()),
+            ..info(1920, 1080, 0)
+        };
+        assert_eq!(probe_target_fps(&vfr), "30000/1001");
+        assert_eq!(probe_video_fps(&vfr), "90000/1");
+        // No usable rates probed: fall back to the float fps.
+        assert_eq!(probe_target_fps(&info(1920, 1080, 0)), "30.00000");
     }
 
     #[test]
@@ -931,6 +2265,7 @@ mod tests {
             .apply_grade(
                 crate::FFmpegCommand::new("in.mp4", "out.mp4"),
                 &info(3840, 2160, 0),
+                0.0,
                 None,
             )
             .build();
@@ -948,6 +2283,188 @@ mod tests {
         assert!(lut_at < dehaze_at, "lut must precede dehaze: {fc}");
     }
 
+    #[test]
+    fn interpolation_only_replaces_frame_repeats_on_a_slow_down() -> Result<()> {
+        let graph = |speed| -> Result<String> {
+            let p = VideoProcessor::new("in.mp4", "out.mp4")
+                .speed(speed)
+                .output_fps("60")
+                .interpolate(Interpolation::Mci);
+            Ok(p.pipeline(&[chapter(10.0)])?.plan()[0].argv.join(" "))
+        };
+        let slow = graph(0.25)?;
+        assert!(
+            slow.contains("setpts=4.0000*PTS,minterpolate=fps=60:mi_mode=mci"),
+            "{slow}"
+        );
+        // A speed-up still just drops frames.
+        let fast = graph(4.0)?;
+        assert!(fast.contains("setpts=0.2500*PTS,fps=60"), "{fast}");
+        assert!(!fast.contains("minterpolate"), "{fast}");
+        Ok(())
+    }
+
+    #[test]
+    fn shutter_angle_blends_dropped_frames_before_the_resample() -> Result<()> {
+        let p = VideoProcessor::new("in.mp4", "out.mp4")
+            .speed(10.0)
+            .shutter_angle(180.0);
+        let graph = p.pipeline(&[chapter(10.0)])?.plan()[0].argv.join(" ");
+        assert!(
+            graph.contains("setpts=0.1000*PTS,tmix=frames=5,fps=30000/1001"),
+            "{graph}"
+        );
+        let bad = VideoProcessor::new("in.mp4", "out.mp4")
+            .speed(10.0)
+            .shutter_angle(400.0);
+        assert!(bad.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn short_boomerang_is_built_in_the_graph_before_the_speed() -> Result<()> {
+        let p = VideoProcessor::new("in.mp4", "out.mp4")
+            .boomerang(1)
+            .speed(2.0);
+        let planned = p.pipeline(&[chapter(2.0)])?.plan();
+        assert_eq!(planned.len(), 1);
+        let graph = planned[0].argv.join(" ");
+        assert!(
+            graph.contains("concat=n=2:v=1:a=0,setpts=0.5000*PTS"),
+            "{graph}"
+        );
+        assert!(
+            graph.contains("[aboomr1]concat=n=2:v=0:a=1,atempo"),
+            "{graph}"
+        );
+
+        let both = VideoProcessor::new("in.mp4", "out.mp4")
+            .reverse(true)
+            .boomerang(2);
+        assert!(both.validate().is_err());
+        assert!(
+            VideoProcessor::new("in.mp4", "out.mp4")
+                .boomerang(0)
+                .validate()
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn long_reverse_is_done_in_chunks_back_to_front() -> Result<()> {
+        // 20s of 4K: about 5.75s of frames fit in memory, so four chunks.
+        let p = VideoProcessor::new("in.mp4", "out.mp4").reverse(true);
+        let planned = p.pipeline(&[chapter(20.0)])?.plan();
+        let stages: Vec<&str> = planned.iter().map(|c| c.stage.as_str()).collect();
+        assert_eq!(
+            stages,
+            [
+                "reverse 1/4",
+                "reverse 2/4",
+                "reverse 3/4",
+                "reverse 4/4",
+                "join reversed",
+                "encode"
+            ]
+        );
+        // The last chunk of the clip is reversed first.
+        let first = planned[0].argv.join(" ");
+        assert!(first.contains("-ss 14.246"), "{first}");
+        assert!(first.contains("-to 20.000000"), "{first}");
+        assert!(first.contains("[0:v]reverse") && first.contains("[0:a]areverse"));
+        let encode = planned[5].argv.join(" ");
+        assert!(encode.contains("reversed_0.mkv"), "{encode}");
+        assert!(!encode.contains("reverse,"), "{encode}");
+        Ok(())
+    }
+
+    #[test]
+    fn fit_duration_picks_the_speed_from_the_trimmed_timeline() -> Result<()> {
+        // 30s + 20s of the second clip's 60s, into 10s with a 1s fade: the
+        // fade overlaps the clips by its output length, so 50s of source
+        // must play for 11s.
+        let clips = vec![
+            Clip::new("a.mp4"),
+            Clip::new("b.mp4").start(10.0).duration(20.0),
+        ];
+        let p = VideoProcessor::from_clips(clips, "out.mp4")
+            .fit_duration(10.0)
+            .transition("fade:1".parse()?);
+        let speed = p.fitting_speed(&[chapter(30.0), chapter(60.0)], 10.0)?;
+        assert!((speed - 50.0 / 11.0).abs() < 1e-9, "{speed}");
+        let graph = p.pipeline(&[chapter(30.0), chapter(60.0)])?.plan()[0]
+            .argv
+            .join(" ");
+        assert!(
+            graph.contains("setpts=0.2200*PTS,fps=30000/1001"),
+            "{graph}"
+        );
+
+        let both = VideoProcessor::new("in.mp4", "out.mp4")
+            .speed(2.0)
+            .fit_duration(10.0);
+        assert!(both.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn speed_ramp_spans_the_stitched_timeline_and_excludes_speed() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let infos = [chapter(10.0), chapter(10.0)];
+        let ramp: SpeedRamp = "0:1,12:1,14:4".parse()?;
+        let p = VideoProcessor::new_multi(inputs.clone(), "out.mp4").speed_ramp(ramp.clone());
+        // 12s at real time, about 0.92s ramping up to 4x, then 1.5s at 4x.
+        assert!((p.output_seconds(0.0, 20.0) - 14.42).abs() < 0.01);
+        let graph = p.pipeline(&infos)?.plan()[0].argv.join(" ");
+        assert!(graph.contains("max(T-14.000000,0)/4.000000"), "{graph}");
+        assert!(graph.contains(",fps=30000/1001,"), "{graph}");
+
+        let both = VideoProcessor::new_multi(inputs, "out.mp4")
+            .speed(2.0)
+            .speed_ramp(ramp);
+        assert!(both.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn clip_speeds_retime_their_own_clip_before_the_shared_grade() -> Result<()> {
+        let clips = vec![
+            Clip::new("takeoff.mp4"),
+            Clip::new("transit.mp4").speed(10.0).fps("30"),
+        ];
+        let infos = [chapter(10.0), chapter(60.0)];
+        let p = VideoProcessor::from_clips(clips.clone(), "out.mp4").speed(2.0);
+        let graph = p.pipeline(&infos)?.plan()[0].argv.join(" ");
+        assert!(
+            graph.contains("[1:v]setpts=0.1000*(PTS-STARTPTS),fps=30,scale="),
+            "{graph}"
+        );
+        // The minute of transit lasts six seconds on the joined timeline.
+        assert!(graph.contains("atrim=duration=6.000000"), "{graph}");
+        assert_eq!(graph.matches("setpts=0.5000*PTS").count(), 1, "{graph}");
+
+        let p = VideoProcessor::from_clips(clips, "out.mp4")
+            .speed(2.0)
+            .stabilize(true);
+        let planned = p.pipeline(&infos)?.plan();
+        let grade = |stage: &str| {
+            planned
+                .iter()
+                .find(|c| c.stage == stage)
+                .map(|c| c.argv.join(" "))
+                .unwrap_or_default()
+        };
+        let (takeoff, transit) = (grade("grade 1/2"), grade("grade 2/2"));
+        assert!(!takeoff.contains("setpts=0.1000*PTS"), "{takeoff}");
+        assert!(
+            transit.contains("setpts=0.1000*PTS,fps=30,scale="),
+            "{transit}"
+        );
+        assert!(transit.contains("setpts=0.5000*PTS"), "{transit}");
+        Ok(())
+    }
+
     #[test]
     fn target_dimensions_uses_stored_dims_when_autorotate_off() {
         // -90 clip: stored portrait 3384x6016, displays landscape 6016x3384.
@@ -960,6 +2477,166 @@ mod tests {
         assert_eq!(target_dimensions(&upright, false), (3840, 2160));
     }
 
+    #[test]
+    fn boundary_transitions_repeat_one_scale_by_speed_and_fit_the_clips() -> Result<()> {
+        let inputs = vec![
+            PathBuf::from("a.mp4"),
+            PathBuf::from("b.mp4"),
+            PathBuf::from("c.mp4"),
+        ];
+        let fade = Transition::xfade("fade", 0.5)?;
+        let p = VideoProcessor::new_multi(inputs.clone(), "out.mp4")
+            .speed(4.0)
+            .transition(fade.clone());
+        // Half a second of output is two seconds of 4x source.
+        let doubled = Transition::xfade("fade", 2.0)?;
+        assert_eq!(
+            p.boundary_transitions(&[10.0, 10.0, 10.0], &[])?,
+            vec![doubled.clone(), doubled]
+        );
+        // The middle clip needs 4s of source for the transitions at both ends.
+        assert!(p.boundary_transitions(&[10.0, 4.0, 10.0], &[]).is_err());
+
+        let p = VideoProcessor::new_multi(inputs, "out.mp4").transitions(vec![
+            fade.clone(),
+            Transition::Cut,
+            fade,
+        ]);
+        assert!(p.boundary_transitions(&[10.0, 10.0, 10.0], &[]).is_err());
+        Ok(())
+    }
+
+    /// A probed 4K HEVC clip with AAC audio, as consecutive camera chapter
+    /// files report it.
+    fn chapter(duration: f64) -> VideoInfo {
+        VideoInfo {
+            duration,
+            video_codec: Some("hevc".to_string()),
+            pix_fmt: Some("yuv420p10le".to_string()),
+            time_base: Some("1/30000".to_string()),
+            frame_rate: Some("30000/1001".to_string()),
+            has_audio: true,
+            audio_codec: Some("aac".to_string()),
+            audio_sample_rate: Some(48000),
+            audio_channels: Some(2),
+            ..info(3840, 2160, 0)
+        }
+    }
+
+    #[test]
+    fn matching_clips_without_a_grade_are_joined_by_stream_copy() -> Result<()> {
+        let inputs = vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let infos = [chapter(60.0), chapter(20.0)];
+        let plain = VideoProcessor::new_multi(inputs.clone(), "out.mp4");
+        let planned = plain.pipeline(&infos)?.plan();
+        assert_eq!(planned.len(), 1);
+        assert!(
+            planned[0].argv.windows(2).any(|w| w == ["-c", "copy"]),
+            "{planned:?}"
+        );
+
+        // Any change to the content, or an explicit opt-out, re-encodes.
+        for p in [
+            VideoProcessor::new_multi(inputs.clone(), "out.mp4").saturation(1.2),
+            VideoProcessor::new_multi(inputs.clone(), "out.mp4").stream_copy(false),
+            // As does an explicit encoder setting.
+            VideoProcessor::new_multi(inputs.clone(), "out.mp4").bitrate(20),
+            VideoProcessor::new_multi(inputs.clone(), "out.mp4").codec("h265"),
+            VideoProcessor::new_multi(inputs.clone(), "out.mp4").quality(18),
+            VideoProcessor::new_multi(inputs.clone(), "out.mkv"),
+        ] {
+            let planned = p.pipeline(&infos)?.plan();
+            assert!(
+                planned[0].argv.iter().any(|a| a == "-filter_complex"),
+                "{planned:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn merged_chapters_are_joined_then_stabilized_as_one_segment() -> Result<()> {
+        let inputs = vec![
+            PathBuf::from("GX010042.MP4"),
+            PathBuf::from("GX020042.MP4"),
+            PathBuf::from("GX010043.MP4"),
+        ];
+        let infos = [chapter(60.0), chapter(20.0), chapter(30.0)];
+        let p = VideoProcessor::new_multi(inputs.clone(), "out.mp4")
+            .stabilize(true)
+            .merge_chapters(true);
+        let stages: Vec<String> = p
+            .pipeline(&infos)?
+            .plan()
+            .into_iter()
+            .map(|c| c.stage)
+            .collect();
+        assert_eq!(stages[0], "join chapters");
+        assert_eq!(stages.iter().filter(|s| s.starts_with("detect")).count(), 2);
+        assert!(stages.contains(&"detect 2/2".to_string()), "{stages:?}");
+
+        // Without the flag every file is its own segment; and a transition
+        // never lands inside a recording.
+        let p = VideoProcessor::new_multi(inputs.clone(), "out.mp4").stabilize(true);
+        assert_eq!(p.pipeline(&infos)?.plan()[0].stage, "grade 1/3");
+        let fade = Transition::xfade("fade", 0.5)?;
+        let p = VideoProcessor::new_multi(inputs, "out.mp4")
+            .merge_chapters(true)
+            .transition(fade.clone());
+        assert_eq!(
+            p.boundary_transitions(&[60.0, 20.0, 30.0], &p.chapter_groups(&infos))?,
+            vec![Transition::Cut, fade]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn canvas_policy_sets_the_stitch_frame_and_rate() -> Result<()> {
+        let inputs = vec![
+            PathBuf::from("phone.mp4"),
+            PathBuf::from("a.mp4"),
+            PathBuf::from("b.mp4"),
+        ];
+        let mut phone = info(1080, 1920, 0);
+        phone.frame_rate = Some("30/1".to_string());
+        phone.duration = 10.0;
+        let infos = [phone, chapter(10.0), chapter(10.0)];
+        let graph = |canvas: Canvas| -> Result<String> {
+            let planned = VideoProcessor::new_multi(inputs.clone(), "out.mp4")
+                .canvas(canvas)
+                .pipeline(&infos)?
+                .plan();
+            Ok(planned[0].argv.join(" "))
+        };
+        // The default keeps the smallest of each axis: here the phone's
+        // portrait frame, with the landscape clips shrunk into it.
+        let smallest = graph(Canvas::Smallest)?;
+        assert!(smallest.contains("pad=1080:1920"), "{smallest}");
+        let common = graph(Canvas::Common)?;
+        assert!(common.contains("pad=3840:2160"), "{common}");
+        assert!(common.contains("fps=30000/1001"), "{common}");
+        let first = graph(Canvas::First)?;
+        assert!(first.contains("pad=1080:1920") && first.contains("fps=30/1"));
+        let largest = graph(Canvas::Largest)?;
+        assert!(largest.contains("pad=3840:2160"), "{largest}");
+        assert!(graph(Canvas::Size(1920, 1080))?.contains("pad=1920:1080"));
+        Ok(())
+    }
+
+    #[test]
+    fn copy_mismatch_names_the_first_differing_property() {
+        assert_eq!(copy_mismatch(&[chapter(60.0), chapter(20.0)]), None);
+        let mut other = chapter(20.0);
+        other.frame_rate = Some("25/1".to_string());
+        assert_eq!(
+            copy_mismatch(&[chapter(60.0), other]).as_deref(),
+            Some("clip 2 differs in frame rate")
+        );
+        let mut mute = chapter(20.0);
+        mute.has_audio = false;
+        assert!(copy_mismatch(&[chapter(60.0), mute]).is_some());
+    }
+
     #[test]
     fn validate_speed_accepts_positive_finite_rejects_bad() {
         for ok in [1.0, 2.0, 0.5, 10.0] {
@@ -994,4 +2671,114 @@ mod tests {
         let result = VideoProcessor::new_multi(Vec::new(), "out.mp4").process();
         assert!(result.is_err(), "empty inputs should error, not panic");
     }
+
+    #[test]
+    fn lut_strength_mixes_the_lut_in_or_leaves_it_out() -> Result<()> {
+        let grade = |strength: f32| {
+            let built = VideoProcessor::new("in.mp4", "out.mp4")
+                .lut("grade.cube")
+                .lut_strength(strength)
+                .saturation(1.2)
+                .apply_grade(
+                    FFmpegCommand::new("in.mp4", "out.mp4"),
+                    &info(1920, 1080, 0),
+                    0.0,
+                    None,
+                )
+                .build();
+            built
+                .get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let mixed = grade(0.25);
+        assert!(
+            mixed.contains("[lutsrc][lutout]mix=inputs=2:weights='0.750 0.250',eq="),
+            "{mixed}"
+        );
+        let off = grade(0.0);
+        assert!(!off.contains("lut3d"), "{off}");
+
+        let p = VideoProcessor::new("in.mp4", "out.mp4").lut_strength(1.5);
+        assert!(matches!(
+            p.validate(),
+            Err(SpeedyError::InvalidParameter {
+                name: "LUT strength",
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_lut_fails_before_ffmpeg_and_picks_the_filter() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("speedy-lut-{pid}", pid = std::process::id()));
+        std::fs::create_dir_all(&dir).map_err(|e| SpeedyError::io("create dir", e))?;
+        let broken = dir.join("broken.cube");
+        std::fs::write(&broken, "LUT_3D_SIZE 2\n0 0 0\n1 0 0\n")
+            .map_err(|e| SpeedyError::io("write", e))?;
+        // The input does not exist either: the LUT is reported before any probe.
+        let result = VideoProcessor::new("in.mp4", "out.mp4")
+            .lut(&broken)
+            .process();
+        assert!(
+            matches!(&result, Err(SpeedyError::InvalidLut { line: None, message, .. })
+                if message.contains("expected 8 entries")),
+            "{result:?}"
+        );
+
+        let curve = dir.join("curve.cube");
+        std::fs::write(&curve, "LUT_1D_SIZE 2\n0 0 0\n1 1 1\n")
+            .map_err(|e| SpeedyError::io("write", e))?;
+        let p = VideoProcessor::new("in.mp4", "out.mp4").lut(&curve);
+        p.validate()?;
+        let built = p
+            .apply_grade(
+                FFmpegCommand::new("in.mp4", "out.mp4"),
+                &info(1920, 1080, 0),
+                0.0,
+                None,
+            )
+            .build();
+        let args: Vec<String> = built
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        assert!(
+            args.iter().any(|a| a.contains("lut1d=file='curve.cube'")),
+            "{args:?}"
+        );
+
+        std::fs::remove_dir_all(&dir).map_err(|e| SpeedyError::io("cleanup", e))?;
+        Ok(())
+    }
+
+    #[test]
+    fn cancelled_job_keeps_an_output_that_predates_it() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("speedy-cancel-{pid}", pid = std::process::id()));
+        std::fs::create_dir_all(&dir).map_err(|e| SpeedyError::io("create dir", e))?;
+        let existing = dir.join("existing.mp4");
+        std::fs::write(&existing, b"keep me").map_err(|e| SpeedyError::io("write", e))?;
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let result = VideoProcessor::new("in.mp4", &existing)
+            .cancellation(token)
+            .process();
+        assert!(matches!(result, Err(SpeedyError::Cancelled)), "{result:?}");
+        assert!(existing.exists(), "a pre-existing output must survive");
+
+        // A file written during the run is treated as partial and removed.
+        let started = SystemTime::now() - std::time::Duration::from_secs(1);
+        let partial = dir.join("partial.mp4");
+        std::fs::write(&partial, b"half").map_err(|e| SpeedyError::io("write", e))?;
+        VideoProcessor::new("in.mp4", &partial).remove_partial_output(started);
+        assert!(!partial.exists());
+
+        std::fs::remove_dir_all(&dir).map_err(|e| SpeedyError::io("cleanup", e))?;
+        Ok(())
+    }
 }